@@ -19,7 +19,7 @@ pub struct AppLauncher {
     matcher: SkimMatcherV2,
 }
 
-fn parse_desktop_file(path: &std::path::Path) -> Option<HashMap<String, String>> {
+pub fn parse_desktop_file(path: &std::path::Path) -> Option<HashMap<String, String>> {
     // Use mmap for faster file reading if possible, otherwise use standard read
     let content = std::fs::read_to_string(path).ok()?;
     let mut section = HashMap::with_capacity(16); // Pre-allocate capacity
@@ -1,39 +1,1642 @@
+use crate::config::CalculatorConfig;
+use crate::linalg::LinearAlgebra;
+use crate::solver::Solver;
+use crate::units::{Dimension, Units};
+use num_bigint::BigInt;
+use num_complex::Complex64;
+use num_integer::Integer;
+use num_rational::BigRational;
+use num_traits::{One, Signed, ToPrimitive, Zero};
+use std::collections::HashMap;
+
 pub struct Calculator;
 
+/// A calculator value: either an exact rational or a floating point approximation.
+///
+/// Integer and decimal literals start out exact and stay exact through `+ - * /`,
+/// integer powers and factorials. Anything transcendental (sin, ln, non-integer
+/// powers, ...) falls back to `f64`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Number {
+    Exact(BigRational),
+    Approx(f64),
+}
+
+/// Any result of an expression: a real number, a complex number, a vector/matrix
+/// of real numbers or a real number with units. `[5, 6]` is a vector and acts as a
+/// column in matrix products; quantities are stored in SI base units (see `units.rs`).
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Number(Number),
+    Complex(Complex64),
+    Vector(Vec<Number>),
+    Matrix(Vec<Vec<Number>>),
+    Quantity(Number, Dimension),
+}
+
+/// Result of evaluating an expression, ready for display
+#[derive(Debug, Clone, PartialEq)]
+pub struct Calculation {
+    pub value: String,                     // Main rendering (this is what gets copied)
+    pub alternatives: Vec<Representation>, // Fraction, scientific, hex, ...
+}
+
+/// Another way of writing the same result, e.g. ("fraction", "1/3")
+#[derive(Debug, Clone, PartialEq)]
+pub struct Representation {
+    pub label: String,
+    pub value: String,
+}
+
+/// A named constant; value and unit are calculator expressions ("6.62607015e-34", "J s")
+#[derive(Debug, Clone, PartialEq)]
+pub struct Constant {
+    pub name: String,
+    pub value: String,
+    pub unit: String,
+    pub description: String,
+    pub source: String,
+}
+
+/// Decimal and thousands separators for the configured locale
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct NumberFormat {
+    decimal: char,
+    group: char,
+}
+
+// Limits that keep a single keystroke from locking up the UI
+const MAX_EXACT_POW_BITS: u64 = 1_000_000;
+const MAX_EXACT_FACTORIAL: u64 = 5_000;
+const MAX_DISPLAY_DIGITS: usize = 1_000;
+
+// Built-in constants (name, value, unit, description, source), more can be added in config.toml
+const CONSTANTS: &[(&str, &str, &str, &str, &str)] = &[
+    ("c", "299792458", "m/s", "Speed of light in vacuum", "SI 2019, exact"),
+    ("h", "6.62607015e-34", "J s", "Planck constant", "SI 2019, exact"),
+    ("hbar", "6.62607015e-34 / (2 pi)", "J s", "Reduced Planck constant", "SI 2019, exact"),
+    ("G", "6.67430e-11", "m^3/(kg s^2)", "Newtonian constant of gravitation", "CODATA 2018"),
+    ("k_B", "1.380649e-23", "J/K", "Boltzmann constant", "SI 2019, exact"),
+    ("N_A", "6.02214076e23", "1/mol", "Avogadro constant", "SI 2019, exact"),
+    ("e_charge", "1.602176634e-19", "C", "Elementary charge", "SI 2019, exact"),
+    ("m_e", "9.1093837015e-31", "kg", "Electron mass", "CODATA 2018"),
+    ("m_p", "1.67262192369e-27", "kg", "Proton mass", "CODATA 2018"),
+    ("g0", "9.80665", "m/s^2", "Standard acceleration of gravity", "3rd CGPM 1901, exact"),
+    ("phi", "(1 + sqrt(5)) / 2", "", "Golden ratio", "Mathematics"),
+];
+
+// Functions that take a whole list of values, e.g. "sum 3, 5, 9"
+const LIST_FUNCTIONS: &[&str] = &[
+    "sum", "product", "prod", "mean", "avg", "average", "median", "mode", "stdev", "stdevp",
+    "var", "varp", "count", "min", "max", "range",
+];
+
+impl Number {
+    pub fn from_integer(n: i64) -> Self {
+        Number::Exact(BigRational::from_integer(BigInt::from(n)))
+    }
+
+    pub fn to_f64(&self) -> f64 {
+        match self {
+            Number::Exact(r) => r.to_f64().unwrap_or(f64::NAN),
+            Number::Approx(f) => *f,
+        }
+    }
+
+    pub(crate) fn is_zero(&self) -> bool {
+        match self {
+            Number::Exact(r) => r.is_zero(),
+            Number::Approx(f) => *f == 0.0,
+        }
+    }
+
+    /// The value as a big integer, if it is exactly one
+    pub(crate) fn as_integer(&self) -> Option<BigInt> {
+        match self {
+            Number::Exact(r) if r.is_integer() => Some(r.to_integer()),
+            Number::Approx(f) if f.is_finite() && f.fract() == 0.0 => {
+                BigRational::from_float(*f).map(|r| r.to_integer())
+            }
+            _ => None,
+        }
+    }
+
+    fn approx(self) -> Self {
+        Number::Approx(self.to_f64())
+    }
+}
+
+impl Value {
+    /// Complex results without an imaginary part become real again (i * i = -1)
+    fn from_complex(mut z: Complex64) -> Self {
+        // Rounding noise such as e^(i pi) = -1 + 1.2e-16i
+        let noise = z.norm() * 1e-14;
+        if z.im.abs() < noise {
+            z.im = 0.0;
+        }
+        if z.re.abs() < noise {
+            z.re = 0.0;
+        }
+        if z.im == 0.0 {
+            Value::Number(Number::Approx(z.re))
+        } else {
+            Value::Complex(z)
+        }
+    }
+
+    fn to_complex(&self) -> Option<Complex64> {
+        match self {
+            Value::Number(n) => Some(Complex64::new(n.to_f64(), 0.0)),
+            Value::Complex(z) => Some(*z),
+            _ => None,
+        }
+    }
+
+    fn approx(self) -> Self {
+        match self {
+            Value::Number(n) => Value::Number(n.approx()),
+            Value::Vector(v) => Value::Vector(v.into_iter().map(Number::approx).collect()),
+            Value::Matrix(m) => Value::Matrix(m.into_iter().map(|row| row.into_iter().map(Number::approx).collect()).collect()),
+            Value::Quantity(n, d) => Value::Quantity(n.approx(), d),
+            complex => complex,
+        }
+    }
+
+    fn is_finite(&self) -> bool {
+        match self {
+            Value::Number(n) => n.to_f64().is_finite() || matches!(n, Number::Exact(_)),
+            Value::Complex(z) => z.is_finite(),
+            Value::Vector(v) => v.iter().all(|n| Value::Number(n.clone()).is_finite()),
+            Value::Matrix(m) => m.iter().flatten().all(|n| Value::Number(n.clone()).is_finite()),
+            Value::Quantity(n, _) => Value::Number(n.clone()).is_finite(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Number(Number),
+    Ident(String),
+    Op(char),
+    LParen,
+    RParen,
+    LBracket,
+    RBracket,
+    Comma,
+}
+
+/// Parsed expression tree, shared with the equation solver and plotter
+#[derive(Debug, Clone)]
+pub(crate) enum Expr {
+    Number(Number),
+    Var(String),
+    Neg(Box<Expr>),
+    Binary(char, Box<Expr>, Box<Expr>),
+    Factorial(Box<Expr>),
+    Percent(Box<Expr>),
+    Call(String, Vec<Expr>),
+    Array(Vec<Expr>),
+}
+
 impl Calculator {
     pub fn evaluate(expression: &str) -> Result<f64, String> {
-        // Remove common calculator symbols and replace with proper operators
-        let cleaned = expression
-            .replace('×', "*")
-            .replace('÷', "/")
-            .replace('x', "*")
-            .replace('X', "*");
+        let value = Self::evaluate_number(expression, &CalculatorConfig::default())?;
+        Ok(value.to_f64())
+    }
+
+    /// Evaluate an expression that must give a real number, exact where possible
+    pub fn evaluate_number(expression: &str, config: &CalculatorConfig) -> Result<Number, String> {
+        match Self::evaluate_value(expression, config)? {
+            Value::Number(n) => Ok(n),
+            _ => Err("Result is not a real number".to_string()),
+        }
+    }
+
+    /// Evaluate an expression to a real, complex, vector or matrix value
+    pub fn evaluate_value(expression: &str, config: &CalculatorConfig) -> Result<Value, String> {
+        let expr = Self::parse(expression, config)?;
+        let expr = Self::resolve_constants(expr, &Self::constant_table(config));
+
+        let vars = HashMap::new();
+        let result = Self::eval_value(&expr, &vars)?;
+        let result = if config.exact { result } else { result.approx() };
+
+        if !result.is_finite() {
+            return Err("Result is not a number".to_string());
+        }
+        Ok(result)
+    }
 
-        // Try to evaluate the expression using meval
-        match meval::eval_str(&cleaned) {
-            Ok(result) => Ok(result),
-            Err(e) => Err(format!("Invalid expression: {}", e)),
+    /// Parse an expression into a tree without evaluating it
+    pub(crate) fn parse(expression: &str, config: &CalculatorConfig) -> Result<Expr, String> {
+        let decimal_comma = NumberFormat::from_config(config).decimal == ',';
+        let tokens = Self::tokenize(expression, decimal_comma)?;
+        let mut parser = Parser { tokens, pos: 0 };
+        let expr = parser.parse_expression()?;
+        if parser.pos < parser.tokens.len() {
+            return Err("Invalid expression: unexpected input".to_string());
         }
+        Ok(expr)
     }
 
-    pub fn is_calculation(query: &str) -> bool {
+    /// Evaluate and format an expression according to the calculator settings
+    pub fn calculate(expression: &str, config: &CalculatorConfig) -> Result<Calculation, String> {
+        // "solve x^2 - 5x + 6 = 0"
+        if Solver::is_solve_query(expression) {
+            return Solver::solve(expression, config);
+        }
+
+        // "deriv(x^3, x)" gives a formula rather than a number
+        if let Ok(Expr::Call(name, args)) = Self::parse(expression, config) {
+            if (name == "deriv" || name == "diff") && args.len() == 2 {
+                let Expr::Var(var) = &args[1] else {
+                    return Err("deriv() needs a variable as second argument".to_string());
+                };
+                let derivative = Solver::derivative(&args[0], var)?;
+                return Ok(Calculation {
+                    value: Solver::format_expr(&derivative),
+                    alternatives: Vec::new(),
+                });
+            }
+        }
+
+        // "c * 2 s in km"
+        if let Some((value, unit)) = Self::split_conversion(expression, config) {
+            let value = Self::evaluate_value(&value, config)?;
+            let target = Self::evaluate_value(&unit, config)?;
+            let converted = Units::convert(value, target).map_err(|e| Self::explain_units_error(e, expression))?;
+            return Ok(Units::attach(Self::format_number(&converted, config), unit.trim()));
+        }
+
+        // "what % is 12 of 48" / "12 is what % of 48"
+        if let Some((part, whole)) = Self::split_percentage_question(expression) {
+            let part = Self::evaluate_number(&part, config)?;
+            let whole = Self::evaluate_number(&whole, config)?;
+            if whole.is_zero() {
+                return Err("Division by zero".to_string());
+            }
+            let ratio = Self::apply_binary('/', part, whole)?;
+            let percent = Self::apply_binary('*', ratio, Number::from_integer(100))?;
+            let mut calculation = Self::format_number(&percent, config);
+            calculation.value.push('%');
+            return Ok(calculation);
+        }
+
+        let value = Self::evaluate_value(expression, config).map_err(|e| Self::explain_units_error(e, expression))?;
+        Ok(Self::format_value(&value, config))
+    }
+
+    /// "60 km/h" divides by the Planck constant; say so when units don't fit because of it
+    fn explain_units_error(error: String, expression: &str) -> String {
+        let uses_h = expression.split(|c: char| !c.is_alphanumeric() && c != '_').any(|word| word == "h");
+        if uses_h && (error.starts_with("Cannot convert") || error.starts_with("Incompatible units")) {
+            format!("{} ('h' is the Planck constant, write 'hour' or 'kmh')", error)
+        } else {
+            error
+        }
+    }
+
+    /// A constant's value as listed by "const": tiny and huge ones in scientific notation,
+    /// so none of their digits are cut off
+    pub fn constant_value(constant: &Constant, config: &CalculatorConfig) -> String {
+        let Ok(value) = Self::evaluate_number(&constant.value, config) else {
+            return constant.value.clone();
+        };
+        let magnitude = value.to_f64().abs();
+        let notation = if magnitude != 0.0 && !(1e-3..1e9).contains(&magnitude) {
+            "scientific".to_string()
+        } else {
+            config.notation.clone()
+        };
+        Self::format_number(&value, &CalculatorConfig { notation, ..config.clone() }).value
+    }
+
+    pub fn is_calculation(query: &str, config: &CalculatorConfig) -> bool {
         // Simple heuristic: if it contains numbers and math operators, it's likely a calculation
+        if Solver::is_solve_query(query) {
+            return true;
+        }
+
         let has_number = query.chars().any(|c| c.is_ascii_digit());
-        let has_operator = query.chars().any(|c| "+-*/×÷xX^()".contains(c));
-        
-        has_number && (has_operator || query.trim().parse::<f64>().is_ok())
+        let has_operator = query.chars().any(|c| "+-*/×÷xX^()!%".contains(c))
+            || query.contains(" mod ");
+
+        // "sum 3, 5, 9" pasted without parentheses
+        let first_word = query.split_whitespace().next().unwrap_or("").to_lowercase();
+        let is_list = LIST_FUNCTIONS.contains(&first_word.as_str());
+
+        // "5 km in mi"
+        let is_conversion = Self::split_conversion(query, config).is_some();
+
+        has_number && (has_operator || is_list || is_conversion || query.trim().parse::<f64>().is_ok())
+    }
+
+    /// Split "A in B" at the last top-level " in " into (A, B) when B is a unit ("3 in 4" is not
+    /// a conversion)
+    fn split_conversion(expression: &str, config: &CalculatorConfig) -> Option<(String, String)> {
+        let mut depth = 0;
+        let mut split = None;
+        for (i, c) in expression.char_indices() {
+            match c {
+                '(' | '[' => depth += 1,
+                ')' | ']' => depth -= 1,
+                _ if depth == 0 && expression[i..].starts_with(" in ") => split = Some(i),
+                _ => {}
+            }
+        }
+        let i = split?;
+        let (value, unit) = (expression[..i].trim(), expression[i + 4..].trim());
+        if value.is_empty() || !matches!(Self::evaluate_value(unit, config), Ok(Value::Quantity(..))) {
+            return None;
+        }
+        Some((value.to_string(), unit.to_string()))
+    }
+
+    /// Built-in constants followed by the ones from config.toml
+    pub fn constants(config: &CalculatorConfig) -> Vec<Constant> {
+        let builtin = CONSTANTS.iter().map(|(name, value, unit, description, source)| Constant {
+            name: name.to_string(),
+            value: value.to_string(),
+            unit: unit.to_string(),
+            description: description.to_string(),
+            source: source.to_string(),
+        });
+        let custom = config.constants.iter().map(|c| Constant {
+            name: c.name.clone(),
+            value: c.value.clone(),
+            unit: c.unit.clone(),
+            description: c.description.clone(),
+            source: "config.toml".to_string(),
+        });
+        builtin.chain(custom).collect()
+    }
+
+    /// Constants whose name or description contains every word of the query ("const planck")
+    pub fn find_constants(query: &str, config: &CalculatorConfig) -> Vec<Constant> {
+        let query = query.to_lowercase();
+        let words: Vec<&str> = query.split_whitespace().collect();
+        let mut found: Vec<Constant> = Vec::new();
+        // Later definitions (config.toml) replace built-in ones of the same name
+        for constant in Self::constants(config).into_iter().rev() {
+            let text = format!("{} {}", constant.name, constant.description).to_lowercase();
+            if words.iter().all(|word| text.contains(word)) && !found.iter().any(|c| c.name == constant.name) {
+                found.push(constant);
+            }
+        }
+        found.reverse();
+        found
+    }
+
+    /// Constant names mapped to their value times unit; invalid config entries are left out
+    fn constant_table(config: &CalculatorConfig) -> HashMap<String, Expr> {
+        Self::constants(config)
+            .into_iter()
+            .filter_map(|constant| {
+                let value = Self::parse(&constant.value, config).ok()?;
+                let expr = if constant.unit.trim().is_empty() {
+                    value
+                } else {
+                    Expr::Binary('*', Box::new(value), Box::new(Self::parse(&constant.unit, config).ok()?))
+                };
+                Some((constant.name, expr))
+            })
+            .collect()
+    }
+
+    fn resolve_constants(expr: Expr, constants: &HashMap<String, Expr>) -> Expr {
+        let resolve = |e: Box<Expr>| Box::new(Self::resolve_constants(*e, constants));
+        match expr {
+            Expr::Var(name) => constants.get(&name).cloned().unwrap_or(Expr::Var(name)),
+            Expr::Neg(inner) => Expr::Neg(resolve(inner)),
+            Expr::Binary(op, lhs, rhs) => Expr::Binary(op, resolve(lhs), resolve(rhs)),
+            Expr::Factorial(inner) => Expr::Factorial(resolve(inner)),
+            Expr::Percent(inner) => Expr::Percent(resolve(inner)),
+            Expr::Call(name, args) => {
+                Expr::Call(name, args.into_iter().map(|arg| Self::resolve_constants(arg, constants)).collect())
+            }
+            Expr::Array(items) => {
+                Expr::Array(items.into_iter().map(|item| Self::resolve_constants(item, constants)).collect())
+            }
+            number @ Expr::Number(_) => number,
+        }
+    }
+
+    /// Split "what % is A of B" and "A is what % of B" into (A, B)
+    fn split_percentage_question(expression: &str) -> Option<(String, String)> {
+        let lower = expression.trim().to_lowercase();
+        let lower = lower.trim_end_matches('?');
+
+        for what in ["what %", "what percent", "what percentage"] {
+            let question = format!("{} is ", what);
+            if let Some(rest) = lower.strip_prefix(&question) {
+                let (part, whole) = rest.rsplit_once(" of ")?;
+                return Some((part.to_string(), whole.to_string()));
+            }
+            let infix = format!(" is {} of ", what);
+            if let Some((part, whole)) = lower.split_once(&infix) {
+                return Some((part.to_string(), whole.to_string()));
+            }
+        }
+        None
     }
 
     pub fn format_result(result: f64) -> String {
-        // Format as integer if it's a whole number, otherwise keep decimals
-        if result.fract() == 0.0 {
-            format!("{}", result as i64)
+        Self::format_number(&Number::Approx(result), &CalculatorConfig::default()).value
+    }
+
+    /// Render a value in the configured notation and locale, plus alternative renderings
+    pub fn format_number(value: &Number, config: &CalculatorConfig) -> Calculation {
+        let format = NumberFormat::from_config(config);
+        let precision = config.precision;
+
+        let decimal = Self::plain_decimal(value, precision);
+        let scientific = Self::scientific(value, precision);
+        let primary = match config.notation.as_str() {
+            "scientific" => scientific.clone(),
+            "engineering" => Self::engineering(&scientific),
+            // Auto: decimal unless that would be unreadable or cut off most digits
+            _ => match &decimal {
+                Some(d) if !Self::loses_digits(value, d) => d.clone(),
+                _ => scientific.clone(),
+            },
+        };
+        let value_text = format.localize(&primary, config.grouping);
+
+        let mut alternatives = Vec::new();
+        let mut push = |label: &str, text: String| {
+            if text != value_text && !alternatives.iter().any(|r: &Representation| r.value == text) {
+                alternatives.push(Representation { label: label.to_string(), value: text });
+            }
+        };
+
+        if let Number::Exact(r) = value {
+            // Fraction when the decimal expansion had to be cut off
+            if !r.is_integer() && !Self::is_terminating(r.denom()) && r.denom().bits() <= 128 {
+                push("fraction", format!("{}/{}", r.numer(), r.denom()));
+            }
+        }
+
+        let magnitude = value.to_f64().abs();
+        if primary != scientific && (magnitude >= 1e6 || (magnitude > 0.0 && magnitude < 1e-3)) {
+            push("scientific", format.localize(&scientific, false));
+        }
+        // A decimal that lost the value's digits isn't worth copying
+        if let Some(d) = &decimal {
+            if primary != *d && !Self::loses_digits(value, d) {
+                push("decimal", format.localize(d, config.grouping));
+            }
+        }
+
+        if let Some(n) = value.as_integer() {
+            if n.abs() >= BigInt::from(10) && n.bits() <= 256 {
+                let sign = if n.is_negative() { "-" } else { "" };
+                push("hex", format!("{}0x{}", sign, n.abs().to_str_radix(16).to_uppercase()));
+            }
+        } else if let Some(rounded) = Self::plain_decimal(value, precision.min(2)) {
+            if rounded.trim_start_matches('-').chars().any(|c| c.is_ascii_digit() && c != '0') {
+                push("rounded", format.localize(&rounded, config.grouping));
+            }
+        }
+
+        // Unformatted digits for pasting into code and spreadsheets
+        if primary != value_text {
+            push("plain", primary.clone());
+        }
+
+        alternatives.truncate(4);
+        Calculation { value: value_text, alternatives }
+    }
+
+    /// Render any value; complex numbers and matrices reuse the number formatting per part
+    pub fn format_value(value: &Value, config: &CalculatorConfig) -> Calculation {
+        let number = |n: &Number| Self::format_number(n, config).value;
+        // "[1,5, 2]" would be ambiguous with a decimal comma
+        let separator = if NumberFormat::from_config(config).decimal == ',' { "; " } else { ", " };
+        let vector = |v: &[Number]| format!("[{}]", v.iter().map(number).collect::<Vec<_>>().join(separator));
+
+        match value {
+            Value::Number(n) => Self::format_number(n, config),
+            Value::Complex(z) => {
+                let imaginary = Self::format_number(&Number::Approx(z.im.abs()), config).value;
+                let imaginary = if imaginary == "1" { String::new() } else { imaginary };
+                let text = if z.re == 0.0 {
+                    format!("{}{}i", if z.im < 0.0 { "-" } else { "" }, imaginary)
+                } else {
+                    let sign = if z.im < 0.0 { '-' } else { '+' };
+                    format!("{} {} {}i", number(&Number::Approx(z.re)), sign, imaginary)
+                };
+                let (r, theta) = z.to_polar();
+                let alternatives = vec![
+                    Representation {
+                        label: "polar".to_string(),
+                        value: format!("{} * e^({}i)", number(&Number::Approx(r)), number(&Number::Approx(theta))),
+                    },
+                    Representation {
+                        label: "abs".to_string(),
+                        value: number(&Number::Approx(r)),
+                    },
+                ];
+                Calculation { value: text, alternatives }
+            }
+            Value::Vector(v) => Calculation { value: vector(v), alternatives: Vec::new() },
+            Value::Matrix(m) => {
+                let rows: Vec<String> = m.iter().map(|row| vector(row)).collect();
+                // One row per line below the result for easier reading
+                let alternatives = if m.len() > 1 {
+                    rows.iter()
+                        .enumerate()
+                        .take(4)
+                        .map(|(i, row)| Representation { label: format!("row {}", i + 1), value: row.clone() })
+                        .collect()
+                } else {
+                    Vec::new()
+                };
+                Calculation { value: format!("[{}]", rows.join(separator)), alternatives }
+            }
+            Value::Quantity(n, d) => Units::attach(Self::format_number(n, config), &Units::unit_name(d)),
+        }
+    }
+
+    /// Decimal expansion with `precision` decimals and '.' as separator ("-1234.5").
+    /// None when the number is too large to print digit by digit.
+    fn plain_decimal(value: &Number, precision: usize) -> Option<String> {
+        match value {
+            Number::Exact(r) => {
+                if r.is_integer() {
+                    let digits = r.to_integer().to_string();
+                    return (digits.trim_start_matches('-').len() <= MAX_DISPLAY_DIGITS).then_some(digits);
+                }
+                if r.abs() >= BigRational::from_integer(BigInt::from(10).pow(MAX_DISPLAY_DIGITS as u32)) {
+                    return None;
+                }
+                Some(Self::expand_rational(r, precision))
+            }
+            Number::Approx(f) => {
+                if f.abs() >= 1e21 {
+                    None
+                } else if f.fract() == 0.0 {
+                    // Whole number: print every digit instead of casting through i64
+                    Some(format!("{:.0}", f))
+                } else {
+                    // Round to reasonable precision
+                    Some(
+                        format!("{:.*}", precision, f)
+                            .trim_end_matches('0')
+                            .trim_end_matches('.')
+                            .to_string(),
+                    )
+                }
+            }
+        }
+    }
+
+    /// Expand a rational to `precision` decimal places, rounding half away from zero
+    fn expand_rational(r: &BigRational, precision: usize) -> String {
+        let negative = r.is_negative();
+        let abs = r.abs();
+        let scale = BigInt::from(10).pow(precision as u32);
+        let scaled = abs.numer() * &scale;
+        let (quotient, remainder) = scaled.div_rem(abs.denom());
+        let quotient = if remainder * 2 >= *abs.denom() { quotient + 1 } else { quotient };
+
+        let (int_part, frac_part) = quotient.div_rem(&scale);
+        let frac_digits = format!("{:0>width$}", frac_part.to_string(), width = precision);
+        let frac_digits = frac_digits.trim_end_matches('0');
+        let mut value = int_part.to_string();
+        if !frac_digits.is_empty() {
+            value.push('.');
+            value.push_str(frac_digits);
+        }
+        if negative && value.chars().any(|c| c.is_ascii_digit() && c != '0') {
+            value.insert(0, '-');
+        }
+        value
+    }
+
+    /// Whether a small value's rounded decimal is 0 or noticeably off, as
+    /// G = 6.6743e-11 would be at "0.0000000001"
+    fn loses_digits(value: &Number, decimal: &str) -> bool {
+        if value.is_zero() {
+            return false;
+        }
+        if decimal.trim_start_matches('-').chars().all(|c| c == '0' || c == '.') {
+            return true;
+        }
+        let exact = value.to_f64();
+        let shown = decimal.parse::<f64>().unwrap_or(exact);
+        ((shown - exact) / exact).abs() > 1e-6
+    }
+
+    /// Scientific notation with '.' as separator ("1.2345e-7")
+    fn scientific(value: &Number, precision: usize) -> String {
+        let (negative, mantissa, exponent) = match value {
+            _ if value.is_zero() => return "0".to_string(),
+            Number::Exact(r) => {
+                let abs = r.abs();
+                let ten = BigRational::from_integer(BigInt::from(10));
+                // Estimate the exponent from the digit counts, then correct it
+                let mut exponent = abs.numer().to_string().len() as i32 - abs.denom().to_string().len() as i32;
+                let mut mantissa = &abs / num_traits::Pow::pow(&ten, exponent);
+                while mantissa >= ten {
+                    mantissa /= &ten;
+                    exponent += 1;
+                }
+                while mantissa < BigRational::one() {
+                    mantissa *= &ten;
+                    exponent -= 1;
+                }
+                let mut digits = Self::expand_rational(&mantissa, precision);
+                if digits.starts_with("10") {
+                    // Rounding carried over, e.g. 9.99999999999 -> 10
+                    digits = "1".to_string();
+                    exponent += 1;
+                }
+                (r.is_negative(), digits, exponent)
+            }
+            Number::Approx(f) => {
+                let formatted = format!("{:.*e}", precision, f.abs());
+                let (mantissa, exponent) = formatted.split_once('e').unwrap_or((&formatted, "0"));
+                let mantissa = if mantissa.contains('.') {
+                    mantissa.trim_end_matches('0').trim_end_matches('.')
+                } else {
+                    mantissa
+                };
+                (*f < 0.0, mantissa.to_string(), exponent.parse().unwrap_or(0))
+            }
+        };
+        format!("{}{}e{}", if negative { "-" } else { "" }, mantissa, exponent)
+    }
+
+    /// Rewrite scientific notation so the exponent is a multiple of 3 ("12.5e3")
+    fn engineering(scientific: &str) -> String {
+        let Some((mantissa, exponent)) = scientific.split_once('e') else {
+            return scientific.to_string();
+        };
+        let exponent: i32 = exponent.parse().unwrap_or(0);
+        let shift = exponent.rem_euclid(3) as usize;
+        if shift == 0 {
+            return scientific.to_string();
+        }
+
+        let (sign, mantissa) = match mantissa.strip_prefix('-') {
+            Some(rest) => ("-", rest),
+            None => ("", mantissa),
+        };
+        let (int_part, frac_part) = mantissa.split_once('.').unwrap_or((mantissa, ""));
+        let frac_padded = format!("{:0<width$}", frac_part, width = shift);
+        let (moved, rest) = frac_padded.split_at(shift);
+        let mut result = format!("{}{}{}", sign, int_part, moved);
+        if !rest.is_empty() {
+            result.push('.');
+            result.push_str(rest);
+        }
+        format!("{}e{}", result, exponent - shift as i32)
+    }
+
+    /// A denominator only made of 2s and 5s gives a finite decimal expansion
+    fn is_terminating(denom: &BigInt) -> bool {
+        let mut d = denom.clone();
+        for p in [2u32, 5] {
+            let p = BigInt::from(p);
+            while (&d % &p).is_zero() {
+                d /= &p;
+            }
+        }
+        d.is_one()
+    }
+
+    fn tokenize(expression: &str, decimal_comma: bool) -> Result<Vec<Token>, String> {
+        let chars: Vec<char> = expression.chars().collect();
+        let mut tokens = Vec::new();
+        let mut i = 0;
+        // Per open parenthesis or bracket, whether commas in it separate arguments or elements.
+        // A list separated by semicolons keeps decimal commas: "max(1; 2,5)".
+        let mut lists: Vec<bool> = Vec::new();
+
+        while i < chars.len() {
+            let c = chars[i];
+            if c.is_whitespace() && c != '\t' && c != '\n' {
+                i += 1;
+                continue;
+            }
+
+            if c.is_ascii_digit() || (c == '.' && chars.get(i + 1).is_some_and(|d| d.is_ascii_digit())) {
+                let start = i;
+                while i < chars.len() {
+                    // "3,5" is a decimal number in locales that use a decimal comma, except
+                    // between arguments: "max(1,5)" and "[1,2]" keep two values
+                    let comma_decimal = decimal_comma
+                        && lists.last() != Some(&true)
+                        && chars[i] == ','
+                        && chars.get(i + 1).is_some_and(|d| d.is_ascii_digit());
+                    if !(chars[i].is_ascii_digit() || chars[i] == '.' || chars[i] == '_' || comma_decimal) {
+                        break;
+                    }
+                    i += 1;
+                }
+                // Scientific notation: 1e5, 2.5E-3
+                if i < chars.len() && (chars[i] == 'e' || chars[i] == 'E') {
+                    let mut j = i + 1;
+                    if j < chars.len() && (chars[j] == '+' || chars[j] == '-') {
+                        j += 1;
+                    }
+                    if j < chars.len() && chars[j].is_ascii_digit() {
+                        i = j;
+                        while i < chars.len() && chars[i].is_ascii_digit() {
+                            i += 1;
+                        }
+                    }
+                }
+                let literal: String = chars[start..i]
+                    .iter()
+                    .filter(|c| **c != '_')
+                    .map(|c| if *c == ',' { '.' } else { *c })
+                    .collect();
+                // An exponent too large to expand exactly ("1e999999") gives a float
+                let number = match Self::parse_literal(&literal) {
+                    Ok(n) => Number::Exact(n),
+                    Err(e) => Number::Approx(literal.parse().map_err(|_| e)?),
+                };
+                tokens.push(Token::Number(number));
+                continue;
+            }
+
+            if c.is_alphabetic() || c == '_' {
+                let start = i;
+                while i < chars.len() && (chars[i].is_alphanumeric() || chars[i] == '_') {
+                    i += 1;
+                }
+                let ident: String = chars[start..i].iter().collect();
+
+                // "3 x 4" and "3x4" mean multiplication, a lone x is a variable
+                if ident == "x" || ident == "X" {
+                    let after_operand = matches!(tokens.last(), Some(Token::Number(_)) | Some(Token::RParen));
+                    let next = chars[i..].iter().find(|c| !c.is_whitespace());
+                    if after_operand && next.is_some_and(|n| n.is_ascii_digit() || *n == '.') {
+                        tokens.push(Token::Op('*'));
+                        continue;
+                    }
+                }
+
+                tokens.push(Token::Ident(ident));
+                continue;
+            }
+
+            let token = match c {
+                '+' | '-' | '*' | '/' | '^' | '!' | '%' => Token::Op(c),
+                '×' | '·' => Token::Op('*'),
+                '÷' => Token::Op('/'),
+                '−' => Token::Op('-'),
+                '(' => {
+                    let call = matches!(tokens.last(), Some(Token::Ident(_)));
+                    lists.push(call && !Self::separated_by_semicolons(&chars[i + 1..]));
+                    Token::LParen
+                }
+                '[' => {
+                    lists.push(!Self::separated_by_semicolons(&chars[i + 1..]));
+                    Token::LBracket
+                }
+                ')' | ']' => {
+                    lists.pop();
+                    if c == ')' { Token::RParen } else { Token::RBracket }
+                }
+                // Tabs, newlines and semicolons separate values pasted from spreadsheets
+                ',' | ';' | '\t' | '\n' => Token::Comma,
+                _ => return Err(format!("Invalid expression: unexpected character '{}'", c)),
+            };
+            tokens.push(token);
+            i += 1;
+        }
+
+        // Treat "**" as power, and collapse empty cells ("3\t\t5") into one separator
+        let mut merged: Vec<Token> = Vec::with_capacity(tokens.len());
+        for token in tokens {
+            if token == Token::Op('*') && merged.last() == Some(&Token::Op('*')) {
+                merged.pop();
+                merged.push(Token::Op('^'));
+            } else if token == Token::Comma
+                && matches!(merged.last(), None | Some(Token::Comma) | Some(Token::LParen) | Some(Token::LBracket))
+            {
+                continue;
+            } else if matches!(token, Token::RParen | Token::RBracket) && merged.last() == Some(&Token::Comma) {
+                merged.pop();
+                merged.push(token);
+            } else {
+                merged.push(token);
+            }
+        }
+        if merged.last() == Some(&Token::Comma) {
+            merged.pop();
+        }
+
+        Ok(merged)
+    }
+
+    /// Whether the list starting at `rest`, just after its opening parenthesis or bracket,
+    /// has a semicolon of its own before it closes
+    fn separated_by_semicolons(rest: &[char]) -> bool {
+        let mut depth = 0;
+        for c in rest {
+            match c {
+                '(' | '[' => depth += 1,
+                ')' | ']' if depth == 0 => return false,
+                ')' | ']' => depth -= 1,
+                ';' if depth == 0 => return true,
+                _ => {}
+            }
+        }
+        false
+    }
+
+    /// Parse a decimal literal (optionally with exponent) into an exact rational; fails for
+    /// a power of ten past `MAX_EXACT_POW_BITS`
+    pub(crate) fn parse_literal(literal: &str) -> Result<BigRational, String> {
+        let invalid = || format!("Invalid expression: bad number '{}'", literal);
+
+        let (mantissa, exponent) = match literal.find(['e', 'E']) {
+            Some(pos) => (&literal[..pos], literal[pos + 1..].parse::<i32>().map_err(|_| invalid())?),
+            None => (literal, 0),
+        };
+
+        let (int_part, frac_part) = mantissa.split_once('.').unwrap_or((mantissa, ""));
+        if frac_part.contains('.') {
+            return Err(invalid());
+        }
+        let digits = format!("{}{}", int_part, frac_part);
+        let numer: BigInt = if digits.is_empty() { BigInt::zero() } else { digits.parse().map_err(|_| invalid())? };
+
+        let exponent = i64::from(exponent) - frac_part.len() as i64;
+        // log2(10) < 10/3 bits per decimal digit
+        if exponent.unsigned_abs().saturating_mul(10) / 3 > MAX_EXACT_POW_BITS {
+            return Err(format!("Number too large to be exact: '{}'", literal));
+        }
+        let ten = BigInt::from(10);
+        let value = if exponent >= 0 {
+            BigRational::from_integer(numer * ten.pow(exponent as u32))
+        } else {
+            BigRational::new(numer, ten.pow((-exponent) as u32))
+        };
+        Ok(value)
+    }
+
+    /// Names that evaluate on their own and are never unknowns
+    pub(crate) fn is_constant(name: &str) -> bool {
+        matches!(name, "pi" | "π" | "tau" | "τ" | "e" | "i")
+    }
+
+    /// Evaluate to a real number, for callers that can't handle complex values or matrices
+    pub(crate) fn eval(expr: &Expr, vars: &HashMap<String, Number>) -> Result<Number, String> {
+        match Self::eval_value(expr, vars)? {
+            Value::Number(n) => Ok(n),
+            _ => Err("Expected a real number".to_string()),
+        }
+    }
+
+    pub(crate) fn eval_value(expr: &Expr, vars: &HashMap<String, Number>) -> Result<Value, String> {
+        match expr {
+            Expr::Number(n) => Ok(Value::Number(n.clone())),
+            Expr::Var(name) => {
+                if let Some(value) = vars.get(name) {
+                    return Ok(Value::Number(value.clone()));
+                }
+                match name.as_str() {
+                    "pi" | "π" => Ok(Value::Number(Number::Approx(std::f64::consts::PI))),
+                    "tau" | "τ" => Ok(Value::Number(Number::Approx(std::f64::consts::TAU))),
+                    "e" => Ok(Value::Number(Number::Approx(std::f64::consts::E))),
+                    "i" => Ok(Value::Complex(Complex64::i())),
+                    _ => Units::lookup(name).ok_or_else(|| format!("Unknown variable '{}'", name)),
+                }
+            }
+            Expr::Neg(inner) => {
+                let value = Self::eval_value(inner, vars)?;
+                Self::apply_value('*', Value::Number(Number::from_integer(-1)), value)
+            }
+            Expr::Binary(op, lhs, rhs) => {
+                let a = Self::eval_value(lhs, vars)?;
+                let b = Self::eval_value(rhs, vars)?;
+                // Everyday calculator semantics: "80 + 15%" is 80 plus 15% of 80
+                if let (Value::Number(a), Value::Number(b)) = (&a, &b) {
+                    if matches!(op, '+' | '-') && matches!(**rhs, Expr::Percent(_)) {
+                        let share = Self::apply_binary('*', a.clone(), b.clone())?;
+                        return Ok(Value::Number(Self::apply_binary(*op, a.clone(), share)?));
+                    }
+                }
+                Self::apply_value(*op, a, b)
+            }
+            Expr::Factorial(inner) => Ok(Value::Number(Self::factorial(Self::eval(inner, vars)?)?)),
+            Expr::Percent(inner) => {
+                let value = Self::eval_value(inner, vars)?;
+                Self::apply_value('/', value, Value::Number(Number::from_integer(100)))
+            }
+            // Calculus helpers get the unevaluated expression
+            Expr::Call(name, args) if matches!(name.as_str(), "deriv" | "diff" | "integrate" | "int") => {
+                Ok(Value::Number(Solver::call_calculus(name, args, vars)?))
+            }
+            Expr::Call(name, args) => {
+                let args = args
+                    .iter()
+                    .map(|arg| Self::eval_value(arg, vars))
+                    .collect::<Result<Vec<_>, _>>()?;
+                Self::call_value(name, args)
+            }
+            Expr::Array(items) => {
+                let items = items
+                    .iter()
+                    .map(|item| Self::eval_value(item, vars))
+                    .collect::<Result<Vec<_>, _>>()?;
+                LinearAlgebra::from_items(items)
+            }
+        }
+    }
+
+    /// Binary operators on any value, real numbers go through `apply_binary`
+    fn apply_value(op: char, a: Value, b: Value) -> Result<Value, String> {
+        match (a, b) {
+            (Value::Number(a), Value::Number(b)) => Ok(Value::Number(Self::apply_binary(op, a, b)?)),
+            (a @ Value::Quantity(..), b) | (a, b @ Value::Quantity(..)) => Units::apply_binary(op, a, b),
+            (a @ (Value::Vector(_) | Value::Matrix(_)), b) | (a, b @ (Value::Vector(_) | Value::Matrix(_))) => {
+                LinearAlgebra::apply_binary(op, a, b)
+            }
+            (a, b) => {
+                let (x, y) = (a.to_complex().unwrap(), b.to_complex().unwrap());
+                let z = match op {
+                    '+' => x + y,
+                    '-' => x - y,
+                    '*' => x * y,
+                    '/' if y == Complex64::new(0.0, 0.0) => return Err("Division by zero".to_string()),
+                    '/' => x / y,
+                    // Integer powers by repeated multiplication keep i^2 = -1 exact
+                    '^' => match &b {
+                        Value::Number(n) => match n.as_integer().and_then(|n| n.to_i32()) {
+                            Some(n) => x.powi(n),
+                            None => x.powc(y),
+                        },
+                        _ => x.powc(y),
+                    },
+                    _ => return Err("This operator needs real numbers".to_string()),
+                };
+                Ok(Value::from_complex(z))
+            }
+        }
+    }
+
+    /// Function calls on any value: matrix functions, complex functions, then real ones
+    fn call_value(name: &str, args: Vec<Value>) -> Result<Value, String> {
+        if args.iter().any(|a| matches!(a, Value::Quantity(..))) {
+            return Units::call_function(name, args);
+        }
+        if args.iter().any(|a| matches!(a, Value::Vector(_) | Value::Matrix(_))) {
+            // "mean([1, 2, 3])" works on the vector's entries
+            if let [Value::Vector(v)] = args.as_slice() {
+                if LIST_FUNCTIONS.contains(&name) {
+                    return Ok(Value::Number(Self::call_function(name, v.clone())?));
+                }
+            }
+            return LinearAlgebra::call_function(name, args);
+        }
+
+        let complex = args.iter().any(|a| matches!(a, Value::Complex(_)));
+        if let [arg] = args.as_slice() {
+            let z = arg.to_complex().unwrap();
+            match name {
+                "re" | "real" => return Ok(Value::Number(Number::Approx(z.re))),
+                "im" | "imag" => return Ok(Value::Number(Number::Approx(z.im))),
+                "arg" | "angle" | "phase" => return Ok(Value::Number(Number::Approx(z.arg()))),
+                "conj" => return Ok(Value::from_complex(z.conj())),
+                "abs" if complex => return Ok(Value::Number(Number::Approx(z.norm()))),
+                // sqrt(-4) = 2i, ln(-1) = pi i
+                "sqrt" | "ln" | "log" | "log10" if z.re < 0.0 || complex => {
+                    let result = match name {
+                        "sqrt" => z.sqrt(),
+                        "ln" => z.ln(),
+                        _ => z.log10(),
+                    };
+                    return Ok(Value::from_complex(result));
+                }
+                "exp" | "sin" | "cos" | "tan" | "sinh" | "cosh" | "tanh" if complex => {
+                    let result = match name {
+                        "exp" => z.exp(),
+                        "sin" => z.sin(),
+                        "cos" => z.cos(),
+                        "tan" => z.tan(),
+                        "sinh" => z.sinh(),
+                        "cosh" => z.cosh(),
+                        _ => z.tanh(),
+                    };
+                    return Ok(Value::from_complex(result));
+                }
+                _ => {}
+            }
+        }
+        if complex {
+            return Err(format!("{}() needs real numbers", name));
+        }
+
+        let numbers = args
+            .into_iter()
+            .map(|a| match a {
+                Value::Number(n) => n,
+                _ => unreachable!(),
+            })
+            .collect();
+        Ok(Value::Number(Self::call_function(name, numbers)?))
+    }
+
+    pub(crate) fn apply_binary(op: char, a: Number, b: Number) -> Result<Number, String> {
+        match op {
+            '^' => return Self::pow(a, b),
+            'm' => return Self::modulo(a, b),
+            // "15% off 80" = 80 - 15% of 80
+            'f' => {
+                let share = Self::apply_binary('*', a, b.clone())?;
+                return Self::apply_binary('-', b, share);
+            }
+            _ => {}
+        }
+
+        match (a, b) {
+            (Number::Exact(x), Number::Exact(y)) => match op {
+                '+' => Ok(Number::Exact(x + y)),
+                '-' => Ok(Number::Exact(x - y)),
+                '*' => Ok(Number::Exact(x * y)),
+                '/' => {
+                    if y.is_zero() {
+                        Err("Division by zero".to_string())
+                    } else {
+                        Ok(Number::Exact(x / y))
+                    }
+                }
+                _ => Err(format!("Unknown operator '{}'", op)),
+            },
+            (a, b) => {
+                let (x, y) = (a.to_f64(), b.to_f64());
+                match op {
+                    '+' => Ok(Number::Approx(Self::denoise(x + y, x.abs().max(y.abs())))),
+                    '-' => Ok(Number::Approx(Self::denoise(x - y, x.abs().max(y.abs())))),
+                    '*' => Ok(Number::Approx(x * y)),
+                    '/' => Ok(Number::Approx(x / y)),
+                    _ => Err(format!("Unknown operator '{}'", op)),
+                }
+            }
+        }
+    }
+
+    fn pow(base: Number, exponent: Number) -> Result<Number, String> {
+        if let (Number::Exact(b), Some(e)) = (&base, exponent.as_integer()) {
+            // Keep integer powers exact as long as the result stays a sane size
+            let base_bits = b.numer().bits().max(b.denom().bits()).max(1);
+            let exact_ok = e
+                .abs()
+                .to_u64()
+                .is_some_and(|e| base_bits.saturating_mul(e) <= MAX_EXACT_POW_BITS);
+            if exact_ok {
+                if b.is_zero() && e.is_negative() {
+                    return Err("Division by zero".to_string());
+                }
+                let e = e.to_i32().unwrap_or(i32::MAX);
+                return Ok(Number::Exact(num_traits::Pow::pow(b, e)));
+            }
+        }
+        Ok(Number::Approx(base.to_f64().powf(exponent.to_f64())))
+    }
+
+    fn modulo(a: Number, b: Number) -> Result<Number, String> {
+        if b.is_zero() {
+            return Err("Division by zero".to_string());
+        }
+        match (a, b) {
+            // Mathematical modulo: the result takes the sign of the divisor
+            (Number::Exact(x), Number::Exact(y)) => {
+                let q = (&x / &y).floor();
+                Ok(Number::Exact(x - y * q))
+            }
+            (a, b) => {
+                let (x, y) = (a.to_f64(), b.to_f64());
+                Ok(Number::Approx(x - y * (x / y).floor()))
+            }
+        }
+    }
+
+    fn factorial(n: Number) -> Result<Number, String> {
+        let n = n
+            .as_integer()
+            .filter(|n| !n.is_negative())
+            .ok_or_else(|| "Factorial needs a non-negative integer".to_string())?;
+
+        match n.to_u64() {
+            Some(n) if n <= MAX_EXACT_FACTORIAL => {
+                let mut result = BigInt::one();
+                for i in 2..=n {
+                    result *= i;
+                }
+                Ok(Number::Exact(BigRational::from_integer(result)))
+            }
+            // Too big to compute digit by digit
+            _ => Err("Factorial too large".to_string()),
+        }
+    }
+
+    fn integer_arg(name: &str, value: &Number) -> Result<BigInt, String> {
+        value
+            .as_integer()
+            .ok_or_else(|| format!("{}() needs integer arguments", name))
+    }
+
+    pub(crate) fn call_function(name: &str, args: Vec<Number>) -> Result<Number, String> {
+        let arity = |n: usize| -> Result<(), String> {
+            if args.len() == n {
+                Ok(())
+            } else {
+                Err(format!("{}() takes {} argument(s)", name, n))
+            }
+        };
+
+        // Functions that can stay exact
+        match name {
+            "abs" => {
+                arity(1)?;
+                return Ok(match &args[0] {
+                    Number::Exact(r) => Number::Exact(r.abs()),
+                    Number::Approx(f) => Number::Approx(f.abs()),
+                });
+            }
+            "floor" | "ceil" | "trunc" | "round" if args.len() != 2 => {
+                arity(1)?;
+                return Ok(match &args[0] {
+                    Number::Exact(r) => Number::Exact(match name {
+                        "floor" => r.floor(),
+                        "ceil" => r.ceil(),
+                        "trunc" => r.trunc(),
+                        _ => r.round(),
+                    }),
+                    Number::Approx(f) => Number::Approx(match name {
+                        "floor" => f.floor(),
+                        "ceil" => f.ceil(),
+                        "trunc" => f.trunc(),
+                        _ => f.round(),
+                    }),
+                });
+            }
+            "sqrt" => {
+                arity(1)?;
+                if let Number::Exact(r) = &args[0] {
+                    if !r.is_negative() {
+                        let (n, d) = (r.numer().sqrt(), r.denom().sqrt());
+                        if &(&n * &n) == r.numer() && &(&d * &d) == r.denom() {
+                            return Ok(Number::Exact(BigRational::new(n, d)));
+                        }
+                    }
+                }
+                return Ok(Number::Approx(args[0].to_f64().sqrt()));
+            }
+            "mod" => {
+                arity(2)?;
+                let mut args = args.into_iter();
+                let (a, b) = (args.next().unwrap(), args.next().unwrap());
+                return Self::modulo(a, b);
+            }
+            "fact" | "factorial" => {
+                arity(1)?;
+                return Self::factorial(args.into_iter().next().unwrap());
+            }
+            "gcd" | "lcm" => {
+                if args.len() < 2 {
+                    return Err(format!("{}() takes at least 2 arguments", name));
+                }
+                let mut acc = Self::integer_arg(name, &args[0])?;
+                for arg in &args[1..] {
+                    let n = Self::integer_arg(name, arg)?;
+                    acc = if name == "gcd" { acc.gcd(&n) } else { acc.lcm(&n) };
+                }
+                return Ok(Number::Exact(BigRational::from_integer(acc)));
+            }
+            "powmod" => {
+                arity(3)?;
+                let base = Self::integer_arg(name, &args[0])?;
+                let exp = Self::integer_arg(name, &args[1])?;
+                let modulus = Self::integer_arg(name, &args[2])?;
+                if modulus.is_zero() || exp.is_negative() {
+                    return Err("powmod() needs a non-zero modulus and non-negative exponent".to_string());
+                }
+                let result = base.modpow(&exp, &modulus.abs());
+                return Ok(Number::Exact(BigRational::from_integer(result)));
+            }
+            "isprime" => {
+                arity(1)?;
+                let n = Self::integer_arg(name, &args[0])?;
+                let prime = if Self::is_prime(&n) { 1 } else { 0 };
+                return Ok(Number::from_integer(prime));
+            }
+            "round" if args.len() == 2 => {
+                // round(x, digits)
+                let digits = Self::integer_arg(name, &args[1])?
+                    .to_i32()
+                    .filter(|d| d.abs() <= 1000)
+                    .ok_or_else(|| "round() digits out of range".to_string())?;
+                let scale = num_traits::Pow::pow(BigRational::from_integer(BigInt::from(10)), digits);
+                return Ok(match &args[0] {
+                    Number::Exact(r) => Number::Exact((r * &scale).round() / scale),
+                    Number::Approx(f) => {
+                        let scale = 10f64.powi(digits);
+                        Number::Approx((f * scale).round() / scale)
+                    }
+                });
+            }
+            _ if LIST_FUNCTIONS.contains(&name) => return Self::list_function(name, args),
+            _ => {}
+        }
+
+        // Everything else is evaluated in floating point
+        let f: Vec<f64> = args.iter().map(Number::to_f64).collect();
+        let result = match (name, f.as_slice()) {
+            ("exp", [x]) => x.exp(),
+            ("ln", [x]) => x.ln(),
+            ("log", [x]) | ("log10", [x]) => x.log10(),
+            ("log", [x, base]) => x.log(*base),
+            ("log2", [x]) => x.log2(),
+            ("cbrt", [x]) => x.cbrt(),
+            ("sin", [x]) => Self::denoise(x.sin(), x.abs()),
+            ("cos", [x]) => Self::denoise(x.cos(), x.abs()),
+            ("tan", [x]) => Self::denoise(x.tan(), x.abs()),
+            ("asin", [x]) => x.asin(),
+            ("acos", [x]) => x.acos(),
+            ("atan", [x]) => x.atan(),
+            ("atan2", [y, x]) => y.atan2(*x),
+            ("sinh", [x]) => x.sinh(),
+            ("cosh", [x]) => x.cosh(),
+            ("tanh", [x]) => x.tanh(),
+            ("signum", [x]) | ("sign", [x]) => {
+                if *x == 0.0 { 0.0 } else { x.signum() }
+            }
+            _ => return Err(format!("Unknown function '{}' or wrong number of arguments", name)),
+        };
+        Ok(Number::Approx(result))
+    }
+
+    /// Rounding noise such as sin(pi) = 1.2e-16 or sqrt(2)^2 - 2 = 4.4e-16: a float result
+    /// this far below the size of what it came from is 0
+    fn denoise(result: f64, scale: f64) -> f64 {
+        if result.abs() < scale * 8.0 * f64::EPSILON { 0.0 } else { result }
+    }
+
+    /// Statistics over a list of values. Stays exact unless a square root is involved.
+    fn list_function(name: &str, mut args: Vec<Number>) -> Result<Number, String> {
+        if args.is_empty() {
+            return match name {
+                "sum" | "count" => Ok(Number::from_integer(0)),
+                "product" | "prod" => Ok(Number::from_integer(1)),
+                _ => Err(format!("{}() needs at least one value", name)),
+            };
+        }
+
+        let count = Number::from_integer(args.len() as i64);
+        let sum = args
+            .iter()
+            .cloned()
+            .try_fold(Number::from_integer(0), |acc, n| Self::apply_binary('+', acc, n))?;
+
+        match name {
+            "count" => Ok(count),
+            "sum" => Ok(sum),
+            "product" | "prod" => args
+                .into_iter()
+                .try_fold(Number::from_integer(1), |acc, n| Self::apply_binary('*', acc, n)),
+            "mean" | "avg" | "average" => Self::apply_binary('/', sum, count),
+            "min" | "max" | "median" | "range" => {
+                Self::sort_numbers(&mut args);
+                let last = args.len() - 1;
+                match name {
+                    "min" => Ok(args.swap_remove(0)),
+                    "max" => Ok(args.swap_remove(last)),
+                    "range" => Self::apply_binary('-', args[last].clone(), args[0].clone()),
+                    _ if args.len() % 2 == 1 => Ok(args.swap_remove(last / 2)),
+                    _ => {
+                        let middle = Self::apply_binary('+', args[last / 2].clone(), args[last / 2 + 1].clone())?;
+                        Self::apply_binary('/', middle, Number::from_integer(2))
+                    }
+                }
+            }
+            "mode" => {
+                // Most frequent value, the smallest one on ties
+                Self::sort_numbers(&mut args);
+                let mut best = (0, 0);
+                let mut start = 0;
+                for i in 1..=args.len() {
+                    if i == args.len() || args[i] != args[start] {
+                        if i - start > best.1 {
+                            best = (start, i - start);
+                        }
+                        start = i;
+                    }
+                }
+                Ok(args.swap_remove(best.0))
+            }
+            "var" | "varp" | "stdev" | "stdevp" => {
+                let sample = name == "var" || name == "stdev";
+                if sample && args.len() < 2 {
+                    return Err(format!("{}() needs at least two values", name));
+                }
+                let mean = Self::apply_binary('/', sum, count.clone())?;
+                let mut squares = Number::from_integer(0);
+                for n in args {
+                    let diff = Self::apply_binary('-', n, mean.clone())?;
+                    let square = Self::apply_binary('*', diff.clone(), diff)?;
+                    squares = Self::apply_binary('+', squares, square)?;
+                }
+                let divisor = if sample {
+                    Self::apply_binary('-', count, Number::from_integer(1))?
+                } else {
+                    count
+                };
+                let variance = Self::apply_binary('/', squares, divisor)?;
+                if name.starts_with("var") {
+                    Ok(variance)
+                } else {
+                    Self::call_function("sqrt", vec![variance])
+                }
+            }
+            _ => Err(format!("Unknown function '{}'", name)),
+        }
+    }
+
+    fn sort_numbers(numbers: &mut [Number]) {
+        numbers.sort_by(|a, b| match (a, b) {
+            (Number::Exact(x), Number::Exact(y)) => x.cmp(y),
+            _ => a.to_f64().total_cmp(&b.to_f64()),
+        });
+    }
+
+    /// Trial division for small numbers, Miller-Rabin for the rest
+    fn is_prime(n: &BigInt) -> bool {
+        const SMALL_PRIMES: [u32; 12] = [2, 3, 5, 7, 11, 13, 17, 19, 23, 29, 31, 37];
+
+        if n < &BigInt::from(2) {
+            return false;
+        }
+        for p in SMALL_PRIMES {
+            let p = BigInt::from(p);
+            if n == &p {
+                return true;
+            }
+            if (n % &p).is_zero() {
+                return false;
+            }
+        }
+
+        // n - 1 = d * 2^s
+        let one = BigInt::one();
+        let n_minus_one = n - &one;
+        let mut d = n_minus_one.clone();
+        let mut s = 0;
+        while d.is_even() {
+            d >>= 1;
+            s += 1;
+        }
+
+        // These bases are deterministic below 3.3e24 and very reliable above
+        'witness: for a in SMALL_PRIMES {
+            let mut x = BigInt::from(a).modpow(&d, n);
+            if x == one || x == n_minus_one {
+                continue;
+            }
+            for _ in 1..s {
+                x = x.modpow(&BigInt::from(2), n);
+                if x == n_minus_one {
+                    continue 'witness;
+                }
+            }
+            return false;
+        }
+        true
+    }
+}
+
+impl NumberFormat {
+    fn from_config(config: &CalculatorConfig) -> Self {
+        let locale = if config.locale.is_empty() || config.locale == "auto" {
+            // Same precedence as setlocale(LC_NUMERIC, "")
+            ["LC_ALL", "LC_NUMERIC", "LANG"]
+                .iter()
+                .filter_map(|var| std::env::var(var).ok())
+                .find(|value| !value.is_empty())
+                .unwrap_or_default()
+        } else {
+            config.locale.clone()
+        };
+        Self::from_locale(&locale)
+    }
+
+    fn from_locale(locale: &str) -> Self {
+        // "de_CH.UTF-8" -> ("de", "CH")
+        let name = locale.split(['.', '@']).next().unwrap_or("");
+        let (language, region) = name.split_once(['_', '-']).unwrap_or((name, ""));
+
+        match (language, region) {
+            ("de" | "it" | "fr", "CH") => NumberFormat { decimal: '.', group: '\u{2019}' },
+            ("pt", "PT") => NumberFormat { decimal: ',', group: '\u{a0}' },
+            ("de" | "es" | "it" | "nl" | "pt" | "da" | "id" | "tr" | "el" | "ro" | "hr" | "sl" | "sr" | "vi", _) => {
+                NumberFormat { decimal: ',', group: '.' }
+            }
+            (
+                "fr" | "ru" | "pl" | "cs" | "sk" | "sv" | "nb" | "nn" | "no" | "fi" | "hu" | "bg" | "uk" | "lt" | "lv"
+                | "et",
+                _,
+            ) => NumberFormat { decimal: ',', group: '\u{a0}' },
+            _ => NumberFormat { decimal: '.', group: ',' },
+        }
+    }
+
+    /// Apply separators to a plain number like "-1234567.891e5"
+    fn localize(&self, plain: &str, grouping: bool) -> String {
+        let (number, exponent) = match plain.split_once('e') {
+            Some((number, exponent)) => (number, Some(exponent)),
+            None => (plain, None),
+        };
+        let (sign, number) = match number.strip_prefix('-') {
+            Some(rest) => ("-", rest),
+            None => ("", number),
+        };
+        let (int_part, frac_part) = match number.split_once('.') {
+            Some((int_part, frac_part)) => (int_part, Some(frac_part)),
+            None => (number, None),
+        };
+
+        let mut result = sign.to_string();
+        if grouping && int_part.len() > 3 {
+            for (i, digit) in int_part.chars().enumerate() {
+                if i > 0 && (int_part.len() - i) % 3 == 0 {
+                    result.push(self.group);
+                }
+                result.push(digit);
+            }
         } else {
-            // Round to reasonable precision
-            format!("{:.10}", result)
-                .trim_end_matches('0')
-                .trim_end_matches('.')
-                .to_string()
+            result.push_str(int_part);
+        }
+        if let Some(frac_part) = frac_part {
+            result.push(self.decimal);
+            result.push_str(frac_part);
+        }
+        if let Some(exponent) = exponent {
+            result.push('e');
+            result.push_str(exponent);
+        }
+        result
+    }
+}
+
+/// Recursive descent parser over the token stream
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        token
+    }
+
+    fn expect(&mut self, expected: Token) -> Result<(), String> {
+        match self.next() {
+            Some(ref token) if *token == expected => Ok(()),
+            _ => Err(format!("Invalid expression: expected {:?}", expected)),
+        }
+    }
+
+    // expression := term (('+' | '-') term)*
+    fn parse_expression(&mut self) -> Result<Expr, String> {
+        let mut lhs = self.parse_term()?;
+        while let Some(Token::Op(op @ ('+' | '-'))) = self.peek().cloned() {
+            self.pos += 1;
+            let rhs = self.parse_term()?;
+            lhs = Expr::Binary(op, Box::new(lhs), Box::new(rhs));
+        }
+        Ok(lhs)
+    }
+
+    // term := unary (('*' | '/' | 'mod') unary | implicit multiplication)*
+    fn parse_term(&mut self) -> Result<Expr, String> {
+        let mut lhs = self.parse_unary()?;
+        loop {
+            let op = match self.peek() {
+                Some(Token::Op(op @ ('*' | '/'))) => *op,
+                Some(Token::Ident(name)) if name == "mod" => 'm',
+                Some(Token::Ident(name)) if name == "of" => '*',
+                Some(Token::Ident(name)) if name == "off" => 'f',
+                // "2pi", "3(4+5)", "(1+2)(3+4)", "4i", "2[1, 2]"
+                Some(Token::Ident(_)) | Some(Token::LParen) | Some(Token::LBracket) => {
+                    let rhs = self.parse_unary()?;
+                    lhs = Expr::Binary('*', Box::new(lhs), Box::new(rhs));
+                    continue;
+                }
+                _ => break,
+            };
+            self.pos += 1;
+            let rhs = self.parse_unary()?;
+            lhs = Expr::Binary(op, Box::new(lhs), Box::new(rhs));
+        }
+        Ok(lhs)
+    }
+
+    // unary := ('-' | '+') unary | power
+    fn parse_unary(&mut self) -> Result<Expr, String> {
+        match self.peek() {
+            Some(Token::Op('-')) => {
+                self.pos += 1;
+                Ok(Expr::Neg(Box::new(self.parse_unary()?)))
+            }
+            Some(Token::Op('+')) => {
+                self.pos += 1;
+                self.parse_unary()
+            }
+            _ => self.parse_power(),
+        }
+    }
+
+    // power := postfix ('^' unary)?   (right associative, so -2^2 = -4)
+    fn parse_power(&mut self) -> Result<Expr, String> {
+        let base = self.parse_postfix()?;
+        if let Some(Token::Op('^')) = self.peek() {
+            self.pos += 1;
+            let exponent = self.parse_unary()?;
+            return Ok(Expr::Binary('^', Box::new(base), Box::new(exponent)));
+        }
+        Ok(base)
+    }
+
+    // postfix := primary ('!' | '%')*
+    fn parse_postfix(&mut self) -> Result<Expr, String> {
+        let mut expr = self.parse_primary()?;
+        loop {
+            match self.peek() {
+                Some(Token::Op('!')) => expr = Expr::Factorial(Box::new(expr)),
+                Some(Token::Op('%')) => expr = Expr::Percent(Box::new(expr)),
+                _ => break,
+            }
+            self.pos += 1;
+        }
+        Ok(expr)
+    }
+
+    // primary := number | ident | ident '(' args ')' | '(' expression ')' | '[' args ']'
+    fn parse_primary(&mut self) -> Result<Expr, String> {
+        match self.next() {
+            Some(Token::Number(n)) => Ok(Expr::Number(n)),
+            Some(Token::Ident(name)) => {
+                if let Some(Token::LParen) = self.peek() {
+                    self.pos += 1;
+                    let mut args = Vec::new();
+                    if let Some(Token::RParen) = self.peek() {
+                        self.pos += 1;
+                        return Ok(Expr::Call(name, args));
+                    }
+                    loop {
+                        args.push(self.parse_expression()?);
+                        match self.next() {
+                            Some(Token::Comma) => continue,
+                            Some(Token::RParen) => break,
+                            _ => return Err("Invalid expression: expected ',' or ')'".to_string()),
+                        }
+                    }
+                    Ok(Expr::Call(name, args))
+                } else if LIST_FUNCTIONS.contains(&name.as_str()) && self.peek().is_some() {
+                    // Bare list: "sum 3, 5, 9" or "mean 3 5 9"
+                    let mut args = Vec::new();
+                    while self.peek().is_some() {
+                        args.push(self.parse_expression()?);
+                        if let Some(Token::Comma) = self.peek() {
+                            self.pos += 1;
+                        }
+                    }
+                    Ok(Expr::Call(name, args))
+                } else {
+                    Ok(Expr::Var(name))
+                }
+            }
+            Some(Token::LParen) => {
+                let expr = self.parse_expression()?;
+                self.expect(Token::RParen)?;
+                Ok(expr)
+            }
+            Some(Token::LBracket) => {
+                // Vector "[5, 6]" or matrix "[[1, 2], [3, 4]]"
+                let mut items = Vec::new();
+                loop {
+                    items.push(self.parse_expression()?);
+                    match self.next() {
+                        Some(Token::Comma) => continue,
+                        Some(Token::RBracket) => break,
+                        _ => return Err("Invalid expression: expected ',' or ']'".to_string()),
+                    }
+                }
+                Ok(Expr::Array(items))
+            }
+            _ => Err("Invalid expression: unexpected end of input".to_string()),
         }
     }
 }
@@ -42,9 +1645,216 @@ impl Calculator {
 mod tests {
     use super::*;
 
+    fn config() -> CalculatorConfig {
+        CalculatorConfig {
+            locale: "en_US".to_string(),
+            ..CalculatorConfig::default()
+        }
+    }
+
+    fn calc(expression: &str) -> Calculation {
+        Calculator::calculate(expression, &config()).unwrap()
+    }
+
+    fn alternative<'a>(calculation: &'a Calculation, label: &str) -> Option<&'a str> {
+        calculation
+            .alternatives
+            .iter()
+            .find(|r| r.label == label)
+            .map(|r| r.value.as_str())
+    }
+
     #[test]
     fn test_simple_calculation() {
         assert_eq!(Calculator::evaluate("2+2"), Ok(4.0));
         assert_eq!(Calculator::evaluate("10*5"), Ok(50.0));
     }
+
+    #[test]
+    fn test_exact_arithmetic() {
+        assert_eq!(calc("0.1+0.2").value, "0.3");
+        assert_eq!(calc("2^100").value, "1267650600228229401496703205376");
+        assert_eq!(calc("3 x 4").value, "12");
+
+        let third = calc("1/3");
+        assert_eq!(third.value, "0.3333333333");
+        assert_eq!(alternative(&third, "fraction"), Some("1/3"));
+    }
+
+    #[test]
+    fn test_integer_functions() {
+        assert_eq!(calc("25!").value, "15511210043330985984000000");
+        assert_eq!(calc("-7 mod 3").value, "2");
+        assert_eq!(calc("gcd(12, 18)").value, "6");
+        assert_eq!(calc("lcm(4, 6)").value, "12");
+        assert_eq!(calc("isprime(2^61 - 1)").value, "1");
+        assert_eq!(calc("isprime(91)").value, "0");
+    }
+
+    #[test]
+    fn test_percentages() {
+        assert_eq!(calc("15% of 80").value, "12");
+        assert_eq!(calc("80 + 15%").value, "92");
+        assert_eq!(calc("80 - 15%").value, "68");
+        assert_eq!(calc("20% off 50").value, "40");
+        assert_eq!(calc("what % is 12 of 48").value, "25%");
+        assert_eq!(calc("12 is what percent of 48?").value, "25%");
+    }
+
+    #[test]
+    fn test_list_functions() {
+        assert_eq!(calc("mean(3, 5, 9)").value, "5.6666666667");
+        assert_eq!(calc("median(7, 1, 3, 5)").value, "4");
+        assert_eq!(calc("sum 3, 5, 9").value, "17");
+        assert_eq!(calc("sum(1\t2\t\t3\n4)").value, "10");
+        assert_eq!(calc("stdev(2, 4, 4, 4, 5, 5, 7, 9)").value, "2.1380899353");
+        assert_eq!(calc("stdevp(2, 4, 4, 4, 5, 5, 7, 9)").value, "2");
+        assert_eq!(calc("round(3.14159, 2)").value, "3.14");
+        assert!(Calculator::is_calculation("mean 3 5 9", &config()));
+    }
+
+    #[test]
+    fn test_formatting() {
+        let big = Calculator::calculate("2^64", &CalculatorConfig { grouping: true, ..config() }).unwrap();
+        assert_eq!(big.value, "18,446,744,073,709,551,616");
+        assert_eq!(alternative(&big, "scientific"), Some("1.8446744074e19"));
+        assert_eq!(alternative(&big, "hex"), Some("0x10000000000000000"));
+        assert_eq!(alternative(&big, "plain"), Some("18446744073709551616"));
+
+        let engineering = CalculatorConfig { notation: "engineering".to_string(), ..config() };
+        assert_eq!(Calculator::calculate("12345", &engineering).unwrap().value, "12.345e3");
+        assert_eq!(Calculator::calculate("0.00042", &engineering).unwrap().value, "420e-6");
+
+        let german = CalculatorConfig { locale: "de_DE.UTF-8".to_string(), grouping: true, ..config() };
+        assert_eq!(Calculator::calculate("3,5 * 2", &german).unwrap().value, "7");
+        assert_eq!(Calculator::calculate("1234,5 * 2", &german).unwrap().value, "2.469");
+        assert_eq!(Calculator::calculate("1/8", &german).unwrap().value, "0,125");
+        assert_eq!(Calculator::calculate("(3,5 + 1) * 2", &german).unwrap().value, "9");
+
+        // Commas in argument lists and vectors separate values
+        assert_eq!(Calculator::calculate("max(1,5)", &german).unwrap().value, "5");
+        assert_eq!(Calculator::calculate("gcd(12,18)", &german).unwrap().value, "6");
+        assert_eq!(Calculator::calculate("sum(1,2,3)", &german).unwrap().value, "6");
+        assert_eq!(Calculator::calculate("max(1, 2,5)", &german).unwrap().value, "5");
+        assert_eq!(Calculator::calculate("max(1; 2,5)", &german).unwrap().value, "2,5");
+        assert_eq!(Calculator::calculate("[1,5; 2]", &german).unwrap().value, "[1,5; 2]");
+        assert_eq!(Calculator::calculate("max((2,5), 1)", &german).unwrap().value, "2,5");
+        assert_eq!(Calculator::calculate("[1,2] + [3,4]", &german).unwrap().value, "[4; 6]");
+        assert_eq!(Calculator::calculate("dot([1,2], [3,4])", &german).unwrap().value, "11");
+
+        // Huge floats are no longer squeezed through i64
+        assert_eq!(calc("1e19 * sqrt(4)").value, "20000000000000000000");
+        assert_eq!(calc("1e300 * sin(1)").value, "8.4147098481e299");
+        // Exponents too large to expand exactly become floats instead of hanging
+        assert_eq!(calc("1e300000 / 1e299999").value, "10");
+        assert_eq!(calc("1e-400000").value, "0");
+        // Tiny values keep their digits, only floating point noise becomes 0
+        assert_eq!(calc("1e-15").value, "1e-15");
+        assert_eq!(calc("6.6743e-11").value, "6.6743e-11");
+        let gravity = calc("G");
+        assert_eq!(gravity.value, "6.6743e-11 m^3/(kg s^2)");
+        assert!(gravity.alternatives.iter().all(|r| r.label != "decimal" && r.label != "rounded"));
+        assert_eq!(calc("1e-400").value, "1e-400");
+        assert_eq!(calc("sin(pi)").value, "0");
+        assert_eq!(calc("cos(pi / 2)").value, "0");
+        assert_eq!(calc("sqrt(2)^2 - 2").value, "0");
+        assert_eq!(calc("sin(1e-20)").value, "1e-20");
+        assert!(Calculator::calculate("1e400000", &config()).is_err());
+        assert!(Calculator::calculate("1e4294967297", &config()).is_err());
+    }
+
+    #[test]
+    fn test_solver() {
+        assert_eq!(calc("solve x^2 - 5x + 6 = 0").value, "x = 2,  x = 3");
+        assert_eq!(calc("solve 2x + 3 = 10").value, "x = 3.5");
+        assert_eq!(calc("solve x^2 = 2").value, "x ≈ -1.4142135624,  x ≈ 1.4142135624");
+        assert_eq!(calc("solve x^2 + 1 = 0").value, "x ≈ i,  x ≈ -i");
+        assert_eq!(calc("solve x^3 - 6x^2 + 11x - 6 = 0").value, "x = 1,  x = 2,  x = 3");
+        assert_eq!(calc("solve x + y = 3, x - y = 1").value, "x = 2,  y = 1");
+        assert_eq!(calc("solve cos(x) = x").value, "x ≈ 0.7390851332");
+
+        assert_eq!(calc("deriv(x^3 + 2x, x)").value, "3x^2 + 2");
+        assert_eq!(calc("deriv(sin(x), x, 0)").value, "1");
+        assert_eq!(calc("integrate(x^2, 0, 3)").value, "9");
+    }
+
+    #[test]
+    fn test_complex_and_matrices() {
+        assert_eq!(calc("sqrt(-4)").value, "2i");
+        assert_eq!(calc("(3+4i) * (1-2i)").value, "11 - 2i");
+        assert_eq!(calc("abs(3+4i)").value, "5");
+        assert_eq!(calc("conj(3+4i)").value, "3 - 4i");
+        assert_eq!(calc("i^2").value, "-1");
+
+        assert_eq!(calc("[[1,2],[3,4]] * [5,6]").value, "[17, 39]");
+        assert_eq!(calc("det([[1,2],[3,4]])").value, "-2");
+        assert_eq!(calc("inv([[1,2],[3,4]])").value, "[[-2, 1], [1.5, -0.5]]");
+        assert_eq!(calc("transpose([[1,2],[3,4]])").value, "[[1, 3], [2, 4]]");
+        assert_eq!(calc("dot([1,2,3], [4,5,6])").value, "32");
+        assert_eq!(calc("cross([1,0,0], [0,1,0])").value, "[0, 0, 1]");
+        assert!(Calculator::calculate("[1,2] * [3,4]", &config()).is_err());
+    }
+
+    #[test]
+    fn test_history_answer() {
+        use crate::calculator_history::CalculatorHistory;
+
+        let mut history = CalculatorHistory::default();
+        assert_eq!(history.expand_answer("ans * 2"), "ans * 2");
+
+        history.record("1/3", &calc("1/3").value, &config());
+        assert_eq!(history.expand_answer("ans * 3"), "(1/3) * 3");
+        assert_eq!(calc(&history.expand_answer("ans * 3")).value, "1");
+        assert_eq!(history.expand_answer("answer + ans"), "answer + (1/3)");
+
+        history.record("[1,2] + [3,4]", &calc("[1,2] + [3,4]").value, &config());
+        assert_eq!(calc(&history.expand_answer("ans * 2")).value, "[8, 12]");
+        history.record("1/3", &calc("1/3").value, &config());
+        assert_eq!(history.entries.len(), 2);
+        assert_eq!(history.search("1/").len(), 1);
+    }
+
+    #[test]
+    fn test_units_and_constants() {
+        assert_eq!(calc("c * 2 s in km").value, "599584.916 km");
+        assert_eq!(calc("5 km in mi").value, "3.1068559612 mi");
+        assert_eq!(calc("3 m + 20 cm").value, "3.2 m");
+        assert_eq!(calc("2 kg * g0").value, "19.6133 N");
+        assert_eq!(calc("c").value, "299792458 m/s");
+        assert_eq!(calc("1 kWh in J").value, "3600000 J");
+        assert_eq!(calc("(1 + sqrt(5)) / 2 - phi").value, "0");
+        assert_eq!(calc("m_e c^2 in MeV").value, "0.51099895 MeV");
+        assert!(Calculator::calculate("3 m + 2 s", &config()).is_err());
+        assert!(Calculator::calculate("5 kg in m", &config()).is_err());
+        assert!(Calculator::is_calculation("5 km in mi", &config()));
+        assert!(!Calculator::is_calculation("3 in 4", &config()));
+        assert!(Calculator::calculate("3 in 4", &config()).is_err());
+
+        let found = Calculator::find_constants("planck", &config());
+        assert_eq!(found.iter().map(|c| c.name.as_str()).collect::<Vec<_>>(), ["h", "hbar"]);
+        assert_eq!(Calculator::constant_value(&found[0], &config()), "6.62607015e-34");
+        assert_eq!(Calculator::constant_value(&found[1], &config()), "1.0545718176e-34");
+        let value = |name: &str| Calculator::constant_value(&Calculator::find_constants(name, &config())[0], &config());
+        assert_eq!(value("electron mass"), "9.1093837015e-31");
+        assert_eq!(value("avogadro"), "6.02214076e23");
+        assert_eq!(value("speed of light"), "299792458");
+        assert_eq!(value("golden"), "1.6180339887");
+
+        // "h" is the Planck constant, not hours
+        let error = Calculator::calculate("60 km/h in m/s", &config()).unwrap_err();
+        assert!(error.contains("'h' is the Planck constant"), "{}", error);
+        assert_eq!(calc("60 kmh in m/s").value, "16.6666666667 m/s");
+        assert_eq!(calc("60 km/hour in m/s").value, "16.6666666667 m/s");
+
+        let mut custom = config();
+        custom.constants.push(crate::config::ConstantConfig {
+            name: "R".to_string(),
+            value: "8.314462618".to_string(),
+            unit: "J/(mol K)".to_string(),
+            description: "Molar gas constant".to_string(),
+        });
+        let result = Calculator::calculate("2 mol * R * 300 K", &custom).unwrap();
+        assert_eq!(result.value, "4988.6775708 J");
+        assert_eq!(Calculator::find_constants("gas", &custom)[0].source, "config.toml");
+    }
 }
@@ -0,0 +1,207 @@
+use crate::calculator::{Calculator, Value};
+use crate::config::CalculatorConfig;
+use crate::units::Units;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Past calculations, newest first, kept in ~/.local/share/poppi_launcher/calculator_history.json
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CalculatorHistory {
+    pub entries: Vec<HistoryEntry>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HistoryEntry {
+    pub expression: String, // What was typed
+    pub result: String,     // What was shown
+    /// The value as calculator input for `ans`, e.g. "(1/3)"
+    #[serde(default)]
+    pub answer: Option<String>,
+    pub timestamp: u64,
+}
+
+impl CalculatorHistory {
+    pub fn history_path() -> PathBuf {
+        dirs::data_dir()
+            .unwrap_or_else(|| PathBuf::from("."))
+            .join("poppi_launcher")
+            .join("calculator_history.json")
+    }
+
+    pub fn load() -> Self {
+        fs::read_to_string(Self::history_path())
+            .ok()
+            .and_then(|contents| serde_json::from_str(&contents).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self) -> Result<(), Box<dyn std::error::Error>> {
+        let path = Self::history_path();
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(&path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+
+    /// Add a calculation to the front; repeating one moves it up instead of duplicating it
+    pub fn record(&mut self, expression: &str, result: &str, config: &CalculatorConfig) {
+        if config.history_size == 0 {
+            return;
+        }
+
+        let expression = expression.trim();
+        let answer = Calculator::evaluate_value(&self.expand_answer(expression), config)
+            .ok()
+            .map(|value| Self::answer_expression(&value));
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0);
+
+        self.entries.retain(|entry| entry.expression != expression);
+        self.entries.insert(
+            0,
+            HistoryEntry {
+                expression: expression.to_string(),
+                result: result.to_string(),
+                answer,
+                timestamp,
+            },
+        );
+        self.entries.truncate(config.history_size);
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+
+    /// Entries whose expression or result contains every word of the query
+    pub fn search(&self, query: &str) -> Vec<&HistoryEntry> {
+        let query = query.to_lowercase();
+        let words: Vec<&str> = query.split_whitespace().collect();
+        self.entries
+            .iter()
+            .filter(|entry| {
+                let text = format!("{} {}", entry.expression, entry.result).to_lowercase();
+                words.iter().all(|word| text.contains(word))
+            })
+            .collect()
+    }
+
+    /// Replace the word `ans` with the last result, so "ans * 2" continues a calculation
+    pub fn expand_answer(&self, expression: &str) -> String {
+        let Some(answer) = self.entries.iter().find_map(|entry| entry.answer.as_deref()) else {
+            return expression.to_string();
+        };
+
+        let mut result = String::with_capacity(expression.len());
+        let mut word = String::new();
+        for c in expression.chars().chain(std::iter::once(' ')) {
+            if c.is_alphanumeric() || c == '_' {
+                word.push(c);
+                continue;
+            }
+            if word == "ans" {
+                result.push_str(answer);
+            } else {
+                result.push_str(&word);
+            }
+            word.clear();
+            result.push(c);
+        }
+        result.pop();
+        result
+    }
+
+    /// Calculator input that evaluates back to the same value, independent of locale
+    fn answer_expression(value: &Value) -> String {
+        use crate::calculator::Number;
+
+        fn number(n: &Number) -> String {
+            match n {
+                Number::Exact(r) if r.is_integer() => r.to_string(),
+                Number::Exact(r) => format!("{}/{}", r.numer(), r.denom()),
+                // Debug formatting round-trips f64 exactly ("1e300", "0.1")
+                Number::Approx(f) => format!("{:?}", f),
+            }
+        }
+        fn vector(v: &[Number]) -> String {
+            format!("[{}]", v.iter().map(number).collect::<Vec<_>>().join(", "))
+        }
+
+        match value {
+            Value::Number(n) => format!("({})", number(n)),
+            Value::Complex(z) => format!("({:?} + {:?}i)", z.re, z.im),
+            Value::Vector(v) => vector(v),
+            Value::Matrix(m) => format!("[{}]", m.iter().map(|row| vector(row)).collect::<Vec<_>>().join(", ")),
+            Value::Quantity(n, d) => format!("({} {})", number(n), Units::unit_name(d)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(history_size: usize) -> CalculatorConfig {
+        CalculatorConfig {
+            locale: "en_US".to_string(),
+            history_size,
+            ..CalculatorConfig::default()
+        }
+    }
+
+    fn expressions(history: &CalculatorHistory) -> Vec<&str> {
+        history.entries.iter().map(|entry| entry.expression.as_str()).collect()
+    }
+
+    #[test]
+    fn test_size_limit() {
+        let mut history = CalculatorHistory::default();
+        for n in 1..=4 {
+            history.record(&format!("{} + 1", n), &(n + 1).to_string(), &config(3));
+        }
+        assert_eq!(expressions(&history), ["4 + 1", "3 + 1", "2 + 1"]);
+
+        // Repeating a calculation moves it up without making room
+        history.record(" 2 + 1 ", "3", &config(3));
+        assert_eq!(expressions(&history), ["2 + 1", "4 + 1", "3 + 1"]);
+
+        // A smaller limit drops the oldest, 0 turns the history off
+        history.record("5 + 1", "6", &config(2));
+        assert_eq!(expressions(&history), ["5 + 1", "2 + 1"]);
+        history.record("6 + 1", "7", &config(0));
+        assert_eq!(expressions(&history), ["5 + 1", "2 + 1"]);
+
+        assert_eq!(history.search("5 6").len(), 1);
+        assert_eq!(history.search("+ 1").len(), 2);
+        history.clear();
+        assert!(history.entries.is_empty());
+    }
+
+    #[test]
+    fn test_answer() {
+        let mut history = CalculatorHistory::default();
+        assert_eq!(history.expand_answer("ans + 1"), "ans + 1");
+
+        history.record("1/3", "0.3333333333", &config(10));
+        assert_eq!(history.entries[0].answer.as_deref(), Some("(1/3)"));
+        assert_eq!(history.expand_answer("ans*ans"), "(1/3)*(1/3)");
+        assert_eq!(history.expand_answer("answer + trans"), "answer + trans");
+
+        // Results that aren't values leave the previous answer in place
+        history.record("1 +", "", &config(10));
+        assert_eq!(history.entries[0].answer, None);
+        assert_eq!(history.expand_answer("ans"), "(1/3)");
+
+        history.record("0.1 * 3", "0.3", &config(10));
+        assert_eq!(history.expand_answer("ans"), "(3/10)");
+        history.record("sqrt(2)", "1.4142135624", &config(10));
+        assert_eq!(history.expand_answer("ans"), format!("({:?})", 2f64.sqrt()));
+        history.record("[[1,2],[3,4]]", "[[1, 2], [3, 4]]", &config(10));
+        assert_eq!(history.expand_answer("ans"), "[[1, 2], [3, 4]]");
+    }
+}
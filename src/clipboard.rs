@@ -0,0 +1,136 @@
+use gtk::prelude::*;
+use gtk::{gdk, glib, Application};
+use serde::{Deserialize, Serialize};
+use std::cell::RefCell;
+use std::rc::Rc;
+use std::time::Duration;
+
+/// Something copied by a provider or kept in the clipboard history
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum ClipboardContent {
+    Text(String),
+    Uris(Vec<String>), // "file:///…" or "https://…", offered as text/uri-list and as text
+    Image(#[serde(with = "base64_bytes")] Vec<u8>), // PNG
+}
+
+// Set by password managers (KeePassXC, KDE's) on copied passwords
+const SECRET_HINT: &str = "x-kde-passwordManagerHint";
+
+/// The display's clipboard through GDK, no external tools needed. Only usable on the GTK
+/// main thread.
+pub struct Clipboard;
+
+impl Clipboard {
+    pub fn set(content: &ClipboardContent) -> Result<(), Box<dyn std::error::Error>> {
+        let clipboard = Self::clipboard()?;
+        match content {
+            ClipboardContent::Text(text) => clipboard.set_text(text),
+            ClipboardContent::Uris(uris) => {
+                let uri_list: String = uris.iter().map(|uri| format!("{}\r\n", uri)).collect();
+                let provider = gdk::ContentProvider::new_union(&[
+                    gdk::ContentProvider::for_bytes("text/uri-list", &glib::Bytes::from_owned(uri_list.into_bytes())),
+                    gdk::ContentProvider::for_value(&uris.join("\n").to_value()),
+                ]);
+                clipboard.set_content(Some(&provider))?;
+            }
+            ClipboardContent::Image(png) => {
+                let texture = gdk::Texture::from_bytes(&glib::Bytes::from(png))?;
+                clipboard.set_texture(&texture);
+            }
+        }
+        Ok(())
+    }
+
+    pub fn set_text(text: &str) -> Result<(), Box<dyn std::error::Error>> {
+        Self::set(&ClipboardContent::Text(text.to_string()))
+    }
+
+    /// Current clipboard text, `None` if it holds none or can't be read
+    pub async fn read_text() -> Option<String> {
+        let clipboard = Self::clipboard().ok()?;
+        clipboard.read_text_future().await.ok().flatten().map(|text| text.to_string())
+    }
+
+    /// Current clipboard contents: files, else text, else an image as PNG
+    pub async fn read() -> Option<ClipboardContent> {
+        let clipboard = Self::clipboard().ok()?;
+        // Other programs offer MIME types, the types GDK can turn them into are added
+        let formats = clipboard.formats().union_deserialize_types();
+
+        if formats.contains_type(gdk::FileList::static_type()) {
+            let value = clipboard
+                .read_value_future(gdk::FileList::static_type(), glib::Priority::DEFAULT)
+                .await
+                .ok()?;
+            let files = value.get::<gdk::FileList>().ok()?;
+            let uris: Vec<String> = files.files().iter().map(|file| file.uri().to_string()).collect();
+            return (!uris.is_empty()).then_some(ClipboardContent::Uris(uris));
+        }
+        if formats.contains_type(glib::Type::STRING) {
+            let text = clipboard.read_text_future().await.ok().flatten()?;
+            return Some(ClipboardContent::Text(text.to_string()));
+        }
+        if formats.contains_type(gdk::Texture::static_type()) {
+            let texture = clipboard.read_texture_future().await.ok().flatten()?;
+            return Some(ClipboardContent::Image(texture.save_to_png_bytes().to_vec()));
+        }
+        None
+    }
+
+    /// Whether the clipboard holds something a password manager marked as secret
+    pub fn is_secret() -> bool {
+        Self::clipboard().is_ok_and(|clipboard| clipboard.formats().contain_mime_type(SECRET_HINT))
+    }
+
+    /// Call `f` whenever the clipboard contents change, in any application
+    pub fn connect_changed<F: Fn() + 'static>(f: F) -> Result<(), Box<dyn std::error::Error>> {
+        Self::clipboard()?.connect_changed(move |_| f());
+        Ok(())
+    }
+
+    /// Clipboard contents vanish with the program that owns them, so after the window closes
+    /// keep running while we own the clipboard, at most `timeout`. On X11 the contents are
+    /// also handed to a clipboard manager; one taking over ends the wait early.
+    pub fn keep_alive(app: &Application, timeout: Duration) {
+        let Ok(clipboard) = Self::clipboard() else {
+            return;
+        };
+        if !clipboard.is_local() || timeout.is_zero() {
+            return;
+        }
+
+        clipboard.store_async(glib::Priority::DEFAULT, None::<&gtk::gio::Cancellable>, |_| {});
+
+        let guard = Rc::new(RefCell::new(Some(app.hold())));
+        let guard_clone = guard.clone();
+        clipboard.connect_local_notify(move |clipboard| {
+            if !clipboard.is_local() {
+                guard_clone.borrow_mut().take();
+            }
+        });
+        glib::timeout_add_local_once(timeout, move || {
+            guard.borrow_mut().take();
+        });
+    }
+
+    fn clipboard() -> Result<gdk::Clipboard, Box<dyn std::error::Error>> {
+        let display = gdk::Display::default().ok_or("No display to use the clipboard of")?;
+        Ok(display.clipboard())
+    }
+}
+
+/// Image bytes as base64 in the history file instead of a list of numbers
+mod base64_bytes {
+    use base64::engine::general_purpose::STANDARD;
+    use base64::Engine;
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(bytes: &[u8], serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&STANDARD.encode(bytes))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<u8>, D::Error> {
+        let encoded = String::deserialize(deserializer)?;
+        STANDARD.decode(encoded).map_err(serde::de::Error::custom)
+    }
+}
@@ -0,0 +1,387 @@
+use crate::clipboard::ClipboardContent;
+use crate::config::ClipboardConfig;
+use crate::utils;
+use fuzzy_matcher::skim::SkimMatcherV2;
+use fuzzy_matcher::FuzzyMatcher;
+use ring::aead::{Aad, LessSafeKey, Nonce, UnboundKey, CHACHA20_POLY1305, NONCE_LEN};
+use ring::rand::{SecureRandom, SystemRandom};
+use serde::{Deserialize, Serialize};
+use std::fs::{self, File};
+use std::io::Write;
+use std::os::unix::fs::OpenOptionsExt;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// What was copied, newest first, kept in ~/.local/share/poppi_launcher/clipboard_history.json,
+/// or clipboard_history.enc when encrypted
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ClipboardHistory {
+    pub entries: Vec<ClipboardEntry>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ClipboardEntry {
+    pub content: ClipboardContent,
+    #[serde(default)]
+    pub pinned: bool, // Kept beyond the size limit and when clearing
+    #[serde(default)]
+    pub app: Option<String>, // Where it was copied, when known
+    pub timestamp: u64,
+}
+
+// Larger copies (big screenshots, whole files of text) aren't remembered
+const MAX_ENTRY_BYTES: usize = 8 * 1024 * 1024;
+// Text searched and shown per entry
+const PREVIEW_CHARS: usize = 200;
+
+impl ClipboardHistory {
+    pub fn history_path(encrypted: bool) -> PathBuf {
+        Self::data_dir().join(if encrypted { "clipboard_history.enc" } else { "clipboard_history.json" })
+    }
+
+    /// Kept next to the history and readable only by the user: this guards copies of the history
+    /// file, not the data directory as a whole
+    fn key_path() -> PathBuf {
+        Self::data_dir().join("clipboard_history.key")
+    }
+
+    fn data_dir() -> PathBuf {
+        dirs::data_dir()
+            .unwrap_or_else(|| PathBuf::from("."))
+            .join("poppi_launcher")
+    }
+
+    /// The encrypted file if there is one, else the plain one, so switching encryption on or
+    /// off keeps the history
+    pub fn load() -> Self {
+        let encrypted = fs::read(Self::history_path(true))
+            .ok()
+            .and_then(|contents| Self::decrypt(&contents).map_err(|e| eprintln!("Error reading clipboard history: {}", e)).ok());
+        encrypted
+            .or_else(|| fs::read(Self::history_path(false)).ok())
+            .and_then(|contents| serde_json::from_slice(&contents).ok())
+            .unwrap_or_default()
+    }
+
+    /// Write the history as configured and remove the file in the other format
+    pub fn save(&self, config: &ClipboardConfig) -> Result<(), Box<dyn std::error::Error>> {
+        fs::create_dir_all(Self::data_dir())?;
+        let json = serde_json::to_vec(self)?;
+        let contents = if config.encrypt_history { Self::encrypt(json)? } else { json };
+        Self::write_private(&Self::history_path(config.encrypt_history), &contents)?;
+
+        let other = Self::history_path(!config.encrypt_history);
+        if other.exists() {
+            fs::remove_file(other)?;
+        }
+        Ok(())
+    }
+
+    /// Add a copy to the front; copying something again moves it up instead of duplicating it.
+    /// Returns false when it isn't remembered: history off, empty or too large, or copied in an
+    /// excluded or unknown app. Copies already in the history may come from an unknown app, as
+    /// when pasting an old entry.
+    pub fn record(&mut self, content: ClipboardContent, app: Option<&str>, config: &ClipboardConfig) -> bool {
+        let size = match &content {
+            ClipboardContent::Text(text) => text.len(),
+            ClipboardContent::Uris(uris) => uris.iter().map(|uri| uri.len()).sum(),
+            ClipboardContent::Image(png) => png.len(),
+        };
+        let blank = matches!(&content, ClipboardContent::Text(text) if text.trim().is_empty());
+        let app = app.filter(|app| !app.is_empty());
+        let known = self.entries.iter().any(|entry| entry.content == content);
+        let excluded = Self::is_excluded(app, config) && (app.is_some() || !known);
+        if !config.history || size == 0 || size > MAX_ENTRY_BYTES || blank || excluded {
+            return false;
+        }
+        if self.entries.first().is_some_and(|entry| entry.content == content) {
+            return false;
+        }
+
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0);
+        // A copy seen again stays pinned and keeps its app
+        let previous = self.entries.iter().position(|entry| entry.content == content).map(|i| self.entries.remove(i));
+        let app = app.map(str::to_string).or_else(|| previous.as_ref().and_then(|entry| entry.app.clone()));
+        self.entries.insert(
+            0,
+            ClipboardEntry {
+                content,
+                pinned: previous.is_some_and(|entry| entry.pinned),
+                app,
+                timestamp,
+            },
+        );
+
+        // Pinned entries don't count towards the limit
+        let mut unpinned = 0;
+        self.entries.retain(|entry| {
+            unpinned += usize::from(!entry.pinned);
+            entry.pinned || unpinned <= config.history_size
+        });
+        true
+    }
+
+    /// Whether the app (a window class or app id) matches an excluded name, ignoring case. An
+    /// unknown app (or none focused) may be one of them, so it counts as excluded while any are.
+    pub fn is_excluded(app: Option<&str>, config: &ClipboardConfig) -> bool {
+        let mut excluded = config.exclude_apps.iter().filter(|excluded| !excluded.is_empty());
+        match app.filter(|app| !app.is_empty()).map(str::to_lowercase) {
+            Some(app) => excluded.any(|excluded| app.contains(&excluded.to_lowercase())),
+            None => excluded.next().is_some(),
+        }
+    }
+
+    /// Forget everything but the pinned entries
+    pub fn clear(&mut self) {
+        self.entries.retain(|entry| entry.pinned);
+    }
+
+    pub fn toggle_pin(&mut self, content: &ClipboardContent) {
+        if let Some(entry) = self.entries.iter_mut().find(|entry| entry.content == *content) {
+            entry.pinned = !entry.pinned;
+        }
+    }
+
+    pub fn remove(&mut self, content: &ClipboardContent) {
+        self.entries.retain(|entry| entry.content != *content);
+    }
+
+    /// Fuzzy search over the entries' text, file names and apps; without a query pinned
+    /// entries come first, then the newest
+    pub fn search(&self, query: &str) -> Vec<&ClipboardEntry> {
+        if query.is_empty() {
+            let (pinned, rest): (Vec<_>, Vec<_>) = self.entries.iter().partition(|entry| entry.pinned);
+            return pinned.into_iter().chain(rest).collect();
+        }
+
+        let matcher = SkimMatcherV2::default();
+        let mut results: Vec<(&ClipboardEntry, i64)> = self
+            .entries
+            .iter()
+            .filter_map(|entry| {
+                let haystack = format!("{} {}", entry.preview(), entry.app.as_deref().unwrap_or_default());
+                matcher.fuzzy_match(&haystack, query).map(|score| (entry, score))
+            })
+            .collect();
+        results.sort_by_key(|(_, score)| std::cmp::Reverse(*score));
+        results.into_iter().map(|(entry, _)| entry).collect()
+    }
+
+    /// Sealed with ChaCha20-Poly1305 as nonce followed by ciphertext. The key is created on
+    /// first use next to the history, readable only by the user, and only while there's no
+    /// encrypted history yet.
+    fn encrypt(contents: Vec<u8>) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+        Self::seal(&Self::key()?, contents)
+    }
+
+    fn decrypt(contents: &[u8]) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+        Self::open(&Self::key()?, contents)
+    }
+
+    fn seal(key: &LessSafeKey, mut contents: Vec<u8>) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+        let mut nonce = [0u8; NONCE_LEN];
+        SystemRandom::new().fill(&mut nonce).map_err(|_| "No random numbers for a nonce")?;
+        key.seal_in_place_append_tag(Nonce::assume_unique_for_key(nonce), Aad::empty(), &mut contents)
+            .map_err(|_| "Encrypting the clipboard history failed")?;
+        Ok([nonce.as_slice(), &contents].concat())
+    }
+
+    fn open(key: &LessSafeKey, contents: &[u8]) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+        if contents.len() < NONCE_LEN {
+            return Err("Clipboard history file is truncated".into());
+        }
+        let (nonce, ciphertext) = contents.split_at(NONCE_LEN);
+        let nonce = Nonce::try_assume_unique_for_key(nonce).map_err(|_| "Invalid nonce")?;
+        let mut ciphertext = ciphertext.to_vec();
+        let plaintext = key
+            .open_in_place(nonce, Aad::empty(), &mut ciphertext)
+            .map_err(|_| "Clipboard history doesn't match its key")?;
+        Ok(plaintext.to_vec())
+    }
+
+    fn key() -> Result<LessSafeKey, Box<dyn std::error::Error>> {
+        let path = Self::key_path();
+        let bytes = match fs::read(&path) {
+            Ok(bytes) => bytes,
+            // A new key can't read an existing history, and saving with it would overwrite it
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound && Self::history_path(true).exists() => {
+                return Err(format!("{} is missing, the encrypted clipboard history can't be read", path.display()).into());
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                let mut bytes = vec![0u8; CHACHA20_POLY1305.key_len()];
+                SystemRandom::new().fill(&mut bytes).map_err(|_| "No random numbers for a key")?;
+                fs::create_dir_all(Self::data_dir())?;
+                Self::write_private(&path, &bytes)?;
+                bytes
+            }
+            Err(e) => return Err(e.into()),
+        };
+        let key = UnboundKey::new(&CHACHA20_POLY1305, &bytes).map_err(|_| "Invalid clipboard history key")?;
+        Ok(LessSafeKey::new(key))
+    }
+
+    /// Replace a file readable only by the user; through a temporary file, since the launcher
+    /// and the resident process may save at the same time
+    fn write_private(path: &Path, contents: &[u8]) -> Result<(), Box<dyn std::error::Error>> {
+        let temporary = path.with_extension(format!("tmp{}", std::process::id()));
+        let mut file = File::options()
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .mode(0o600)
+            .open(&temporary)?;
+        file.write_all(contents)?;
+        fs::rename(&temporary, path)?;
+        Ok(())
+    }
+}
+
+impl ClipboardEntry {
+    /// One line describing the contents: the text, file names, or the image size
+    pub fn preview(&self) -> String {
+        match &self.content {
+            ClipboardContent::Text(text) => {
+                let start: String = text.trim().chars().take(PREVIEW_CHARS).collect();
+                start.split_whitespace().collect::<Vec<_>>().join(" ")
+            }
+            ClipboardContent::Uris(uris) => uris
+                .iter()
+                .map(|uri| match uri.strip_prefix("file://") {
+                    Some(path) => {
+                        let name = path.trim_end_matches('/').rsplit('/').next().unwrap_or(path);
+                        urlencoding::decode(name).map(|name| name.into_owned()).unwrap_or_else(|_| name.to_string())
+                    }
+                    None => uri.clone(),
+                })
+                .collect::<Vec<_>>()
+                .join(", "),
+            ClipboardContent::Image(png) => match Self::png_size(png) {
+                Some((width, height)) => format!("Image {} × {}", width, height),
+                None => "Image".to_string(),
+            },
+        }
+    }
+
+    /// "📌 · firefox · 5 min ago · 12 lines"
+    pub fn description(&self) -> String {
+        let mut parts = Vec::new();
+        if self.pinned {
+            parts.push("📌".to_string());
+        }
+        if let Some(app) = &self.app {
+            parts.push(app.clone());
+        }
+        parts.push(utils::format_age(self.timestamp));
+        match &self.content {
+            ClipboardContent::Text(text) if text.trim().lines().count() > 1 => {
+                parts.push(format!("{} lines", text.trim().lines().count()));
+            }
+            ClipboardContent::Uris(uris) if uris.len() > 1 => parts.push(format!("{} items", uris.len())),
+            _ => {}
+        }
+        parts.join(" · ")
+    }
+
+    /// Width and height from the PNG header, without decoding the image
+    fn png_size(png: &[u8]) -> Option<(u32, u32)> {
+        if png.len() < 24 || &png[12..16] != b"IHDR" {
+            return None;
+        }
+        let width = u32::from_be_bytes(png[16..20].try_into().ok()?);
+        let height = u32::from_be_bytes(png[20..24].try_into().ok()?);
+        Some((width, height))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn text(text: &str) -> ClipboardContent {
+        ClipboardContent::Text(text.to_string())
+    }
+
+    fn texts(history: &ClipboardHistory) -> Vec<&str> {
+        history
+            .entries
+            .iter()
+            .map(|entry| match &entry.content {
+                ClipboardContent::Text(text) => text.as_str(),
+                _ => "",
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_record() {
+        let config = ClipboardConfig { history_size: 2, ..ClipboardConfig::default() };
+        let mut history = ClipboardHistory::default();
+        assert!(history.record(text("one"), Some("firefox"), &config));
+        assert!(history.record(text("two"), Some("kitty"), &config));
+        assert!(!history.record(text("two"), Some("kitty"), &config));
+        assert!(!history.record(text("  "), Some("kitty"), &config));
+        assert!(!history.record(text("hunter2"), Some("org.keepassxc.KeePassXC"), &config));
+        assert_eq!(texts(&history), ["two", "one"]);
+
+        // Copied again it moves up, keeping its app and pin
+        history.toggle_pin(&text("one"));
+        assert!(history.record(text("one"), None, &config));
+        assert_eq!(texts(&history), ["one", "two"]);
+        assert_eq!(history.entries[0].app.as_deref(), Some("firefox"));
+        assert!(history.entries[0].pinned);
+
+        // Pinned entries don't count towards the limit
+        assert!(history.record(text("three"), Some("kitty"), &config));
+        assert!(history.record(text("four"), Some("kitty"), &config));
+        assert_eq!(texts(&history), ["four", "three", "one"]);
+        assert_eq!(history.search("").first().map(|entry| entry.pinned), Some(true));
+
+        history.clear();
+        assert_eq!(texts(&history), ["one"]);
+        history.remove(&text("one"));
+        assert!(history.entries.is_empty());
+
+        let off = ClipboardConfig { history: false, ..ClipboardConfig::default() };
+        assert!(!history.record(text("one"), Some("kitty"), &off));
+    }
+
+    #[test]
+    fn test_unknown_app() {
+        // Without knowing the app a copy may come from a password manager
+        let config = ClipboardConfig::default();
+        let mut history = ClipboardHistory::default();
+        assert!(ClipboardHistory::is_excluded(None, &config));
+        assert!(ClipboardHistory::is_excluded(Some(""), &config));
+        assert!(!history.record(text("hunter2"), None, &config));
+        assert!(history.entries.is_empty());
+
+        // Unless the copy was already remembered, as when pasting from the history
+        assert!(history.record(text("one"), Some("firefox"), &config));
+        assert!(history.record(text("two"), Some("firefox"), &config));
+        assert!(history.record(text("one"), None, &config));
+        assert_eq!(texts(&history), ["one", "two"]);
+
+        let anything = ClipboardConfig { exclude_apps: Vec::new(), ..ClipboardConfig::default() };
+        assert!(!ClipboardHistory::is_excluded(None, &anything));
+        assert!(history.record(text("three"), None, &anything));
+    }
+
+    #[test]
+    fn test_encryption_round_trip() {
+        let key = || LessSafeKey::new(UnboundKey::new(&CHACHA20_POLY1305, &[7; 32]).unwrap());
+        let sealed = ClipboardHistory::seal(&key(), b"secret".to_vec()).unwrap();
+        assert_eq!(sealed.len(), NONCE_LEN + b"secret".len() + CHACHA20_POLY1305.tag_len());
+        assert!(!sealed.windows(6).any(|window| window == b"secret"));
+        assert_eq!(ClipboardHistory::open(&key(), &sealed).unwrap(), b"secret");
+
+        let other = LessSafeKey::new(UnboundKey::new(&CHACHA20_POLY1305, &[8; 32]).unwrap());
+        assert!(ClipboardHistory::open(&other, &sealed).is_err());
+        let mut tampered = sealed.clone();
+        *tampered.last_mut().unwrap() ^= 1;
+        assert!(ClipboardHistory::open(&key(), &tampered).is_err());
+        assert!(ClipboardHistory::open(&key(), &sealed[..4]).is_err());
+    }
+}
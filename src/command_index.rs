@@ -0,0 +1,129 @@
+use rustix::fs::inotify::{self, CreateFlags, WatchFlags};
+use std::collections::HashSet;
+use std::fs;
+use std::mem::MaybeUninit;
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Path, PathBuf};
+use std::sync::{OnceLock, RwLock};
+use std::thread;
+use std::time::Duration;
+
+/// Which commands exist, without starting a shell: the executables in the PATH directories
+/// plus shell builtins. Built once per process and kept current by an inotify watch on the
+/// directories, so checking the first word of every query costs a hash lookup.
+pub struct CommandIndex;
+
+static INDEX: OnceLock<RwLock<HashSet<String>>> = OnceLock::new();
+
+// Builtins and keywords of bash, zsh and fish that have no executable on PATH
+const BUILTINS: &[&str] = &[
+    ".", ":", "[", "alias", "bg", "bind", "break", "builtin", "case", "cd", "command", "compgen",
+    "complete", "continue", "declare", "dirs", "disown", "echo", "enable", "eval", "exec", "exit",
+    "export", "false", "fc", "fg", "for", "function", "getopts", "hash", "help", "history", "if",
+    "jobs", "kill", "let", "local", "logout", "popd", "printf", "pushd", "pwd", "read", "readonly",
+    "return", "set", "shift", "shopt", "source", "suspend", "test", "time", "times", "trap", "true",
+    "type", "typeset", "ulimit", "umask", "unalias", "unset", "until", "wait", "while",
+];
+
+// Changes to PATH directories come in bursts (package installs), one rescan covers them
+const RESCAN_DELAY: Duration = Duration::from_millis(250);
+
+impl CommandIndex {
+    /// Whether `name` runs as a command: a builtin, an executable on PATH, or the path of an
+    /// executable file ("./build.sh")
+    pub fn contains(name: &str) -> bool {
+        if name.contains('/') {
+            return Self::is_executable(Path::new(name));
+        }
+        !name.is_empty() && Self::index().read().is_ok_and(|index| index.contains(name))
+    }
+
+    /// The commands starting with `prefix`, sorted
+    pub fn complete(prefix: &str) -> Vec<String> {
+        let mut commands: Vec<String> = Self::index()
+            .read()
+            .map(|index| index.iter().filter(|name| name.starts_with(prefix)).cloned().collect())
+            .unwrap_or_default();
+        commands.sort();
+        commands
+    }
+
+    /// Build the index ahead of the first query, e.g. from a background thread
+    pub fn load() {
+        Self::index();
+    }
+
+    fn index() -> &'static RwLock<HashSet<String>> {
+        INDEX.get_or_init(|| {
+            thread::spawn(Self::watch);
+            RwLock::new(Self::scan())
+        })
+    }
+
+    fn path_dirs() -> Vec<PathBuf> {
+        std::env::var_os("PATH")
+            .map(|path| std::env::split_paths(&path).filter(|dir| dir.is_absolute()).collect())
+            .unwrap_or_default()
+    }
+
+    fn scan() -> HashSet<String> {
+        let mut commands: HashSet<String> = BUILTINS.iter().map(|builtin| builtin.to_string()).collect();
+        for dir in Self::path_dirs() {
+            let Ok(entries) = fs::read_dir(&dir) else {
+                continue;
+            };
+            for entry in entries.flatten() {
+                if Self::is_executable(&entry.path()) {
+                    if let Some(name) = entry.file_name().to_str() {
+                        commands.insert(name.to_string());
+                    }
+                }
+            }
+        }
+        commands
+    }
+
+    /// A file (or a link to one) with an execute bit set
+    fn is_executable(path: &Path) -> bool {
+        fs::metadata(path).is_ok_and(|metadata| metadata.is_file() && metadata.permissions().mode() & 0o111 != 0)
+    }
+
+    /// Rescan whenever an executable appears in, disappears from or changes mode in a PATH
+    /// directory. Runs for the life of the process; without inotify the index stays as built.
+    fn watch() {
+        let Ok(inotify) = inotify::init(CreateFlags::CLOEXEC) else {
+            return;
+        };
+        let flags = WatchFlags::CREATE
+            | WatchFlags::DELETE
+            | WatchFlags::MOVED_FROM
+            | WatchFlags::MOVED_TO
+            | WatchFlags::ATTRIB
+            | WatchFlags::DELETE_SELF
+            | WatchFlags::MOVE_SELF;
+        let watched = Self::path_dirs()
+            .iter()
+            .filter(|dir| inotify::add_watch(&inotify, dir.as_path(), flags).is_ok())
+            .count();
+        if watched == 0 {
+            return;
+        }
+
+        let mut buffer = [MaybeUninit::uninit(); 4096];
+        let mut events = inotify::Reader::new(&inotify, &mut buffer);
+        loop {
+            if events.next().is_err() {
+                return;
+            }
+            while !events.is_buffer_empty() {
+                let _ = events.next();
+            }
+            thread::sleep(RESCAN_DELAY);
+
+            let commands = Self::scan();
+            if let Some(Ok(mut index)) = INDEX.get().map(|index| index.write()) {
+                *index = commands;
+            }
+        }
+    }
+}
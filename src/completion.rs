@@ -0,0 +1,412 @@
+use crate::command_index::CommandIndex;
+use crate::utils;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Completions for the word being typed in a terminal query: command names from PATH, file
+/// paths, and subcommands and flags from fish completion files, which many packages install
+/// even without fish
+#[derive(Debug, Default)]
+pub struct Completer {
+    fish_rules: HashMap<String, Vec<FishRule>>, // Parsed files by command, read on first use
+}
+
+/// A completed query, accepted into the entry with Tab
+#[derive(Debug, Clone, PartialEq)]
+pub struct Completion {
+    pub line: String,                // The whole command line with the word completed
+    pub word: String,                // The completed word, shown in the row
+    pub description: Option<String>, // From the completion file, or "Directory"
+}
+
+/// One `complete -c …` line of a fish completion file
+#[derive(Debug, Clone, PartialEq)]
+struct FishRule {
+    context: FishContext,
+    arguments: Vec<(String, Option<String>)>, // Words from -a, with their own descriptions
+    flags: Vec<String>,                       // -s, -l and -o options, dashes included
+    description: Option<String>,
+}
+
+/// Where a rule applies, from its -n condition
+#[derive(Debug, Clone, PartialEq)]
+enum FishContext {
+    Anywhere,           // No condition
+    Subcommand,         // Before any subcommand ("__fish_use_subcommand")
+    After(Vec<String>), // Once one of these subcommands is given
+    Other,              // A condition we can't evaluate, only its flags are offered
+}
+
+// Candidates computed per keystroke, more don't fit the list anyway
+const MAX_COMPLETIONS: usize = 50;
+
+// Searched in this order for <command>.fish, the user's own files first
+const FISH_COMPLETION_DIRS: &[&str] = &[
+    "/etc/fish/completions",
+    "/usr/local/share/fish/vendor_completions.d",
+    "/usr/share/fish/vendor_completions.d",
+    "/usr/local/share/fish/completions",
+    "/usr/share/fish/completions",
+];
+
+impl Completer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Completions of the last word of `line`, a command line without a terminal prefix, with
+    /// relative paths in `dir`, where the command will run. With nothing typed after a space,
+    /// the next word is completed.
+    pub fn complete(&mut self, line: &str, dir: &Path) -> Vec<Completion> {
+        let start = Self::word_start(line);
+        let (head, word) = line.split_at(start);
+        let words = utils::split_words(head);
+
+        // (word, description, whether it is a directory)
+        let candidates: Vec<(String, Option<String>, bool)> = match words.first() {
+            // The command itself
+            None if Self::is_path(word) => Self::complete_path(word, dir),
+            None => CommandIndex::complete(word)
+                .into_iter()
+                .map(|command| (command, None, false))
+                .collect(),
+            Some(command) => {
+                let command = command.rsplit('/').next().unwrap_or(command).to_string();
+                let mut candidates = self.complete_argument(&command, &words[1..], word);
+                if !word.starts_with('-') {
+                    candidates.extend(Self::complete_path(word, dir));
+                }
+                candidates
+            }
+        };
+
+        candidates
+            .into_iter()
+            .filter(|(candidate, _, _)| candidate != word || word.is_empty())
+            .take(MAX_COMPLETIONS)
+            .map(|(candidate, description, directory)| {
+                // A finished word gets its space, a directory waits for the rest of the path
+                let separator = if directory { "" } else { " " };
+                Completion {
+                    line: format!("{}{}{}", head, candidate, separator),
+                    word: candidate,
+                    description,
+                }
+            })
+            .collect()
+    }
+
+    /// Subcommands and flags of `command` from its fish completion file. `args` are the
+    /// words between the command and the one being completed.
+    fn complete_argument(&mut self, command: &str, args: &[String], word: &str) -> Vec<(String, Option<String>, bool)> {
+        let rules = self
+            .fish_rules
+            .entry(command.to_string())
+            .or_insert_with(|| Self::load_fish_rules(command));
+
+        // The first argument that one of the rules names as a subcommand
+        let subcommand = args.iter().find(|arg| {
+            !arg.starts_with('-')
+                && rules.iter().any(|rule| {
+                    matches!(rule.context, FishContext::Subcommand) && rule.arguments.iter().any(|(name, _)| name == *arg)
+                })
+        });
+        let applies = |rule: &FishRule| match (&rule.context, subcommand) {
+            (FishContext::Anywhere | FishContext::Other, _) => true,
+            (FishContext::Subcommand, None) => true,
+            (FishContext::After(names), Some(subcommand)) => names.contains(subcommand),
+            _ => false,
+        };
+
+        let mut candidates: Vec<(String, Option<String>, bool)> = Vec::new();
+        for rule in rules.iter().filter(|rule| applies(rule)) {
+            if word.starts_with('-') {
+                for flag in rule.flags.iter().filter(|flag| flag.starts_with(word)) {
+                    candidates.push((flag.clone(), rule.description.clone(), false));
+                }
+            } else if rule.context != FishContext::Other {
+                for (argument, description) in rule.arguments.iter().filter(|(argument, _)| argument.starts_with(word)) {
+                    let description = description.clone().or_else(|| rule.description.clone());
+                    candidates.push((Self::escape(argument), description, false));
+                }
+            }
+        }
+        candidates.sort_by(|a, b| a.0.cmp(&b.0));
+        candidates.dedup_by(|a, b| a.0 == b.0);
+        candidates
+    }
+
+    /// Entries of the directory the word points into that start with its last part. Hidden
+    /// files only when that part starts with a dot; "~/" stands for the home directory and
+    /// relative paths start from `base`.
+    fn complete_path(word: &str, base: &Path) -> Vec<(String, Option<String>, bool)> {
+        if word == "~" {
+            return vec![("~/".to_string(), Some("Directory".to_string()), true)];
+        }
+        let (dir_part, name_prefix) = match word.rfind('/') {
+            Some(i) => word.split_at(i + 1),
+            None => ("", word),
+        };
+        let name_prefix = Self::unescape(name_prefix);
+        let dir_path = Self::unescape(dir_part);
+        let dir = match dir_path.strip_prefix("~/") {
+            Some(rest) => dirs::home_dir().unwrap_or_default().join(rest),
+            None => base.join(dir_path),
+        };
+        let Ok(entries) = fs::read_dir(&dir) else {
+            return Vec::new();
+        };
+
+        let mut candidates: Vec<(String, Option<String>, bool)> = entries
+            .flatten()
+            .filter_map(|entry| {
+                let name = entry.file_name().to_str()?.to_string();
+                if !name.starts_with(&name_prefix) || (name.starts_with('.') && !name_prefix.starts_with('.')) {
+                    return None;
+                }
+                // Follows links, a link to a directory completes like one
+                let directory = entry.path().is_dir();
+                let completed = format!("{}{}{}", dir_part, Self::escape(&name), if directory { "/" } else { "" });
+                Some((completed, directory.then(|| "Directory".to_string()), directory))
+            })
+            .collect();
+        candidates.sort_by_key(|(completed, _, _)| completed.to_lowercase());
+        candidates
+    }
+
+    fn is_path(word: &str) -> bool {
+        word.contains('/') || word.starts_with('~')
+    }
+
+    /// Where the last word starts: after the last space that isn't escaped
+    fn word_start(line: &str) -> usize {
+        let mut start = 0;
+        let mut escaped = false;
+        for (i, c) in line.char_indices() {
+            if escaped {
+                escaped = false;
+            } else if c == '\\' {
+                escaped = true;
+            } else if c.is_whitespace() {
+                start = i + c.len_utf8();
+            }
+        }
+        start
+    }
+
+    /// Backslashes before the characters the shell would otherwise split or expand on
+    fn escape(word: &str) -> String {
+        let mut escaped = String::with_capacity(word.len());
+        for c in word.chars() {
+            if c.is_whitespace() || "\\'\"$`&|;<>()*?[]!#".contains(c) {
+                escaped.push('\\');
+            }
+            escaped.push(c);
+        }
+        escaped
+    }
+
+    fn unescape(word: &str) -> String {
+        let mut unescaped = String::with_capacity(word.len());
+        let mut chars = word.chars();
+        while let Some(c) = chars.next() {
+            match c {
+                '\\' => unescaped.extend(chars.next()),
+                c => unescaped.push(c),
+            }
+        }
+        unescaped
+    }
+
+    /// The `complete` lines of <command>.fish in the first directory that has one
+    fn load_fish_rules(command: &str) -> Vec<FishRule> {
+        let mut dirs: Vec<PathBuf> = Vec::new();
+        if let Some(config_dir) = dirs::config_dir() {
+            dirs.push(config_dir.join("fish").join("completions"));
+        }
+        if let Some(data_dir) = dirs::data_dir() {
+            dirs.push(data_dir.join("fish").join("vendor_completions.d"));
+        }
+        dirs.extend(FISH_COMPLETION_DIRS.iter().map(PathBuf::from));
+
+        let Some(contents) = dirs
+            .iter()
+            .find_map(|dir| fs::read_to_string(dir.join(format!("{}.fish", command))).ok())
+        else {
+            return Vec::new();
+        };
+
+        // Long lines continue after a trailing backslash
+        contents
+            .replace("\\\n", " ")
+            .lines()
+            .filter(|line| line.trim_start().starts_with("complete "))
+            .filter_map(|line| Self::parse_fish_rule(&utils::split_words(line), command))
+            .collect()
+    }
+
+    /// The options of one `complete` line; None when it is for another command or offers
+    /// nothing. Arguments computed by fish, `(…)` or `$…`, can't be offered.
+    fn parse_fish_rule(words: &[String], command: &str) -> Option<FishRule> {
+        let mut rule = FishRule { context: FishContext::Anywhere, arguments: Vec::new(), flags: Vec::new(), description: None };
+        let mut for_command = false;
+        let mut words = words.iter().skip(1);
+        while let Some(word) = words.next() {
+            // "--long=value", "--long value", "-x value" or clusters like "-fc git"
+            let (option, inline_value) = match word.strip_prefix("--") {
+                Some(long) => match long.split_once('=') {
+                    Some((name, value)) => (name.to_string(), Some(value.to_string())),
+                    None => (long.to_string(), None),
+                },
+                None => match word.strip_prefix('-') {
+                    Some(short) => {
+                        let Some(position) = short.find(|c| "csloadnw".contains(c)) else {
+                            continue; // Only flags like -f or -x
+                        };
+                        let value = &short[position + 1..];
+                        (short[position..position + 1].to_string(), (!value.is_empty()).then(|| value.to_string()))
+                    }
+                    None => continue,
+                },
+            };
+            let takes_value = matches!(
+                option.as_str(),
+                "c" | "command" | "s" | "short-option" | "l" | "long-option" | "o" | "old-option" | "a" | "arguments"
+                    | "d" | "description" | "n" | "condition" | "w" | "wraps"
+            );
+            if !takes_value {
+                continue;
+            }
+            let Some(value) = inline_value.or_else(|| words.next().cloned()) else {
+                break;
+            };
+
+            match option.as_str() {
+                "c" | "command" => for_command = value == command,
+                "s" | "short-option" => rule.flags.push(format!("-{}", value)),
+                "l" | "long-option" => rule.flags.push(format!("--{}", value)),
+                "o" | "old-option" => rule.flags.push(format!("-{}", value)),
+                "d" | "description" => rule.description = Some(value),
+                "n" | "condition" => rule.context = Self::fish_context(&value),
+                "a" | "arguments" => {
+                    if value.contains('(') || value.contains('$') {
+                        continue;
+                    }
+                    for argument in value.split_whitespace() {
+                        // "name\tdescription"
+                        let (name, description) = match argument.split_once("\\t") {
+                            Some((name, description)) => (name.to_string(), Some(description.to_string())),
+                            None => (argument.to_string(), None),
+                        };
+                        rule.arguments.push((name, description));
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        (for_command && (!rule.arguments.is_empty() || !rule.flags.is_empty())).then_some(rule)
+    }
+
+    /// The conditions fish completion files use for subcommands; anything else is `Other`
+    fn fish_context(condition: &str) -> FishContext {
+        let words: Vec<&str> = condition.split(|c: char| c.is_whitespace() || c == ';' || c == '&').collect();
+        let names_after = |marker: &str| -> Option<Vec<String>> {
+            let position = words.iter().position(|word| word.ends_with(marker))?;
+            Some(
+                words[position + 1..]
+                    .iter()
+                    .take_while(|word| !word.is_empty() && !word.starts_with('-'))
+                    .map(|word| word.to_string())
+                    .collect(),
+            )
+        };
+
+        if condition.contains("use_subcommand")
+            || condition.contains("needs_command")
+            || condition.contains("__fish_is_first_arg")
+            || condition.contains("__fish_is_first_token")
+            || condition.trim_start().starts_with("not __fish_seen_subcommand_from")
+        {
+            return FishContext::Subcommand;
+        }
+        if let Some(names) = names_after("seen_subcommand_from").or_else(|| names_after("using_command")) {
+            if !names.is_empty() {
+                return FishContext::After(names);
+            }
+        }
+        FishContext::Other
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rule(line: &str) -> Option<FishRule> {
+        Completer::parse_fish_rule(&utils::split_words(line), "git")
+    }
+
+    #[test]
+    fn test_parse_fish_rule() {
+        let subcommands = rule("complete -f -c git -n __fish_git_needs_command -a 'push\\tUpload pull' -d 'Command'").unwrap();
+        assert_eq!(subcommands.context, FishContext::Subcommand);
+        assert_eq!(
+            subcommands.arguments,
+            [("push".to_string(), Some("Upload".to_string())), ("pull".to_string(), None)]
+        );
+        assert_eq!(subcommands.description.as_deref(), Some("Command"));
+
+        let flags = rule("complete -c git -n '__fish_seen_subcommand_from push' -l force -s f -d 'Force'").unwrap();
+        assert_eq!(flags.context, FishContext::After(vec!["push".to_string()]));
+        assert_eq!(flags.flags, ["--force", "-f"]);
+
+        // Clustered and inline values
+        let clustered = rule("complete -fcgit --long-option=verbose").unwrap();
+        assert_eq!(clustered.flags, ["--verbose"]);
+
+        assert_eq!(rule("complete -c git -n 'test -d .git' -a '(__fish_git_branches)'"), None);
+        assert_eq!(rule("complete -c hg -l verbose"), None);
+        assert_eq!(Completer::fish_context("__fish_git_using_command log"), FishContext::After(vec!["log".to_string()]));
+        assert_eq!(Completer::fish_context("test -d .git"), FishContext::Other);
+    }
+
+    #[test]
+    fn test_complete_argument() {
+        let mut completer = Completer::new();
+        let rules = [
+            "complete -c git -n __fish_use_subcommand -a 'push pull status'",
+            "complete -c git -n '__fish_seen_subcommand_from push' -l force -d 'Force'",
+            "complete -c git -n '__fish_seen_subcommand_from pull' -l rebase",
+        ];
+        completer.fish_rules.insert("git".to_string(), rules.iter().filter_map(|line| rule(line)).collect());
+
+        let names = |candidates: Vec<(String, Option<String>, bool)>| -> Vec<String> {
+            candidates.into_iter().map(|(name, _, _)| name).collect()
+        };
+        assert_eq!(names(completer.complete_argument("git", &[], "pu")), ["pull", "push"]);
+        assert_eq!(names(completer.complete_argument("git", &["push".to_string()], "--")), ["--force"]);
+        assert!(completer.complete_argument("git", &["push".to_string()], "pu").is_empty());
+    }
+
+    #[test]
+    fn test_complete_path() {
+        let base = std::env::temp_dir().join(format!("poppi_launcher-test-{}-completion", std::process::id()));
+        fs::create_dir_all(base.join("my dir")).unwrap();
+        fs::write(base.join("my file"), "").unwrap();
+        fs::write(base.join(".hidden"), "").unwrap();
+
+        // No completion file for cat, whatever is installed
+        let mut completer = Completer::new();
+        completer.fish_rules.insert("cat".to_string(), Vec::new());
+        let completions = completer.complete("cat my", &base);
+        let words: Vec<&str> = completions.iter().map(|completion| completion.word.as_str()).collect();
+        assert_eq!(words, ["my\\ dir/", "my\\ file"]);
+        assert_eq!(completions[0].line, "cat my\\ dir/");
+        assert_eq!(completions[1].line, "cat my\\ file ");
+        assert_eq!(Completer::complete_path("my\\ dir/", &base), Vec::new());
+        assert_eq!(Completer::complete_path(".h", &base)[0].0, ".hidden");
+        fs::remove_dir_all(&base).unwrap();
+    }
+}
@@ -9,6 +9,16 @@ pub struct Config {
     pub shortcuts: ShortcutConfig,
     pub search: SearchConfig,
     pub calculator: CalculatorConfig,
+    #[serde(default)]
+    pub emoji: EmojiConfig,
+    #[serde(default)]
+    pub snippets: SnippetConfig,
+    #[serde(default)]
+    pub insert: InsertConfig,
+    #[serde(default)]
+    pub clipboard: ClipboardConfig,
+    #[serde(default)]
+    pub terminal: TerminalConfig,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -35,8 +45,75 @@ pub struct SearchConfig {
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct CalculatorConfig {
     pub enabled: bool,
+    pub exact: bool,         // Exact rational arithmetic instead of floating point
+    pub precision: usize,    // Number of decimal places shown
+    pub notation: String,    // "auto", "scientific" or "engineering"
+    pub grouping: bool,      // Thousands separators
+    pub locale: String,      // e.g. "de_DE" for a decimal comma, "auto" uses $LC_NUMERIC / $LANG
+    pub history_size: usize, // Calculations kept in the history, 0 disables it
+    pub constants: Vec<ConstantConfig>, // Extra named constants, see `Calculator::constants`
+}
+
+/// A user defined calculator constant; value and unit are calculator expressions
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConstantConfig {
+    pub name: String,
+    pub value: String,
+    #[serde(default)]
+    pub unit: String,
+    #[serde(default)]
+    pub description: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct EmojiConfig {
+    pub skin_tone: String, // "default", "light", "medium-light", "medium", "medium-dark" or "dark"
+    pub languages: Vec<String>, // Search languages besides English, e.g. ["de", "fr"]; empty uses the locale
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SnippetConfig {
+    pub prefix: String, // Typed before a snippet search, empty disables snippets
+    pub builtin: bool,  // Include the built-in kaomoji and symbol sets
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct InsertConfig {
+    pub method: String,          // "type", "paste" (clipboard and Ctrl+V) or "clipboard" (copy only)
+    pub restore_clipboard: bool, // Put the previous clipboard text back after pasting
+    pub focus_timeout_ms: u64,   // Longest wait for the previous window to get focus back
+    pub delay_ms: u64,           // Wait before inserting where focus can't be followed
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ClipboardConfig {
+    pub keep_alive_secs: u64,       // How long copied content stays available after the launcher closes
+    pub history: bool,              // Remember what is copied, searched with "cb"
+    pub history_size: usize,        // Entries kept besides pinned ones
+    pub encrypt_history: bool,      // Encrypt the history file; the key file sits next to it, so this
+                                    // guards copies of the history (backups), not the data directory
+    pub exclude_apps: Vec<String>,  // Apps whose copies are never remembered, e.g. password managers
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TerminalConfig {
+    /// Terminal to run commands in, e.g. "kitty --title {title} {argv}"; {argv} becomes the
+    /// command's arguments, {command} the same as one quoted string. Empty follows the
+    /// xdg-terminal-exec preferences, then $TERMINAL, then the terminals we know.
+    pub command: String,
+    pub hold: bool,                // Keep the window open until Enter after the command ends
+    pub working_directory: String, // Where commands run, "~" for home; empty keeps the launcher's
+    pub title: String,             // Window title, {command} is the command line
+    pub inline_timeout_secs: u64,  // Commands run in the launcher with "!!" are stopped after this long
+    pub shell_history: bool,       // Suggest commands from the bash, zsh, fish and atuin history
 }
 
 impl Default for Config {
@@ -59,9 +136,86 @@ impl Default for Config {
                 youtube_enabled: true,
                 chatgpt_enabled: true,
             },
-            calculator: CalculatorConfig {
-                enabled: true,
-            },
+            calculator: CalculatorConfig::default(),
+            emoji: EmojiConfig::default(),
+            snippets: SnippetConfig::default(),
+            insert: InsertConfig::default(),
+            clipboard: ClipboardConfig::default(),
+            terminal: TerminalConfig::default(),
+        }
+    }
+}
+
+impl Default for CalculatorConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            exact: true,
+            precision: 10,
+            notation: "auto".to_string(),
+            grouping: false,
+            locale: "auto".to_string(),
+            history_size: 100,
+            constants: Vec::new(),
+        }
+    }
+}
+
+impl Default for EmojiConfig {
+    fn default() -> Self {
+        Self {
+            skin_tone: "default".to_string(),
+            languages: Vec::new(),
+        }
+    }
+}
+
+impl Default for SnippetConfig {
+    fn default() -> Self {
+        Self {
+            prefix: ";".to_string(),
+            builtin: true,
+        }
+    }
+}
+
+impl Default for InsertConfig {
+    fn default() -> Self {
+        Self {
+            method: "type".to_string(),
+            restore_clipboard: true,
+            focus_timeout_ms: 1000,
+            delay_ms: 150,
+        }
+    }
+}
+
+impl Default for ClipboardConfig {
+    fn default() -> Self {
+        Self {
+            keep_alive_secs: 300,
+            history: true,
+            history_size: 50,
+            encrypt_history: false,
+            exclude_apps: vec![
+                "keepassxc".to_string(),
+                "bitwarden".to_string(),
+                "1password".to_string(),
+                "seahorse".to_string(),
+            ],
+        }
+    }
+}
+
+impl Default for TerminalConfig {
+    fn default() -> Self {
+        Self {
+            command: String::new(),
+            hold: true,
+            working_directory: "~".to_string(),
+            title: "{command}".to_string(),
+            inline_timeout_secs: 30,
+            shell_history: true,
         }
     }
 }
@@ -0,0 +1,815 @@
+use chrono::{DateTime, Datelike, Days, Months, NaiveDate, NaiveDateTime, NaiveTime};
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::OnceLock;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tz::TimeZone;
+
+/// Date, time and time zone arithmetic.
+///
+/// Everything runs offline: zones come from the system tzdata in
+/// `/usr/share/zoneinfo` (via `tz-rs`), calendar maths from `chrono`.
+pub struct DateTimeCalculator;
+
+/// Result of a date/time query, ready for display
+#[derive(Debug, Clone, PartialEq)]
+pub struct DateTimeResult {
+    pub value: String,        // Main rendering (this is what gets copied)
+    pub details: Vec<String>, // Alternative formats shown under the value
+}
+
+/// A resolved time zone with the name we show to the user
+#[derive(Debug, Clone)]
+struct Zone {
+    label: String,
+    tz: TimeZone,
+}
+
+/// A wall-clock time in a specific zone
+#[derive(Debug, Clone)]
+struct Instant {
+    local: NaiveDateTime,
+    zone: Zone,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Unit {
+    Years,
+    Months,
+    Weeks,
+    Days,
+    Hours,
+    Minutes,
+    Seconds,
+}
+
+/// Intermediate value while evaluating "a + b - c"
+#[derive(Debug, Clone)]
+enum Value {
+    Instant(Instant),
+    Span { seconds: i64, from: Option<Instant>, to: Option<Instant> },
+}
+
+// Common city names and abbreviations that are not the last part of a tzdata zone name
+const CITY_ALIASES: &[(&str, &str)] = &[
+    ("utc", "UTC"),
+    ("gmt", "UTC"),
+    ("z", "UTC"),
+    ("zulu", "UTC"),
+    ("san francisco", "America/Los_Angeles"),
+    ("sf", "America/Los_Angeles"),
+    ("seattle", "America/Los_Angeles"),
+    ("las vegas", "America/Los_Angeles"),
+    ("pst", "America/Los_Angeles"),
+    ("pdt", "America/Los_Angeles"),
+    ("washington", "America/New_York"),
+    ("boston", "America/New_York"),
+    ("miami", "America/New_York"),
+    ("atlanta", "America/New_York"),
+    ("nyc", "America/New_York"),
+    ("est", "America/New_York"),
+    ("edt", "America/New_York"),
+    ("dallas", "America/Chicago"),
+    ("houston", "America/Chicago"),
+    ("cst", "America/Chicago"),
+    ("cdt", "America/Chicago"),
+    ("mst", "America/Denver"),
+    ("mdt", "America/Denver"),
+    ("montreal", "America/Toronto"),
+    ("rio", "America/Sao_Paulo"),
+    ("munich", "Europe/Berlin"),
+    ("frankfurt", "Europe/Berlin"),
+    ("hamburg", "Europe/Berlin"),
+    ("cet", "Europe/Berlin"),
+    ("cest", "Europe/Berlin"),
+    ("milan", "Europe/Rome"),
+    ("barcelona", "Europe/Madrid"),
+    ("geneva", "Europe/Zurich"),
+    ("st petersburg", "Europe/Moscow"),
+    ("beijing", "Asia/Shanghai"),
+    ("shenzhen", "Asia/Shanghai"),
+    ("mumbai", "Asia/Kolkata"),
+    ("bombay", "Asia/Kolkata"),
+    ("delhi", "Asia/Kolkata"),
+    ("new delhi", "Asia/Kolkata"),
+    ("bangalore", "Asia/Kolkata"),
+    ("ist", "Asia/Kolkata"),
+    ("saigon", "Asia/Ho_Chi_Minh"),
+    ("osaka", "Asia/Tokyo"),
+    ("jst", "Asia/Tokyo"),
+    ("kyoto", "Asia/Tokyo"),
+    ("canberra", "Australia/Sydney"),
+    ("wellington", "Pacific/Auckland"),
+];
+
+impl DateTimeCalculator {
+    pub fn is_datetime_query(query: &str) -> bool {
+        let lower = query.trim().to_lowercase();
+        let first = lower.split_whitespace().next().unwrap_or("");
+
+        let keywords = ["now", "today", "tomorrow", "yesterday", "unix", "epoch"];
+        if keywords.contains(&first) || lower.starts_with("time in ") {
+            return true;
+        }
+
+        // "days until ...", "weeks since ..."
+        let words: Vec<&str> = lower.split_whitespace().collect();
+        if words.len() >= 3 && (words[1] == "until" || words[1] == "since") {
+            return first == "time" || Self::parse_unit(first).is_some();
+        }
+
+        // Starts with an ISO date or a clock time
+        Self::parse_date(first).is_some() || Self::parse_clock(first).is_some()
+    }
+
+    pub fn evaluate(query: &str) -> Result<DateTimeResult, String> {
+        Self::evaluate_at(query, Self::unix_now(), &Self::local_zone())
+    }
+
+    /// The current wall-clock time in the local zone
+    pub fn local_now() -> NaiveDateTime {
+        Instant::from_unix(Self::unix_now(), Self::local_zone())
+            .map(|instant| instant.local)
+            .unwrap_or_default()
+    }
+
+    fn unix_now() -> i64 {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs() as i64)
+            .unwrap_or(0)
+    }
+
+    fn evaluate_at(query: &str, now: i64, local: &Zone) -> Result<DateTimeResult, String> {
+        let lower = query.trim().to_lowercase();
+        let words: Vec<&str> = lower.split_whitespace().collect();
+        let Some(&first) = words.first() else {
+            return Err("Expected a date or time".to_string());
+        };
+        let now_instant = Instant::from_unix(now, local.clone())?;
+
+        // "time in New York"
+        if let Some(place) = lower.strip_prefix("time in ") {
+            let zone = Self::lookup_zone(place).ok_or_else(|| format!("Unknown time zone '{}'", place))?;
+            return Ok(Self::describe_instant(&now_instant.in_zone(&zone)?, now));
+        }
+
+        // "unix 1700000000" / "epoch" / "unix 2026-10-16"
+        if first == "unix" || first == "epoch" {
+            if words.len() == 1 {
+                return Ok(Self::describe_instant(&now_instant, now));
+            }
+            if words.len() == 2 {
+                if let Ok(seconds) = words[1].parse::<i64>() {
+                    // Values this large are almost certainly milliseconds
+                    let seconds = if seconds.abs() >= 100_000_000_000 { seconds / 1000 } else { seconds };
+                    let utc = Zone::utc();
+                    return Ok(Self::describe_instant(&Instant::from_unix(seconds, utc)?, now));
+                }
+            }
+            let value = Self::evaluate_expression(&words[1..], &now_instant)?;
+            return match value {
+                Value::Instant(instant) => {
+                    let unix = instant.unix().to_string();
+                    let mut result = Self::describe_instant(&instant, now);
+                    result.details.insert(0, result.value.clone());
+                    result.value = unix;
+                    Ok(result)
+                }
+                Value::Span { .. } => Err("Expected a date".to_string()),
+            };
+        }
+
+        // "days until 2026-12-24", "weeks since 2025-01-01"
+        if words.len() >= 3 && (words[1] == "until" || words[1] == "since") {
+            let unit = if first == "time" { None } else { Self::parse_unit(first) };
+            let target = match Self::evaluate_expression(&words[2..], &now_instant)? {
+                Value::Instant(instant) => instant,
+                Value::Span { .. } => return Err("Expected a date".to_string()),
+            };
+            // Plain dates count whole calendar days from today
+            let mut reference = now_instant.in_zone(&target.zone)?;
+            if target.local.time() == NaiveTime::MIN {
+                reference.local = reference.local.date().and_time(NaiveTime::MIN);
+            }
+            let (from, to) = if words[1] == "until" {
+                (reference, target)
+            } else {
+                (target, reference)
+            };
+            let seconds = to.unix() - from.unix();
+            return Ok(Self::describe_span(seconds, Some(&from), Some(&to), unit));
+        }
+
+        match Self::evaluate_expression(&words, &now_instant)? {
+            Value::Instant(instant) => Ok(Self::describe_instant(&instant, now)),
+            Value::Span { seconds, from, to } => Ok(Self::describe_span(seconds, from.as_ref(), to.as_ref(), None)),
+        }
+    }
+
+    /// Evaluate "<base> (+|- <duration or date>)* [in <zone>]"
+    fn evaluate_expression(words: &[&str], now: &Instant) -> Result<Value, String> {
+        // Split off a trailing "in <zone>" / "to <zone>" conversion
+        let (words, target_zone) = match words.iter().rposition(|w| *w == "in" || *w == "to") {
+            Some(pos) if pos > 0 && pos + 1 < words.len() => {
+                let place = words[pos + 1..].join(" ");
+                match Self::lookup_zone(&place) {
+                    Some(zone) => (&words[..pos], Some(zone)),
+                    None => return Err(format!("Unknown time zone '{}'", place)),
+                }
+            }
+            _ => (words, None),
+        };
+
+        // Separate operators from operands: "now", "+", "3", "weeks", ...
+        let mut tokens: Vec<String> = Vec::new();
+        for word in words {
+            if word.len() > 1 && (word.starts_with('+') || word.starts_with('-')) && Self::parse_date(word).is_none() {
+                tokens.push(word[..1].to_string());
+                tokens.push(word[1..].to_string());
+            } else {
+                tokens.push(word.to_string());
+            }
+        }
+
+        let mut groups: Vec<(char, Vec<String>)> = vec![('+', Vec::new())];
+        for token in tokens {
+            if token == "+" || token == "-" {
+                groups.push((token.chars().next().unwrap(), Vec::new()));
+            } else {
+                groups.last_mut().unwrap().1.push(token);
+            }
+        }
+
+        let (_, base) = &groups[0];
+        let mut value = Value::Instant(Self::parse_instant(base, now)?);
+
+        for (op, operand) in &groups[1..] {
+            if let Some(duration) = Self::parse_duration(operand) {
+                value = match value {
+                    Value::Instant(instant) => Value::Instant(instant.shift(&duration, *op == '-')?),
+                    Value::Span { seconds, .. } => {
+                        let delta = Self::duration_seconds(&duration)?;
+                        let seconds = if *op == '-' { seconds - delta } else { seconds + delta };
+                        Value::Span { seconds, from: None, to: None }
+                    }
+                };
+                continue;
+            }
+
+            let other = Self::parse_instant(operand, now)?;
+            value = match (value, op) {
+                (Value::Instant(instant), '-') => Value::Span {
+                    seconds: instant.unix() - other.unix(),
+                    from: Some(other),
+                    to: Some(instant),
+                },
+                _ => return Err("Dates can only be subtracted from each other".to_string()),
+            };
+        }
+
+        if let (Value::Instant(instant), Some(zone)) = (&value, target_zone) {
+            return Ok(Value::Instant(instant.in_zone(&zone)?));
+        }
+        Ok(value)
+    }
+
+    /// Parse "now", "2026-10-16", "2026-10-16 14:00", "14:00 berlin", "tomorrow 9:30"
+    fn parse_instant(words: &[String], now: &Instant) -> Result<Instant, String> {
+        if words.is_empty() {
+            return Err("Expected a date or time".to_string());
+        }
+
+        let mut rest = words;
+        let mut date = None;
+        let mut time = None;
+        let mut fixed_utc = None;
+        let mut is_now = false;
+
+        match rest[0].as_str() {
+            "now" => is_now = true,
+            "today" => date = Some(now.local.date()),
+            "tomorrow" => date = now.local.date().checked_add_days(Days::new(1)),
+            "yesterday" => date = now.local.date().checked_sub_days(Days::new(1)),
+            word => {
+                if let Some(instant) = Self::parse_iso_with_offset(word) {
+                    fixed_utc = Some(instant);
+                } else if let Some((d, t)) = Self::parse_date(word) {
+                    date = Some(d);
+                    time = t;
+                } else if let Some(t) = Self::parse_clock(word) {
+                    time = Some(t);
+                } else {
+                    return Err(format!("Not a date: '{}'", word));
+                }
+            }
+        }
+        rest = &rest[1..];
+
+        // Optional clock time after a date: "2026-10-16 14:00"
+        if time.is_none() && !is_now && fixed_utc.is_none() {
+            if let Some(t) = rest.first().and_then(|w| Self::parse_clock(w)) {
+                time = Some(t);
+                rest = &rest[1..];
+            }
+        }
+
+        // Optional zone the date/time is expressed in: "14:00 berlin"
+        let zone = if rest.is_empty() {
+            now.zone.clone()
+        } else {
+            let place = rest.join(" ");
+            Self::lookup_zone(&place).ok_or_else(|| format!("Unknown time zone '{}'", place))?
+        };
+
+        if let Some(unix) = fixed_utc {
+            return Instant::from_unix(unix, zone);
+        }
+        if is_now {
+            return now.in_zone(&zone);
+        }
+
+        // Dates and times without a zone are read as wall-clock time in that zone
+        let today = now.in_zone(&zone)?.local.date();
+        let local = NaiveDateTime::new(date.unwrap_or(today), time.unwrap_or(NaiveTime::MIN));
+        Ok(Instant { local, zone })
+    }
+
+    /// ISO 8601 date with optional time: "2026-10-16", "2026-10-16T14:00", "2026-10-16T14:00:30"
+    fn parse_date(word: &str) -> Option<(NaiveDate, Option<NaiveTime>)> {
+        let (date_part, time_part) = match word.split_once(['t', 'T']) {
+            Some((d, t)) => (d, Some(t)),
+            None => (word, None),
+        };
+        let date = NaiveDate::parse_from_str(date_part, "%Y-%m-%d").ok()?;
+        match time_part {
+            Some(t) => Some((date, Some(Self::parse_clock(t)?))),
+            None => Some((date, None)),
+        }
+    }
+
+    /// ISO 8601 timestamp with explicit offset: "2026-10-16t14:00:00z", "2026-10-16t14:00+02:00"
+    fn parse_iso_with_offset(word: &str) -> Option<i64> {
+        let upper = word.to_uppercase();
+        for format in ["%Y-%m-%dT%H:%M:%S%:z", "%Y-%m-%dT%H:%M%:z", "%Y-%m-%dT%H:%M:%S%.f%:z"] {
+            if let Ok(dt) = DateTime::parse_from_str(&upper.replace('Z', "+00:00"), format) {
+                return Some(dt.timestamp());
+            }
+        }
+        None
+    }
+
+    /// Clock time: "14:00", "9:30:15", "2pm", "9:30am"
+    fn parse_clock(word: &str) -> Option<NaiveTime> {
+        let (digits, offset) = if let Some(d) = word.strip_suffix("am") {
+            (d, 0)
+        } else if let Some(d) = word.strip_suffix("pm") {
+            (d, 12)
+        } else {
+            (word, 0)
+        };
+        let meridiem = digits.len() != word.len();
+
+        let parts: Vec<&str> = digits.split(':').collect();
+        if parts.len() > 3 || (parts.len() == 1 && !meridiem) {
+            return None;
+        }
+        let mut numbers = parts.iter().map(|p| p.parse::<u32>().ok());
+        let hour = numbers.next()??;
+        let minute = numbers.next().unwrap_or(Some(0))?;
+        let second = numbers.next().unwrap_or(Some(0))?;
+
+        let hour = if meridiem {
+            if hour == 0 || hour > 12 {
+                return None;
+            }
+            hour % 12 + offset
+        } else {
+            hour
+        };
+        NaiveTime::from_hms_opt(hour, minute, second)
+    }
+
+    fn parse_unit(word: &str) -> Option<Unit> {
+        match word {
+            "y" | "yr" | "yrs" | "year" | "years" => Some(Unit::Years),
+            "mo" | "mon" | "month" | "months" => Some(Unit::Months),
+            "w" | "wk" | "wks" | "week" | "weeks" => Some(Unit::Weeks),
+            "d" | "day" | "days" => Some(Unit::Days),
+            "h" | "hr" | "hrs" | "hour" | "hours" => Some(Unit::Hours),
+            "m" | "min" | "mins" | "minute" | "minutes" => Some(Unit::Minutes),
+            "s" | "sec" | "secs" | "second" | "seconds" => Some(Unit::Seconds),
+            _ => None,
+        }
+    }
+
+    /// Parse "3 weeks", "2 days 4 hours", "90min", "1.5 h"
+    fn parse_duration(words: &[String]) -> Option<Vec<(f64, Unit)>> {
+        let mut parts = Vec::new();
+        let mut pending: Option<f64> = None;
+
+        for word in words {
+            match pending.take() {
+                Some(amount) => parts.push((amount, Self::parse_unit(word)?)),
+                None => {
+                    let split = word.find(|c: char| !(c.is_ascii_digit() || c == '.')).unwrap_or(word.len());
+                    let amount = word[..split].parse::<f64>().ok()?;
+                    if split == word.len() {
+                        pending = Some(amount);
+                    } else {
+                        parts.push((amount, Self::parse_unit(&word[split..])?));
+                    }
+                }
+            }
+        }
+
+        if pending.is_some() || parts.is_empty() {
+            return None;
+        }
+        Some(parts)
+    }
+
+    /// Length of a duration in seconds (months and years use their average length)
+    fn duration_seconds(duration: &[(f64, Unit)]) -> Result<i64, String> {
+        let seconds: f64 = duration
+            .iter()
+            .map(|(amount, unit)| amount * unit.seconds())
+            .sum();
+        if !seconds.is_finite() || seconds.abs() > 1e15 {
+            return Err("Duration too large".to_string());
+        }
+        Ok(seconds.round() as i64)
+    }
+
+    fn lookup_zone(place: &str) -> Option<Zone> {
+        let place = place.trim();
+        let key = place.to_lowercase().replace('_', " ");
+
+        // Fixed offsets: "utc+2", "gmt-05:30", "+05:30"
+        let offset_part = key
+            .strip_prefix("utc")
+            .or_else(|| key.strip_prefix("gmt"))
+            .unwrap_or(&key);
+        if offset_part.starts_with('+') || offset_part.starts_with('-') {
+            let offset = Self::parse_offset(offset_part)?;
+            return Some(Zone {
+                label: format!("UTC{}", Self::format_offset(offset)),
+                tz: TimeZone::fixed(offset).ok()?,
+            });
+        }
+
+        let name = CITY_ALIASES
+            .iter()
+            .find(|(alias, _)| *alias == key)
+            .map(|(_, zone)| zone.to_string())
+            .or_else(|| Self::zone_index().get(&key).cloned())?;
+
+        let tz = TimeZone::from_posix_tz(&name).ok()?;
+        Some(Zone { label: name, tz })
+    }
+
+    fn parse_offset(text: &str) -> Option<i32> {
+        let sign = if text.starts_with('-') { -1 } else { 1 };
+        let body = &text[1..];
+        let (hours, minutes) = match body.split_once(':') {
+            Some((h, m)) => (h.parse::<i32>().ok()?, m.parse::<i32>().ok()?),
+            None if body.len() == 4 => (body[..2].parse().ok()?, body[2..].parse().ok()?),
+            None => (body.parse().ok()?, 0),
+        };
+        if hours > 14 || minutes >= 60 {
+            return None;
+        }
+        Some(sign * (hours * 3600 + minutes * 60))
+    }
+
+    fn format_offset(offset: i32) -> String {
+        let sign = if offset < 0 { '-' } else { '+' };
+        let offset = offset.abs();
+        format!("{}{:02}:{:02}", sign, offset / 3600, (offset % 3600) / 60)
+    }
+
+    /// Map of "new york" / "america/new york" -> "America/New_York", built from the local tzdata
+    fn zone_index() -> &'static HashMap<String, String> {
+        static INDEX: OnceLock<HashMap<String, String>> = OnceLock::new();
+        INDEX.get_or_init(|| {
+            let mut index = HashMap::new();
+            let root = Path::new("/usr/share/zoneinfo");
+
+            let mut names = Vec::new();
+            for table in ["tzdata.zi", "zone1970.tab", "zone.tab"] {
+                if let Ok(contents) = std::fs::read_to_string(root.join(table)) {
+                    for line in contents.lines() {
+                        if line.starts_with('#') {
+                            continue;
+                        }
+                        let fields: Vec<&str> = line.split_whitespace().collect();
+                        match (table, fields.as_slice()) {
+                            // tzdata.zi: "Z America/New_York ..." and "L America/New_York US/Eastern"
+                            ("tzdata.zi", ["Z", name, ..]) => names.push(name.to_string()),
+                            ("tzdata.zi", ["L", _, alias]) => names.push(alias.to_string()),
+                            (_, [_, _, name, ..]) if table != "tzdata.zi" => names.push(name.to_string()),
+                            _ => {}
+                        }
+                    }
+                }
+            }
+
+            for name in names {
+                let full = name.to_lowercase().replace('_', " ");
+                let city = full.rsplit('/').next().unwrap_or(&full).to_string();
+                index.insert(full, name.clone());
+                // The first zone for a city wins ("America/..." over "US/...")
+                index.entry(city).or_insert(name);
+            }
+            index
+        })
+    }
+
+    fn local_zone() -> Zone {
+        let tz = TimeZone::local().unwrap_or_else(|_| TimeZone::utc());
+
+        // Try to find a readable name for the local zone
+        let label = std::env::var("TZ")
+            .ok()
+            .map(|tz| tz.trim_start_matches(':').to_string())
+            .filter(|tz| !tz.is_empty())
+            .or_else(|| {
+                std::fs::read_link("/etc/localtime").ok().and_then(|target| {
+                    let target = target.to_string_lossy().to_string();
+                    target.split_once("zoneinfo/").map(|(_, name)| name.to_string())
+                })
+            })
+            .or_else(|| std::fs::read_to_string("/etc/timezone").ok().map(|s| s.trim().to_string()))
+            .unwrap_or_else(|| "Local".to_string());
+
+        Zone { label, tz }
+    }
+
+    fn describe_instant(instant: &Instant, now: i64) -> DateTimeResult {
+        let (offset, abbreviation) = instant.zone.offset_at(instant.unix());
+        let value = format!("{} {}", instant.local.format("%a, %d %b %Y %H:%M:%S"), abbreviation);
+
+        let iso = format!("{}{}", instant.local.format("%Y-%m-%dT%H:%M:%S"), Self::format_offset(offset));
+        let mut details = vec![
+            iso,
+            format!("Unix {}", instant.unix()),
+            format!("Week {}, day {} of the year", instant.local.iso_week().week(), instant.local.ordinal()),
+        ];
+        if instant.zone.label != abbreviation {
+            details.push(instant.zone.label.clone());
+        }
+        let relative = instant.unix() - now;
+        if relative.abs() >= 60 {
+            let span = Self::format_span_short(relative.abs());
+            details.push(if relative > 0 { format!("in {}", span) } else { format!("{} ago", span) });
+        }
+
+        DateTimeResult { value, details }
+    }
+
+    fn describe_span(seconds: i64, from: Option<&Instant>, to: Option<&Instant>, unit: Option<Unit>) -> DateTimeResult {
+        let sign = if seconds < 0 { "-" } else { "" };
+        let abs = seconds.abs();
+
+        // Calendar difference between the two wall-clock dates, if we have them
+        let calendar = match (from, to) {
+            (Some(from), Some(to)) => Some(Self::calendar_difference(from.local.date(), to.local.date())),
+            _ => None,
+        };
+
+        let value = match unit {
+            Some(Unit::Years) | Some(Unit::Months) if calendar.is_some() => {
+                let (years, months, days) = calendar.unwrap();
+                let total_months = years * 12 + months;
+                if unit == Some(Unit::Years) {
+                    format!("{}{} years", sign, Self::trim_float((total_months as f64 + days as f64 / 30.44) / 12.0))
+                } else {
+                    format!("{}{} months", sign, Self::trim_float(total_months as f64 + days as f64 / 30.44))
+                }
+            }
+            Some(unit) => format!("{}{} {}", sign, Self::trim_float(abs as f64 / unit.seconds()), unit.name()),
+            None if abs % 86_400 == 0 => format!("{}{} days", sign, abs / 86_400),
+            None => format!("{}{}", sign, Self::format_span_short(abs)),
+        };
+
+        let mut details = Vec::new();
+        if let Some((years, months, days)) = calendar {
+            if years > 0 || months > 0 {
+                let parts: Vec<String> = [(years, "years"), (months, "months"), (days, "days")]
+                    .iter()
+                    .filter(|(amount, _)| *amount > 0)
+                    .map(|(amount, name)| format!("{} {}", amount, name))
+                    .collect();
+                details.push(format!("{}{}", sign, parts.join(", ")));
+            }
+        }
+        let days = abs / 86_400;
+        if days >= 7 {
+            details.push(format!("{}{} weeks, {} days", sign, days / 7, days % 7));
+        }
+        if unit != Some(Unit::Days) && days > 0 {
+            details.push(format!("{}{} days", sign, Self::trim_float(abs as f64 / 86_400.0)));
+        }
+        details.push(format!("{}{} hours", sign, Self::trim_float(abs as f64 / 3600.0)));
+        details.push(format!("{}{} seconds", sign, abs));
+        details.retain(|d| *d != value);
+
+        DateTimeResult { value, details }
+    }
+
+    /// Whole years, months and days from `from` to `to` (both dates, `from` <= `to` or swapped)
+    fn calendar_difference(from: NaiveDate, to: NaiveDate) -> (i64, i64, i64) {
+        let (from, to) = if from <= to { (from, to) } else { (to, from) };
+        let mut months = (to.year() - from.year()) as i64 * 12 + to.month() as i64 - from.month() as i64;
+        if to.day() < from.day() {
+            months -= 1;
+        }
+        let anchor = from.checked_add_months(Months::new(months as u32)).unwrap_or(from);
+        let days = (to - anchor).num_days();
+        (months / 12, months % 12, days)
+    }
+
+    /// "3d 4h 5m", "2h 30m", "45s"
+    fn format_span_short(seconds: i64) -> String {
+        let days = seconds / 86_400;
+        let hours = (seconds % 86_400) / 3600;
+        let minutes = (seconds % 3600) / 60;
+        let secs = seconds % 60;
+
+        let mut parts = Vec::new();
+        if days > 0 {
+            parts.push(format!("{}d", days));
+        }
+        if hours > 0 {
+            parts.push(format!("{}h", hours));
+        }
+        if minutes > 0 && days == 0 {
+            parts.push(format!("{}m", minutes));
+        }
+        if secs > 0 && days == 0 && hours == 0 {
+            parts.push(format!("{}s", secs));
+        }
+        if parts.is_empty() {
+            parts.push("0s".to_string());
+        }
+        parts.join(" ")
+    }
+
+    fn trim_float(value: f64) -> String {
+        format!("{:.2}", value)
+            .trim_end_matches('0')
+            .trim_end_matches('.')
+            .to_string()
+    }
+}
+
+impl Unit {
+    fn seconds(&self) -> f64 {
+        match self {
+            Unit::Years => 365.2425 * 86_400.0,
+            Unit::Months => 30.436875 * 86_400.0,
+            Unit::Weeks => 7.0 * 86_400.0,
+            Unit::Days => 86_400.0,
+            Unit::Hours => 3600.0,
+            Unit::Minutes => 60.0,
+            Unit::Seconds => 1.0,
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            Unit::Years => "years",
+            Unit::Months => "months",
+            Unit::Weeks => "weeks",
+            Unit::Days => "days",
+            Unit::Hours => "hours",
+            Unit::Minutes => "minutes",
+            Unit::Seconds => "seconds",
+        }
+    }
+}
+
+impl Zone {
+    fn utc() -> Self {
+        Zone { label: "UTC".to_string(), tz: TimeZone::utc() }
+    }
+
+    /// UTC offset in seconds and abbreviation ("CEST") at a point in time
+    fn offset_at(&self, unix: i64) -> (i32, String) {
+        let (offset, designation) = match self.tz.find_local_time_type(unix) {
+            Ok(local_type) => (local_type.ut_offset(), local_type.time_zone_designation().to_string()),
+            Err(_) => (0, String::new()),
+        };
+        // Fixed offsets have no abbreviation
+        let designation = match designation.as_str() {
+            "" if offset == 0 => "UTC".to_string(),
+            "" => format!("UTC{}", DateTimeCalculator::format_offset(offset)),
+            _ => designation,
+        };
+        (offset, designation)
+    }
+}
+
+impl Instant {
+    fn from_unix(unix: i64, zone: Zone) -> Result<Self, String> {
+        let (offset, _) = zone.offset_at(unix);
+        let local = unix
+            .checked_add(offset as i64)
+            .and_then(|wall| DateTime::from_timestamp(wall, 0))
+            .ok_or_else(|| "Date out of range".to_string())?
+            .naive_utc();
+        Ok(Instant { local, zone })
+    }
+
+    fn unix(&self) -> i64 {
+        // Guess with the offset at the wall-clock time, then correct once (handles DST edges)
+        let wall = self.local.and_utc().timestamp();
+        let (guess, _) = self.zone.offset_at(wall);
+        let (offset, _) = self.zone.offset_at(wall - guess as i64);
+        wall - offset as i64
+    }
+
+    fn in_zone(&self, zone: &Zone) -> Result<Instant, String> {
+        Instant::from_unix(self.unix(), zone.clone())
+    }
+
+    /// Add or subtract a duration. Calendar units keep the wall-clock time, clock units are exact.
+    fn shift(&self, duration: &[(f64, Unit)], subtract: bool) -> Result<Instant, String> {
+        let mut instant = self.clone();
+        let overflow = || "Date out of range".to_string();
+
+        for (amount, unit) in duration {
+            let calendar = matches!(unit, Unit::Years | Unit::Months | Unit::Weeks | Unit::Days);
+            if calendar && amount.fract() == 0.0 {
+                if amount.abs() > f64::from(u32::MAX) {
+                    return Err(overflow());
+                }
+                let count = amount.abs() as u32;
+                let local = instant.local;
+                let shifted = match (unit, subtract) {
+                    (Unit::Years, _) => {
+                        let months = Months::new(count.checked_mul(12).ok_or_else(overflow)?);
+                        if subtract { local.checked_sub_months(months) } else { local.checked_add_months(months) }
+                    }
+                    (Unit::Months, false) => local.checked_add_months(Months::new(count)),
+                    (Unit::Months, true) => local.checked_sub_months(Months::new(count)),
+                    (Unit::Weeks, false) => local.checked_add_days(Days::new(count as u64 * 7)),
+                    (Unit::Weeks, true) => local.checked_sub_days(Days::new(count as u64 * 7)),
+                    (_, false) => local.checked_add_days(Days::new(count as u64)),
+                    (_, true) => local.checked_sub_days(Days::new(count as u64)),
+                };
+                instant.local = shifted.ok_or_else(overflow)?;
+            } else {
+                if matches!(unit, Unit::Years | Unit::Months) {
+                    return Err("Fractional months and years are ambiguous".to_string());
+                }
+                let seconds = DateTimeCalculator::duration_seconds(&[(*amount, *unit)])?;
+                let seconds = if subtract { -seconds } else { seconds };
+                let unix = instant.unix().checked_add(seconds).ok_or_else(overflow)?;
+                instant = Instant::from_unix(unix, instant.zone.clone())?;
+            }
+        }
+
+        Ok(instant)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 2026-10-16 12:00:00 UTC
+    const NOW: i64 = 1_792_152_000;
+
+    fn eval(query: &str) -> DateTimeResult {
+        DateTimeCalculator::evaluate_at(query, NOW, &Zone::utc()).unwrap()
+    }
+
+    #[test]
+    fn test_date_arithmetic() {
+        assert_eq!(eval("now + 3 weeks").value, "Fri, 06 Nov 2026 12:00:00 UTC");
+        assert_eq!(eval("2026-01-31 + 1 month").value, "Sat, 28 Feb 2026 00:00:00 UTC");
+        assert_eq!(eval("2026-10-16 - 2025-01-01").value, "653 days");
+        assert_eq!(eval("days until 2026-12-24").value, "69 days");
+        let overflow = DateTimeCalculator::evaluate_at("now + 400000000 years", NOW, &Zone::utc());
+        assert_eq!(overflow.unwrap_err(), "Date out of range");
+        assert!(DateTimeCalculator::evaluate_at("now + 5000000000 months", NOW, &Zone::utc()).is_err());
+    }
+
+    #[test]
+    fn test_epoch_and_offsets() {
+        assert_eq!(eval("unix 1700000000").value, "Tue, 14 Nov 2023 22:13:20 UTC");
+        assert_eq!(eval("unix 2026-10-16t12:00:00z").value, NOW.to_string());
+        assert_eq!(eval("14:00 utc+2 in utc").value, "Fri, 16 Oct 2026 12:00:00 UTC");
+        assert!(DateTimeCalculator::is_datetime_query("time in New York"));
+        assert!(!DateTimeCalculator::is_datetime_query("2+2"));
+        assert!(!DateTimeCalculator::is_datetime_query("   "));
+        assert!(DateTimeCalculator::evaluate_at("  ", NOW, &Zone::utc()).is_err());
+
+        // Out of range instants are an error, not the 1970 epoch
+        let out_of_range = |query: &str| DateTimeCalculator::evaluate_at(query, NOW, &Zone::utc()).unwrap_err();
+        assert_eq!(out_of_range("unix 99999999999999999"), "Date out of range");
+        assert_eq!(out_of_range("unix -99999999999999999"), "Date out of range");
+        assert_eq!(out_of_range("now + 10000000000000 seconds"), "Date out of range");
+    }
+}
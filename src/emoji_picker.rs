@@ -1,33 +1,153 @@
+use crate::config::EmojiConfig;
+use emojis::{Group, SkinTone};
 use fuzzy_matcher::FuzzyMatcher;
 use fuzzy_matcher::skim::SkimMatcherV2;
-use std::io::Write;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
 
 #[derive(Debug, Clone)]
 pub struct Emoji {
-    pub emoji: String,
+    pub emoji: String, // Shown and inserted, with the preferred variant applied
+    pub base: String,  // Variant without skin tone, remembered choices are keyed by it
     pub name: String,
     pub keywords: Vec<String>,
+    pub favorite: bool,
+    pub group: Group,
+}
+
+/// How often and when each emoji was inserted, plus pinned favorites.
+/// Kept in ~/.local/share/poppi_launcher/emoji_usage.json, keyed by `Emoji::base`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct EmojiUsage {
+    #[serde(default)]
+    pub uses: HashMap<String, EmojiUse>,
+    #[serde(default)]
+    pub favorites: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EmojiUse {
+    pub count: u32,
+    pub last_used: u64, // Unix timestamp
 }
 
 pub struct EmojiPicker {
     emojis: Vec<Emoji>,
+    usage: EmojiUsage,
     matcher: SkimMatcherV2,
 }
 
+// Personal emoji (favorites, then most used) shown before the rest on an empty query
+const MAX_PERSONAL: usize = 16;
+
+// Tabs shown when browsing with an empty query: the personal emoji, then every group
+// of `emojis::Group` in order. Icon and tooltip.
+pub const TABS: &[(&str, &str)] = &[
+    ("🕘", "Frequently used"),
+    ("😀", "Smileys & Emotion"),
+    ("👋", "People & Body"),
+    ("🐻", "Animals & Nature"),
+    ("🍔", "Food & Drink"),
+    ("✈️", "Travel & Places"),
+    ("⚽", "Activities"),
+    ("💡", "Objects"),
+    ("🔣", "Symbols"),
+    ("🏁", "Flags"),
+];
+
+// Translated names and keywords for the most common emoji, so searching in another
+// language works without the full CLDR data installed
+const BUNDLED_KEYWORDS: &[(&str, &str)] = &[
+    ("de", include_str!("../data/emoji_keywords/de.tsv")),
+    ("es", include_str!("../data/emoji_keywords/es.tsv")),
+    ("fr", include_str!("../data/emoji_keywords/fr.tsv")),
+];
+
+// Complete CLDR annotations as installed by distributions (e.g. unicode-cldr-core)
+const CLDR_DIRS: &[&str] = &[
+    "/usr/share/unicode/cldr/common/annotations",
+    "/usr/share/unicode/cldr/common/annotationsDerived",
+];
+
+impl EmojiUsage {
+    fn path() -> PathBuf {
+        dirs::data_dir()
+            .unwrap_or_else(|| PathBuf::from("."))
+            .join("poppi_launcher")
+            .join("emoji_usage.json")
+    }
+
+    pub fn load() -> Self {
+        fs::read_to_string(Self::path())
+            .ok()
+            .and_then(|contents| serde_json::from_str(&contents).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self) -> Result<(), Box<dyn std::error::Error>> {
+        let path = Self::path();
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(&path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+
+    /// Use count weighted by how recent the last use was, halving every week
+    fn frecency(&self, base: &str, now: u64) -> f64 {
+        match self.uses.get(base) {
+            Some(entry) => {
+                let age_weeks = now.saturating_sub(entry.last_used) as f64 / (7.0 * 86400.0);
+                entry.count as f64 * 0.5f64.powf(age_weeks)
+            }
+            None => 0.0,
+        }
+    }
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
 impl EmojiPicker {
-    pub fn new() -> Self {
-        let emojis = Self::load_emojis();
+    pub fn new(config: &EmojiConfig) -> Self {
+        let usage = EmojiUsage::load();
+        let localized = Self::load_localized_keywords(&Self::search_languages(config));
+        let mut emojis = Self::load_emojis(Self::parse_skin_tone(&config.skin_tone), &Self::load_variant_choices(), &localized);
+        for emoji in &mut emojis {
+            emoji.favorite = usage.favorites.contains(&emoji.base);
+        }
         let matcher = SkimMatcherV2::default();
-        Self { emojis, matcher }
+        Self { emojis, usage, matcher }
     }
 
-    fn load_emojis() -> Vec<Emoji> {
+    fn load_emojis(
+        skin_tone: SkinTone,
+        choices: &HashMap<String, String>,
+        localized: &HashMap<String, Vec<String>>,
+    ) -> Vec<Emoji> {
         // Load all emojis from the emojis crate
         let mut emoji_list = Vec::with_capacity(3000); // Pre-allocate for performance
         
         for emoji_data in emojis::iter() {
             let emoji_char = emoji_data.as_str();
             let name = emoji_data.name().to_string();
+
+            // A variant picked earlier wins over the default skin tone
+            let preferred = match choices.get(emoji_char) {
+                Some(choice) => choice.clone(),
+                None => emoji_data
+                    .with_skin_tone(skin_tone)
+                    .unwrap_or(emoji_data)
+                    .as_str()
+                    .to_string(),
+            };
             
             // Build keywords from name (split by space, underscore, hyphen)
             let mut keywords = Vec::new();
@@ -50,11 +170,19 @@ impl EmojiPicker {
                     }
                 }
             }
+
+            // Names and keywords in the user's languages, English stays as fallback
+            if let Some(translations) = localized.get(&Self::annotation_key(emoji_char)) {
+                keywords.extend(translations.iter().cloned());
+            }
             
             emoji_list.push(Emoji {
-                emoji: emoji_char.to_string(),
+                emoji: preferred,
+                base: emoji_char.to_string(),
                 name,
                 keywords,
+                favorite: false,
+                group: emoji_data.group(),
             });
         }
         
@@ -63,7 +191,11 @@ impl EmojiPicker {
 
     pub fn search(&self, query: &str) -> Vec<(&Emoji, i64)> {
         if query.is_empty() {
-            return self.emojis.iter().map(|e| (e, 0)).take(24).collect();
+            return self.personal().into_iter()
+                .chain(self.emojis.iter().filter(|e| !e.favorite && self.usage.frecency(&e.base, now()) == 0.0))
+                .map(|e| (e, 0))
+                .take(24)
+                .collect();
         }
 
         let query_lower = query.to_lowercase();
@@ -81,7 +213,7 @@ impl EmojiPicker {
             let score = name_score.unwrap_or(0).max(keyword_score.unwrap_or(0));
             
             if score > 0 {
-                results.push((emoji, score));
+                results.push((emoji, score + self.usage_boost(emoji)));
             }
         }
 
@@ -91,131 +223,299 @@ impl EmojiPicker {
         results
     }
 
-    pub fn insert_emoji(emoji: &str) -> Result<(), Box<dyn std::error::Error>> {
-        use std::process::Command;
-        use std::io::Write;
-        
-        // Check if we're on Wayland or X11
-        let is_wayland = std::env::var("WAYLAND_DISPLAY").is_ok() || 
-                         std::env::var("XDG_SESSION_TYPE").map(|s| s == "wayland").unwrap_or(false);
-        
-        let mut insertion_successful = false;
-        
-        if is_wayland {
-            // Try wtype for Wayland (if available)
-            if Self::has_command("wtype") {
-                let output = Command::new("wtype")
-                    .arg(emoji)
-                    .output();
-                
-                if let Ok(output) = output {
-                    if output.status.success() {
-                        insertion_successful = true;
-                    }
-                }
+    /// All emoji of a tab in `TABS`, in CLDR order
+    pub fn browse(&self, tab: usize) -> Vec<&Emoji> {
+        match tab.checked_sub(1).and_then(|group| Group::iter().nth(group)) {
+            Some(group) => self.emojis.iter().filter(|e| e.group == group).collect(),
+            None => self.personal(),
+        }
+    }
+
+    /// Browsing starts on the personal tab, or on smileys while it is still empty
+    pub fn default_tab(&self) -> usize {
+        if self.personal().is_empty() { 1 } else { 0 }
+    }
+
+    /// Favorites followed by the most frecent emoji
+    fn personal(&self) -> Vec<&Emoji> {
+        let now = now();
+        let mut recent: Vec<(&Emoji, f64)> = self.emojis
+            .iter()
+            .filter(|e| !e.favorite)
+            .map(|e| (e, self.usage.frecency(&e.base, now)))
+            .filter(|(_, frecency)| *frecency > 0.0)
+            .collect();
+        recent.sort_by(|a, b| b.1.total_cmp(&a.1));
+
+        let favorites = self.usage.favorites
+            .iter()
+            .filter_map(|base| self.emojis.iter().find(|e| e.base == *base));
+        favorites
+            .chain(recent.into_iter().map(|(e, _)| e))
+            .take(MAX_PERSONAL)
+            .collect()
+    }
+
+    /// Extra search score for emoji the user inserts often or has pinned
+    fn usage_boost(&self, emoji: &Emoji) -> i64 {
+        let frecency = self.usage.frecency(&emoji.base, now());
+        let favorite = if emoji.favorite { 25 } else { 0 };
+        (20.0 * frecency.ln_1p()) as i64 + favorite
+    }
+
+    /// Count an insertion towards the recently and frequently used emoji
+    pub fn record_use(base: &str) {
+        let mut usage = EmojiUsage::load();
+        let entry = usage.uses.entry(base.to_string()).or_insert(EmojiUse { count: 0, last_used: 0 });
+        entry.count += 1;
+        entry.last_used = now();
+        if let Err(e) = usage.save() {
+            eprintln!("Error saving emoji usage: {}", e);
+        }
+    }
+
+    /// Pin or unpin an emoji as favorite
+    pub fn toggle_favorite(&mut self, base: &str) -> Result<(), Box<dyn std::error::Error>> {
+        // Reload so uses recorded since startup aren't overwritten
+        self.usage = EmojiUsage::load();
+        let favorite = !self.usage.favorites.iter().any(|b| b == base);
+        if favorite {
+            self.usage.favorites.push(base.to_string());
+        } else {
+            self.usage.favorites.retain(|b| b != base);
+        }
+        if let Some(emoji) = self.emojis.iter_mut().find(|e| e.base == base) {
+            emoji.favorite = favorite;
+        }
+        self.usage.save()
+    }
+
+    /// Skin tones of an emoji followed by its gender and hair variants ("man running",
+    /// "woman: red hair"), each in the skin tone currently shown
+    pub fn variants(emoji: &Emoji) -> Vec<String> {
+        let Some(base) = emojis::get(&emoji.base) else {
+            return vec![emoji.emoji.clone()];
+        };
+        let tone = emojis::get(&emoji.emoji)
+            .and_then(|e| e.skin_tone())
+            .unwrap_or(SkinTone::Default);
+
+        let mut variants: Vec<String> = match base.skin_tones() {
+            Some(tones) => tones.map(|e| e.as_str().to_string()).collect(),
+            None => vec![base.as_str().to_string()],
+        };
+
+        let family = Self::family(base.name());
+        for member in emojis::iter().filter(|e| e.as_str() != base.as_str() && Self::family(e.name()) == family) {
+            let variant = member.with_skin_tone(tone).unwrap_or(member).as_str().to_string();
+            if !variants.contains(&variant) {
+                variants.push(variant);
             }
-            
-            // Fallback: try ydotool for Wayland (if available)
-            if !insertion_successful && Self::has_command("ydotool") {
-                let mut child = Command::new("ydotool")
-                    .arg("type")
-                    .arg("--file")
-                    .arg("-")
-                    .stdin(std::process::Stdio::piped())
-                    .spawn();
-                
-                if let Ok(mut child) = child {
-                    if let Some(stdin) = child.stdin.as_mut() {
-                        if stdin.write_all(emoji.as_bytes()).is_ok() {
-                            if child.wait().map(|s| s.success()).unwrap_or(false) {
-                                insertion_successful = true;
-                            }
-                        }
+        }
+        variants
+    }
+
+    /// Name without the leading person/man/woman, so "man running" and "person running"
+    /// share a family. Hair styles ("woman: curly hair") belong to the plain person.
+    fn family(name: &str) -> &str {
+        let core = ["person", "man", "woman"]
+            .iter()
+            .find_map(|who| {
+                let rest = name.strip_prefix(who)?;
+                (rest.is_empty() || rest.starts_with(' ') || rest.starts_with(':')).then_some(rest)
+            })
+            .unwrap_or(name)
+            .trim_start();
+        if core.starts_with(':') { "" } else { core }
+    }
+
+    fn parse_skin_tone(name: &str) -> SkinTone {
+        match name.to_lowercase().replace(['_', ' '], "-").as_str() {
+            "light" => SkinTone::Light,
+            "medium-light" => SkinTone::MediumLight,
+            "medium" => SkinTone::Medium,
+            "medium-dark" => SkinTone::MediumDark,
+            "dark" => SkinTone::Dark,
+            _ => SkinTone::Default,
+        }
+    }
+
+    /// Languages searched besides English: the configured ones, or those of the locale
+    /// ($LANGUAGE, $LC_ALL, $LC_MESSAGES, $LANG). "de_CH.UTF-8" becomes "de_CH".
+    fn search_languages(config: &EmojiConfig) -> Vec<String> {
+        let requested: Vec<String> = if config.languages.is_empty() {
+            ["LANGUAGE", "LC_ALL", "LC_MESSAGES", "LANG"]
+                .iter()
+                .filter_map(|var| std::env::var(var).ok())
+                .find(|value| !value.is_empty())
+                .map(|value| value.split(':').map(str::to_string).collect())
+                .unwrap_or_default()
+        } else {
+            config.languages.clone()
+        };
+
+        let mut languages: Vec<String> = Vec::new();
+        for language in requested {
+            let language = language.split(['.', '@']).next().unwrap_or("").trim().replace('-', "_");
+            let english = language == "en" || language.starts_with("en_");
+            if !language.is_empty() && !english && language != "C" && language != "POSIX" && !languages.contains(&language) {
+                languages.push(language);
+            }
+        }
+        languages
+    }
+
+    /// Translated names and keywords per emoji, from the bundled tables and any CLDR
+    /// annotation files found. A regional file ("de_CH") is read after its language ("de").
+    fn load_localized_keywords(languages: &[String]) -> HashMap<String, Vec<String>> {
+        let user_dir = dirs::data_dir()
+            .unwrap_or_else(|| PathBuf::from("."))
+            .join("poppi_launcher")
+            .join("cldr");
+        let mut dirs = vec![user_dir];
+        dirs.extend(CLDR_DIRS.iter().map(PathBuf::from));
+
+        let mut keywords: HashMap<String, Vec<String>> = HashMap::new();
+        for language in languages {
+            let base = language.split('_').next().unwrap_or(language);
+            let mut names = vec![base];
+            if base != language {
+                names.push(language);
+            }
+
+            for name in names {
+                let mut found = false;
+                for dir in &dirs {
+                    if let Ok(xml) = fs::read_to_string(dir.join(format!("{}.xml", name))) {
+                        Self::parse_cldr_annotations(&xml, &mut keywords);
+                        found = true;
                     }
                 }
-            }
-        } else {
-            // Try xdotool for X11
-            if Self::has_command("xdotool") {
-                let output = Command::new("xdotool")
-                    .arg("type")
-                    .arg("--clearmodifiers")
-                    .arg(emoji)
-                    .output();
-                
-                if let Ok(output) = output {
-                    if output.status.success() {
-                        insertion_successful = true;
+                if !found {
+                    if let Some((_, table)) = BUNDLED_KEYWORDS.iter().find(|(lang, _)| *lang == name) {
+                        Self::parse_keyword_table(table, &mut keywords);
                     }
                 }
             }
         }
-        
-        // If insertion failed or no tool available, copy to clipboard
-        if !insertion_successful {
-            Self::copy_to_clipboard(emoji)?;
+        keywords
+    }
+
+    /// Lines of `<annotation cp="😀">Gesicht | grinsen</annotation>`; the `type="tts"`
+    /// variant holds the emoji's name
+    fn parse_cldr_annotations(xml: &str, keywords: &mut HashMap<String, Vec<String>>) {
+        for line in xml.lines() {
+            let Some(rest) = line.trim().strip_prefix("<annotation cp=\"") else {
+                continue;
+            };
+            let Some((emoji, rest)) = rest.split_once('"') else {
+                continue;
+            };
+            let Some(text) = rest
+                .split_once('>')
+                .and_then(|(_, text)| text.strip_suffix("</annotation>"))
+            else {
+                continue;
+            };
+            Self::add_keywords(keywords, &Self::unescape_xml(emoji), &Self::unescape_xml(text));
         }
-        
-        Ok(())
     }
-    
-    fn copy_to_clipboard(text: &str) -> Result<(), Box<dyn std::error::Error>> {
-        use std::process::{Command, Stdio};
-        
-        // Try wl-copy for Wayland
-        if Self::has_command("wl-copy") {
-            let mut child = Command::new("wl-copy")
-                .stdin(Stdio::piped())
-                .spawn()?;
-            
-            if let Some(stdin) = child.stdin.as_mut() {
-                stdin.write_all(text.as_bytes())?;
+
+    /// Bundled tables: an emoji, a tab, then its name and keywords separated by " | "
+    fn parse_keyword_table(table: &str, keywords: &mut HashMap<String, Vec<String>>) {
+        for line in table.lines().filter(|line| !line.starts_with('#')) {
+            if let Some((emoji, text)) = line.split_once('\t') {
+                Self::add_keywords(keywords, emoji, text);
             }
-            child.wait()?;
-            return Ok(());
         }
-        
-        // Try xclip for X11
-        if Self::has_command("xclip") {
-            let mut child = Command::new("xclip")
-                .arg("-selection")
-                .arg("clipboard")
-                .stdin(Stdio::piped())
-                .spawn()?;
-            
-            if let Some(stdin) = child.stdin.as_mut() {
-                stdin.write_all(text.as_bytes())?;
+    }
+
+    fn add_keywords(keywords: &mut HashMap<String, Vec<String>>, emoji: &str, text: &str) {
+        let entry = keywords.entry(Self::annotation_key(emoji)).or_default();
+        for keyword in text.split('|').map(|k| k.trim().to_lowercase()) {
+            if !keyword.is_empty() && !entry.contains(&keyword) {
+                entry.push(keyword);
             }
-            child.wait()?;
-            return Ok(());
         }
-        
-        // Try xsel as fallback
-        if Self::has_command("xsel") {
-            let mut child = Command::new("xsel")
-                .arg("--clipboard")
-                .arg("--input")
-                .stdin(Stdio::piped())
-                .spawn()?;
-            
-            if let Some(stdin) = child.stdin.as_mut() {
-                stdin.write_all(text.as_bytes())?;
-            }
-            child.wait()?;
-            return Ok(());
+    }
+
+    /// CLDR usually leaves out the emoji presentation selector, the emojis crate doesn't
+    fn annotation_key(emoji: &str) -> String {
+        emoji.replace('\u{FE0F}', "")
+    }
+
+    fn unescape_xml(text: &str) -> String {
+        text.replace("&lt;", "<")
+            .replace("&gt;", ">")
+            .replace("&quot;", "\"")
+            .replace("&apos;", "'")
+            .replace("&amp;", "&")
+    }
+
+    fn variants_path() -> PathBuf {
+        dirs::data_dir()
+            .unwrap_or_else(|| PathBuf::from("."))
+            .join("poppi_launcher")
+            .join("emoji_variants.json")
+    }
+
+    fn load_variant_choices() -> HashMap<String, String> {
+        fs::read_to_string(Self::variants_path())
+            .ok()
+            .and_then(|contents| serde_json::from_str(&contents).ok())
+            .unwrap_or_default()
+    }
+
+    /// Remember the variant picked for an emoji so it is shown and inserted next time
+    pub fn remember_variant(base: &str, variant: &str) -> Result<(), Box<dyn std::error::Error>> {
+        let mut choices = Self::load_variant_choices();
+        choices.insert(base.to_string(), variant.to_string());
+
+        let path = Self::variants_path();
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
         }
-        
-        Err("No clipboard tool available (wl-copy, xclip, or xsel)".into())
-    }
-    
-    fn has_command(cmd: &str) -> bool {
-        use std::process::Command;
-        Command::new("sh")
-            .arg("-c")
-            .arg(&format!("command -v {}", cmd))
-            .output()
-            .map(|o| o.status.success())
-            .unwrap_or(false)
+        fs::write(&path, serde_json::to_string_pretty(&choices)?)?;
+        Ok(())
     }
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn find<'a>(emojis: &'a [Emoji], base: &str) -> &'a Emoji {
+        emojis.iter().find(|emoji| emoji.base == base).unwrap()
+    }
+
+    #[test]
+    fn test_skin_tones() {
+        assert_eq!(EmojiPicker::parse_skin_tone("Medium_Dark"), SkinTone::MediumDark);
+        assert_eq!(EmojiPicker::parse_skin_tone("medium light"), SkinTone::MediumLight);
+        assert_eq!(EmojiPicker::parse_skin_tone("none"), SkinTone::Default);
+
+        // The default tone applies where an emoji has tones, a remembered choice wins
+        let choices = HashMap::from([("👋".to_string(), "👋🏿".to_string())]);
+        let emojis = EmojiPicker::load_emojis(SkinTone::Light, &choices, &HashMap::new());
+        assert_eq!(find(&emojis, "👍").emoji, "👍🏻");
+        assert_eq!(find(&emojis, "👋").emoji, "👋🏿");
+        assert_eq!(find(&emojis, "😀").emoji, "😀");
+    }
+
+    #[test]
+    fn test_variants() {
+        let emojis = EmojiPicker::load_emojis(SkinTone::Default, &HashMap::new(), &HashMap::new());
+        assert_eq!(EmojiPicker::variants(find(&emojis, "👍")), ["👍", "👍🏻", "👍🏼", "👍🏽", "👍🏾", "👍🏿"]);
+        assert_eq!(EmojiPicker::variants(find(&emojis, "😀")), ["😀"]);
+
+        // Gender variants follow the tone shown
+        let emojis = EmojiPicker::load_emojis(SkinTone::Dark, &HashMap::new(), &HashMap::new());
+        let running = EmojiPicker::variants(find(&emojis, "🏃"));
+        assert!(running.contains(&"🏃🏿\u{200D}♂\u{FE0F}".to_string()));
+        assert!(running.contains(&"🏃🏿\u{200D}♀\u{FE0F}".to_string()));
+
+        assert_eq!(EmojiPicker::family("man running"), "running");
+        assert_eq!(EmojiPicker::family("woman: red hair"), "");
+        assert_eq!(EmojiPicker::family("mango"), "mango");
+    }
+}
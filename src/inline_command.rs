@@ -0,0 +1,165 @@
+use rustix::process::{kill_process_group, Pid, Signal};
+use std::io::Read;
+use std::os::unix::process::{CommandExt, ExitStatusExt};
+use std::process::{Child, Command, ExitStatus, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// A shell command run inside the launcher (`!! date`) instead of a terminal. Its output is
+/// collected in the background while the UI polls it.
+#[derive(Debug)]
+pub struct InlineCommand {
+    pub command: String,
+    started: Instant,
+    pid: Pid,
+    output: Arc<Mutex<Vec<u8>>>, // stdout and stderr, in the order they were written
+    status: Arc<Mutex<Option<CommandStatus>>>,
+    stop: Arc<AtomicBool>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum CommandStatus {
+    Exited(i32),
+    Signaled(i32),
+    Stopped,  // By the user
+    TimedOut, // After `inline_timeout_secs`
+    Failed(String),
+}
+
+// More output is dropped, the pane isn't meant for whole logs
+const MAX_OUTPUT: usize = 1024 * 1024;
+// How often the waiting thread checks for the end, a stop request and the timeout
+const POLL: Duration = Duration::from_millis(50);
+
+impl InlineCommand {
+    /// Start the command with the user's shell, stdin closed and output captured. It runs in
+    /// its own process group so stopping it also ends pipelines and children.
+    pub fn start(command: &str, timeout: Duration) -> Result<Self, Box<dyn std::error::Error>> {
+        let shell = std::env::var("SHELL").unwrap_or_else(|_| "/bin/sh".to_string());
+        let (mut reader, writer) = std::io::pipe()?;
+        let child = Command::new(shell)
+            .arg("-c")
+            .arg(command)
+            .stdin(Stdio::null())
+            .stdout(writer.try_clone()?)
+            .stderr(writer)
+            .process_group(0)
+            .spawn()?;
+
+        let inline = Self {
+            command: command.to_string(),
+            started: Instant::now(),
+            pid: Pid::from_child(&child),
+            output: Arc::new(Mutex::new(Vec::new())),
+            status: Arc::new(Mutex::new(None)),
+            stop: Arc::new(AtomicBool::new(false)),
+        };
+
+        // Until every process holding the pipe has exited
+        let output = inline.output.clone();
+        thread::spawn(move || {
+            let mut chunk = [0u8; 4096];
+            while let Ok(read) = reader.read(&mut chunk) {
+                if read == 0 {
+                    break;
+                }
+                let mut output = output.lock().unwrap();
+                let room = MAX_OUTPUT.saturating_sub(output.len());
+                output.extend_from_slice(&chunk[..read.min(room)]);
+            }
+        });
+
+        let (pid, status, stop) = (inline.pid, inline.status.clone(), inline.stop.clone());
+        thread::spawn(move || Self::wait(child, pid, timeout, &status, &stop));
+
+        Ok(inline)
+    }
+
+    /// Reap the command and set its status, both under the status lock so `stop` never
+    /// signals a process group that is already gone
+    fn wait(mut child: Child, pid: Pid, timeout: Duration, status: &Mutex<Option<CommandStatus>>, stop: &AtomicBool) {
+        let deadline = Instant::now() + timeout;
+        loop {
+            {
+                let mut status = status.lock().unwrap();
+                let stopped = stop.load(Ordering::Relaxed);
+                if stopped || Instant::now() >= deadline {
+                    let _ = kill_process_group(pid, Signal::KILL);
+                    let _ = child.wait();
+                    *status = Some(if stopped { CommandStatus::Stopped } else { CommandStatus::TimedOut });
+                    return;
+                }
+                match child.try_wait() {
+                    Ok(Some(exit)) => {
+                        *status = Some(Self::exit_status(exit));
+                        return;
+                    }
+                    Ok(None) => {}
+                    Err(e) => {
+                        *status = Some(CommandStatus::Failed(e.to_string()));
+                        return;
+                    }
+                }
+            }
+            thread::sleep(POLL);
+        }
+    }
+
+    fn exit_status(status: ExitStatus) -> CommandStatus {
+        match (status.code(), status.signal()) {
+            (Some(code), _) => CommandStatus::Exited(code),
+            (None, Some(signal)) => CommandStatus::Signaled(signal),
+            (None, None) => CommandStatus::Failed("Unknown exit status".to_string()),
+        }
+    }
+
+    /// Everything written so far, with a note once the limit cut it off
+    pub fn output(&self) -> String {
+        let output = self.output.lock().unwrap();
+        let mut text = String::from_utf8_lossy(&output).into_owned();
+        if output.len() >= MAX_OUTPUT {
+            text.push_str("\n[output truncated]");
+        }
+        text
+    }
+
+    /// `None` while the command runs
+    pub fn status(&self) -> Option<CommandStatus> {
+        self.status.lock().unwrap().clone()
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.status().is_none()
+    }
+
+    /// Kill the command and everything it started right away, the launcher may be about to
+    /// exit; the status becomes `Stopped`
+    pub fn stop(&self) {
+        let status = self.status.lock().unwrap();
+        self.stop.store(true, Ordering::Relaxed);
+        if status.is_none() {
+            let _ = kill_process_group(self.pid, Signal::KILL);
+        }
+    }
+
+    /// "Running for 3 s", "Exit status 0", "Stopped after the 30 s timeout"
+    pub fn summary(&self, timeout: Duration) -> String {
+        match self.status() {
+            None => format!("Running for {} s · Ctrl+C stops it", self.started.elapsed().as_secs()),
+            Some(CommandStatus::Exited(code)) => format!("Exit status {}", code),
+            Some(CommandStatus::Signaled(signal)) => format!("Killed by signal {}", signal),
+            Some(CommandStatus::Stopped) => "Stopped".to_string(),
+            Some(CommandStatus::TimedOut) => format!("Stopped after the {} s timeout", timeout.as_secs()),
+            Some(CommandStatus::Failed(e)) => format!("Failed: {}", e),
+        }
+    }
+}
+
+impl Drop for InlineCommand {
+    /// A command still running when the launcher closes or the query changes is stopped
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
@@ -0,0 +1,240 @@
+use crate::clipboard::{Clipboard, ClipboardContent};
+use crate::command_index::CommandIndex;
+use crate::config::InsertConfig;
+use crate::virtual_keyboard::VirtualKeyboard;
+use crate::window_switcher::WindowSwitcher;
+use std::io::Write;
+use std::process::{Command, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Text for the window the launcher was opened over, inserted once the launcher is hidden
+#[derive(Debug, Clone)]
+pub struct Insertion {
+    pub text: String,
+    pub cursor_back: usize, // Left presses afterwards, to leave the cursor inside the text
+    /// Pasted instead of the text (an image, files, or a clipboard history entry) and left on
+    /// the clipboard afterwards
+    pub content: Option<ClipboardContent>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum InsertMethod {
+    Type,
+    Paste,
+    Clipboard,
+}
+
+// Title of the launcher window, focus has returned once another window has it
+const LAUNCHER_TITLE: &str = "Poppi Launcher";
+// How often the focused window is checked while waiting
+const FOCUS_POLL: Duration = Duration::from_millis(20);
+// Time for the target to read the clipboard before the previous contents are put back
+const RESTORE_DELAY: Duration = Duration::from_millis(300);
+
+impl InsertMethod {
+    pub fn from_config(config: &InsertConfig) -> Self {
+        match config.method.as_str() {
+            "paste" => InsertMethod::Paste,
+            "clipboard" => InsertMethod::Clipboard,
+            _ => InsertMethod::Type,
+        }
+    }
+}
+
+impl Insertion {
+    pub fn new(text: &str) -> Self {
+        Self { text: text.to_string(), cursor_back: 0, content: None }
+    }
+
+    /// Paste clipboard contents, even where text would be typed
+    pub fn paste(content: ClipboardContent) -> Self {
+        Self { text: String::new(), cursor_back: 0, content: Some(content) }
+    }
+
+    /// The configured method; contents other than text can't be typed and are pasted
+    pub fn method(&self, config: &InsertConfig) -> InsertMethod {
+        match InsertMethod::from_config(config) {
+            InsertMethod::Type if self.content.is_some() => InsertMethod::Paste,
+            method => method,
+        }
+    }
+
+    /// Put the text on the clipboard when it is to be pasted or only copied. Call it on the
+    /// GTK main thread while the launcher still has focus.
+    pub fn prepare(&self, config: &InsertConfig) -> Result<(), Box<dyn std::error::Error>> {
+        match (self.method(config), &self.content) {
+            (InsertMethod::Paste | InsertMethod::Clipboard, Some(content)) => Clipboard::set(content),
+            (InsertMethod::Paste | InsertMethod::Clipboard, None) => Clipboard::set_text(&self.text),
+            (InsertMethod::Type, _) => Ok(()),
+        }
+    }
+
+    /// Wait for the previous window to be focused again, then type the text or press Ctrl+V.
+    /// Returns false if no tool could. Call it after hiding the launcher and off the GTK main
+    /// thread, it sleeps.
+    pub fn run(&self, config: &InsertConfig) -> Result<bool, Box<dyn std::error::Error>> {
+        let method = self.method(config);
+        if method == InsertMethod::Clipboard {
+            return Ok(true);
+        }
+
+        Self::wait_for_focus(config);
+
+        let inserted = match method {
+            InsertMethod::Type => Self::type_text(&self.text),
+            // Linux input event code 47 is KEY_V
+            _ => Self::press_keys("v", 47, 1, true)?,
+        };
+        if !inserted {
+            return Ok(false);
+        }
+
+        Self::move_cursor_left(self.cursor_back)?;
+        if method == InsertMethod::Paste && config.restore_clipboard && self.content.is_none() {
+            // Let the window read the clipboard before `finish` puts the previous text back
+            thread::sleep(RESTORE_DELAY);
+        }
+        Ok(true)
+    }
+
+    /// Back on the main thread after `run`: text that couldn't be typed or pasted is left on
+    /// the clipboard, pasted text is replaced by what the clipboard held before
+    pub fn finish(&self, inserted: bool, config: &InsertConfig, previous_clipboard: Option<&str>) {
+        let method = self.method(config);
+        let result = if !inserted && method == InsertMethod::Type {
+            Clipboard::set_text(&self.text)
+        } else if inserted && method == InsertMethod::Paste && config.restore_clipboard && self.content.is_none() {
+            previous_clipboard.map_or(Ok(()), Clipboard::set_text)
+        } else {
+            Ok(())
+        };
+        if let Err(e) = result {
+            eprintln!("Error setting the clipboard: {}", e);
+        }
+    }
+
+    /// Poll the focused window until it is not the launcher; without a way to ask the
+    /// compositor just wait `delay_ms`
+    fn wait_for_focus(config: &InsertConfig) {
+        let deadline = Instant::now() + Duration::from_millis(config.focus_timeout_ms);
+        loop {
+            match WindowSwitcher::focused_window() {
+                Some(window) if !window.title.is_empty() && window.title != LAUNCHER_TITLE => return,
+                Some(_) if Instant::now() < deadline => thread::sleep(FOCUS_POLL),
+                Some(_) => return,
+                None => {
+                    thread::sleep(Duration::from_millis(config.delay_ms));
+                    return;
+                }
+            }
+        }
+    }
+
+    /// Type the text as keystrokes; false if no tool could
+    fn type_text(text: &str) -> bool {
+        if Self::is_wayland() {
+            // The compositor's virtual keyboard first, it needs no extra tools
+            if VirtualKeyboard::type_text(text).is_ok() {
+                return true;
+            }
+            // "--" so text starting with '-' isn't read as an option
+            if CommandIndex::contains("wtype")
+                && Command::new("wtype").args(["--", text]).status().is_ok_and(|s| s.success())
+            {
+                return true;
+            }
+            if CommandIndex::contains("ydotool") {
+                let Ok(mut child) = Command::new("ydotool")
+                    .args(["type", "--file", "-"])
+                    .stdin(Stdio::piped())
+                    .spawn()
+                else {
+                    return false;
+                };
+                if let Some(mut stdin) = child.stdin.take() {
+                    if stdin.write_all(text.as_bytes()).is_err() {
+                        return false;
+                    }
+                }
+                return child.wait().is_ok_and(|s| s.success());
+            }
+            return false;
+        }
+
+        CommandIndex::contains("xdotool")
+            && Command::new("xdotool")
+                .args(["type", "--clearmodifiers", "--", text])
+                .status()
+                .is_ok_and(|s| s.success())
+    }
+
+    /// Press Left a number of times after inserting, to leave the cursor inside the text
+    fn move_cursor_left(count: usize) -> Result<(), Box<dyn std::error::Error>> {
+        if count == 0 {
+            return Ok(());
+        }
+
+        // Linux input event code 105 is KEY_LEFT
+        if Self::press_keys("Left", 105, count, false)? {
+            Ok(())
+        } else {
+            Err("No tool available to move the cursor (wtype, ydotool, or xdotool)".into())
+        }
+    }
+
+    /// Press a key, given by its keysym name and Linux input event code, a number of times
+    /// with the virtual keyboard, wtype, ydotool or xdotool; false if none is there
+    fn press_keys(keysym: &str, code: u32, count: usize, control: bool) -> Result<bool, Box<dyn std::error::Error>> {
+        let is_wayland = Self::is_wayland();
+        if is_wayland && VirtualKeyboard::press_key(keysym, count, control).is_ok() {
+            return Ok(true);
+        }
+
+        let status = if is_wayland && CommandIndex::contains("wtype") {
+            let mut command = Command::new("wtype");
+            if control {
+                command.args(["-M", "ctrl"]);
+            }
+            for _ in 0..count {
+                command.args(["-k", keysym]);
+            }
+            if control {
+                command.args(["-m", "ctrl"]);
+            }
+            command.status()?
+        } else if is_wayland && CommandIndex::contains("ydotool") {
+            // Linux input event code 29 is KEY_LEFTCTRL
+            let mut command = Command::new("ydotool");
+            command.arg("key");
+            if control {
+                command.arg("29:1");
+            }
+            for _ in 0..count {
+                command.args([format!("{}:1", code), format!("{}:0", code)]);
+            }
+            if control {
+                command.arg("29:0");
+            }
+            command.status()?
+        } else if !is_wayland && CommandIndex::contains("xdotool") {
+            let key = if control { format!("ctrl+{}", keysym) } else { keysym.to_string() };
+            Command::new("xdotool")
+                .args(["key", "--clearmodifiers", "--repeat", &count.to_string(), &key])
+                .status()?
+        } else {
+            return Ok(false);
+        };
+
+        if status.success() {
+            Ok(true)
+        } else {
+            Err("Sending keys failed".into())
+        }
+    }
+
+    fn is_wayland() -> bool {
+        std::env::var("WAYLAND_DISPLAY").is_ok()
+            || std::env::var("XDG_SESSION_TYPE").map(|s| s == "wayland").unwrap_or(false)
+    }
+}
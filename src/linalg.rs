@@ -0,0 +1,374 @@
+use crate::calculator::{Calculator, Number, Value};
+
+/// Vector and matrix arithmetic for the calculator.
+///
+/// Entries are calculator numbers, so integer and rational matrices keep exact
+/// determinants and inverses. Vectors act as columns when multiplied by a matrix.
+pub struct LinearAlgebra;
+
+type Matrix = Vec<Vec<Number>>;
+
+// Keeps "[[...]]^1000000" and huge pasted tables from freezing the launcher
+const MAX_DIMENSION: usize = 64;
+
+impl LinearAlgebra {
+    /// Build a vector from numbers or a matrix from equally long vectors
+    pub(crate) fn from_items(items: Vec<Value>) -> Result<Value, String> {
+        if items.len() > MAX_DIMENSION {
+            return Err(format!("Vectors and matrices are limited to {} entries per side", MAX_DIMENSION));
+        }
+        if items.iter().all(|item| matches!(item, Value::Number(_))) {
+            return Ok(Value::Vector(
+                items
+                    .into_iter()
+                    .map(|item| match item {
+                        Value::Number(n) => n,
+                        _ => unreachable!(),
+                    })
+                    .collect(),
+            ));
+        }
+
+        let rows: Matrix = items
+            .into_iter()
+            .map(|item| match item {
+                Value::Vector(row) => Ok(row),
+                Value::Complex(_) => Err("Matrices of complex numbers are not supported".to_string()),
+                _ => Err("Matrix rows must be lists of numbers, e.g. [[1, 2], [3, 4]]".to_string()),
+            })
+            .collect::<Result<_, _>>()?;
+        if rows.iter().any(|row| row.len() != rows[0].len()) {
+            return Err("All matrix rows must have the same length".to_string());
+        }
+        Ok(Value::Matrix(rows))
+    }
+
+    pub(crate) fn apply_binary(op: char, a: Value, b: Value) -> Result<Value, String> {
+        match (op, a, b) {
+            ('+' | '-', Value::Vector(x), Value::Vector(y)) => {
+                if x.len() != y.len() {
+                    return Err("Vectors must have the same length".to_string());
+                }
+                Ok(Value::Vector(Self::zip_with(op, x, y)?))
+            }
+            ('+' | '-', Value::Matrix(x), Value::Matrix(y)) => {
+                if Self::size(&x) != Self::size(&y) {
+                    return Err("Matrices must have the same size".to_string());
+                }
+                let rows = x
+                    .into_iter()
+                    .zip(y)
+                    .map(|(r, s)| Self::zip_with(op, r, s))
+                    .collect::<Result<_, _>>()?;
+                Ok(Value::Matrix(rows))
+            }
+            // Scaling
+            ('*', Value::Number(k), v) | ('*' | '/', v, Value::Number(k)) => Self::scale(op, v, &k),
+            ('*', Value::Matrix(x), Value::Matrix(y)) => Ok(Value::Matrix(Self::multiply(&x, &y)?)),
+            ('*', Value::Matrix(x), Value::Vector(v)) => {
+                let column: Matrix = v.into_iter().map(|n| vec![n]).collect();
+                let product = Self::multiply(&x, &column)?;
+                Ok(Value::Vector(product.into_iter().map(|mut row| row.remove(0)).collect()))
+            }
+            ('*', Value::Vector(v), Value::Matrix(x)) => {
+                let mut product = Self::multiply(&[v], &x)?;
+                Ok(Value::Vector(product.remove(0)))
+            }
+            ('*', Value::Vector(_), Value::Vector(_)) => {
+                Err("Use dot(a, b) or cross(a, b) to multiply vectors".to_string())
+            }
+            ('/', Value::Matrix(x), Value::Matrix(y)) => Ok(Value::Matrix(Self::multiply(&x, &Self::inverse(&y)?)?)),
+            ('^', Value::Matrix(x), Value::Number(n)) => Self::power(&x, &n),
+            (_, Value::Complex(_), _) | (_, _, Value::Complex(_)) => {
+                Err("Matrices of complex numbers are not supported".to_string())
+            }
+            _ => Err("This operation is not defined for these vectors or matrices".to_string()),
+        }
+    }
+
+    pub(crate) fn call_function(name: &str, args: Vec<Value>) -> Result<Value, String> {
+        let number = |n: Number| Ok(Value::Number(n));
+        match (name, args.as_slice()) {
+            ("det", [Value::Matrix(m)]) => number(Self::determinant(m)?),
+            ("inv" | "inverse", [Value::Matrix(m)]) => Ok(Value::Matrix(Self::inverse(m)?)),
+            ("transpose", [Value::Matrix(m)]) => Ok(Value::Matrix(Self::transpose(m))),
+            // A vector is a column, its transpose a single row
+            ("transpose", [Value::Vector(v)]) => Ok(Value::Matrix(vec![v.clone()])),
+            ("trace", [Value::Matrix(m)]) => {
+                Self::require_square(m)?;
+                let diagonal = m.iter().enumerate().map(|(i, row)| row[i].clone());
+                number(Self::sum(diagonal)?)
+            }
+            ("dot", [Value::Vector(x), Value::Vector(y)]) => {
+                if x.len() != y.len() {
+                    return Err("dot() needs vectors of the same length".to_string());
+                }
+                number(Self::dot(x, y)?)
+            }
+            ("cross", [Value::Vector(x), Value::Vector(y)]) => {
+                if x.len() != 3 || y.len() != 3 {
+                    return Err("cross() needs two 3-element vectors".to_string());
+                }
+                let term = |i: usize, j: usize| -> Result<Number, String> {
+                    let a = Calculator::apply_binary('*', x[i].clone(), y[j].clone())?;
+                    let b = Calculator::apply_binary('*', x[j].clone(), y[i].clone())?;
+                    Calculator::apply_binary('-', a, b)
+                };
+                Ok(Value::Vector(vec![term(1, 2)?, term(2, 0)?, term(0, 1)?]))
+            }
+            ("norm" | "abs" | "length", [Value::Vector(v)]) => {
+                let squares = Self::dot(v, v)?;
+                number(Calculator::call_function("sqrt", vec![squares])?)
+            }
+            ("rows", [Value::Matrix(m)]) => number(Number::from_integer(m.len() as i64)),
+            ("cols" | "columns", [Value::Matrix(m)]) => number(Number::from_integer(Self::size(m).1 as i64)),
+            _ => Err(format!("{}() is not defined for vectors or matrices", name)),
+        }
+    }
+
+    fn size(m: &Matrix) -> (usize, usize) {
+        (m.len(), m.first().map_or(0, Vec::len))
+    }
+
+    fn require_square(m: &Matrix) -> Result<usize, String> {
+        let (rows, cols) = Self::size(m);
+        if rows != cols {
+            return Err(format!("Needs a square matrix, got {}×{}", rows, cols));
+        }
+        Ok(rows)
+    }
+
+    fn zip_with(op: char, x: Vec<Number>, y: Vec<Number>) -> Result<Vec<Number>, String> {
+        x.into_iter().zip(y).map(|(a, b)| Calculator::apply_binary(op, a, b)).collect()
+    }
+
+    fn scale(op: char, value: Value, k: &Number) -> Result<Value, String> {
+        let scale_row = |row: Vec<Number>| -> Result<Vec<Number>, String> {
+            row.into_iter().map(|n| Calculator::apply_binary(op, n, k.clone())).collect()
+        };
+        match value {
+            Value::Vector(v) => Ok(Value::Vector(scale_row(v)?)),
+            Value::Matrix(m) => Ok(Value::Matrix(m.into_iter().map(scale_row).collect::<Result<_, _>>()?)),
+            _ => Err("This operation is not defined for these values".to_string()),
+        }
+    }
+
+    fn sum(numbers: impl Iterator<Item = Number>) -> Result<Number, String> {
+        numbers.into_iter().try_fold(Number::from_integer(0), |acc, n| Calculator::apply_binary('+', acc, n))
+    }
+
+    fn dot(x: &[Number], y: &[Number]) -> Result<Number, String> {
+        let products = x
+            .iter()
+            .zip(y)
+            .map(|(a, b)| Calculator::apply_binary('*', a.clone(), b.clone()))
+            .collect::<Result<Vec<_>, _>>()?;
+        Self::sum(products.into_iter())
+    }
+
+    fn multiply(x: &[Vec<Number>], y: &[Vec<Number>]) -> Result<Matrix, String> {
+        let (rows, inner) = (x.len(), x.first().map_or(0, Vec::len));
+        let cols = y.first().map_or(0, Vec::len);
+        if inner != y.len() {
+            return Err(format!(
+                "Can't multiply {}×{} by {}×{}",
+                rows,
+                inner,
+                y.len(),
+                cols
+            ));
+        }
+
+        let columns = Self::transpose(&y.to_vec());
+        x.iter()
+            .map(|row| columns.iter().map(|column| Self::dot(row, column)).collect())
+            .collect()
+    }
+
+    fn transpose(m: &Matrix) -> Matrix {
+        let (rows, cols) = Self::size(m);
+        (0..cols).map(|j| (0..rows).map(|i| m[i][j].clone()).collect()).collect()
+    }
+
+    fn identity(n: usize) -> Matrix {
+        (0..n)
+            .map(|i| (0..n).map(|j| Number::from_integer((i == j) as i64)).collect())
+            .collect()
+    }
+
+    fn power(m: &Matrix, exponent: &Number) -> Result<Value, String> {
+        let n = Self::require_square(m)?;
+        let exponent = exponent
+            .as_integer()
+            .and_then(|e| num_traits::ToPrimitive::to_i64(&e))
+            .filter(|e| e.abs() <= 10_000)
+            .ok_or_else(|| "Matrix powers need a small integer exponent".to_string())?;
+
+        let mut base = if exponent < 0 { Self::inverse(m)? } else { m.clone() };
+        let mut result = Self::identity(n);
+        let mut e = exponent.unsigned_abs();
+        // Square and multiply
+        while e > 0 {
+            if e & 1 == 1 {
+                result = Self::multiply(&result, &base)?;
+            }
+            base = Self::multiply(&base, &base)?;
+            e >>= 1;
+        }
+        Ok(Value::Matrix(result))
+    }
+
+    /// Gaussian elimination, exact for rational entries
+    fn determinant(m: &Matrix) -> Result<Number, String> {
+        let n = Self::require_square(m)?;
+        let mut a = m.clone();
+        let mut det = Number::from_integer(1);
+
+        for col in 0..n {
+            let Some(pivot) = Self::pivot_row(&a, col) else {
+                return Ok(Number::from_integer(0));
+            };
+            if pivot != col {
+                a.swap(pivot, col);
+                det = Calculator::apply_binary('*', det, Number::from_integer(-1))?;
+            }
+            det = Calculator::apply_binary('*', det, a[col][col].clone())?;
+            Self::eliminate_below(&mut a, col)?;
+        }
+        Ok(det)
+    }
+
+    /// Gauss-Jordan elimination on [m | I]
+    fn inverse(m: &Matrix) -> Result<Matrix, String> {
+        let n = Self::require_square(m)?;
+        let mut a: Matrix = m
+            .iter()
+            .zip(Self::identity(n))
+            .map(|(row, unit)| row.iter().cloned().chain(unit).collect())
+            .collect();
+
+        for col in 0..n {
+            let pivot = Self::pivot_row(&a, col).ok_or_else(|| "Matrix is singular (no inverse)".to_string())?;
+            a.swap(pivot, col);
+
+            let divisor = a[col][col].clone();
+            for cell in a[col].iter_mut() {
+                *cell = Calculator::apply_binary('/', cell.clone(), divisor.clone())?;
+            }
+            for r in 0..n {
+                if r == col || a[r][col].is_zero() {
+                    continue;
+                }
+                Self::subtract_row(&mut a, r, col)?;
+            }
+        }
+
+        Ok(a.into_iter().map(|row| row[n..].to_vec()).collect())
+    }
+
+    /// Largest non-zero entry at or below the diagonal in this column
+    fn pivot_row(a: &Matrix, col: usize) -> Option<usize> {
+        (col..a.len())
+            .filter(|&r| !a[r][col].is_zero() && a[r][col].to_f64().abs() > 1e-300)
+            .max_by(|&x, &y| a[x][col].to_f64().abs().total_cmp(&a[y][col].to_f64().abs()))
+    }
+
+    fn eliminate_below(a: &mut Matrix, col: usize) -> Result<(), String> {
+        for r in col + 1..a.len() {
+            if !a[r][col].is_zero() {
+                let factor = Calculator::apply_binary('/', a[r][col].clone(), a[col][col].clone())?;
+                let pivot_row = a[col].clone();
+                for (cell, pivot_cell) in a[r].iter_mut().zip(pivot_row).skip(col) {
+                    let scaled = Calculator::apply_binary('*', factor.clone(), pivot_cell)?;
+                    *cell = Calculator::apply_binary('-', cell.clone(), scaled)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// row r -= a[r][col] * row col (row col has a 1 on the diagonal)
+    fn subtract_row(a: &mut Matrix, r: usize, col: usize) -> Result<(), String> {
+        let factor = a[r][col].clone();
+        let pivot_row = a[col].clone();
+        for (cell, pivot_cell) in a[r].iter_mut().zip(pivot_row) {
+            let scaled = Calculator::apply_binary('*', factor.clone(), pivot_cell)?;
+            *cell = Calculator::apply_binary('-', cell.clone(), scaled)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn matrix(rows: &[&[i64]]) -> Matrix {
+        rows.iter().map(|row| row.iter().map(|&n| Number::from_integer(n)).collect()).collect()
+    }
+
+    fn vector(values: &[i64]) -> Value {
+        Value::Vector(values.iter().map(|&n| Number::from_integer(n)).collect())
+    }
+
+    #[test]
+    fn test_determinant() {
+        assert_eq!(LinearAlgebra::determinant(&matrix(&[&[1, 2], &[3, 4]])), Ok(Number::from_integer(-2)));
+        assert_eq!(LinearAlgebra::determinant(&matrix(&[&[0, 1], &[1, 0]])), Ok(Number::from_integer(-1)));
+        assert_eq!(LinearAlgebra::determinant(&matrix(&[&[1, 2], &[2, 4]])), Ok(Number::from_integer(0)));
+        let identity = LinearAlgebra::identity(4);
+        assert_eq!(LinearAlgebra::determinant(&identity), Ok(Number::from_integer(1)));
+        assert!(LinearAlgebra::determinant(&matrix(&[&[1, 2, 3], &[4, 5, 6]])).is_err());
+    }
+
+    #[test]
+    fn test_singular_matrices() {
+        let singular = matrix(&[&[1, 2], &[2, 4]]);
+        assert_eq!(LinearAlgebra::inverse(&singular), Err("Matrix is singular (no inverse)".to_string()));
+        assert!(LinearAlgebra::inverse(&matrix(&[&[0, 0], &[0, 0]])).is_err());
+        assert!(LinearAlgebra::power(&singular, &Number::from_integer(-1)).is_err());
+        assert_eq!(
+            LinearAlgebra::inverse(&matrix(&[&[1, 2, 3], &[4, 5, 6]])),
+            Err("Needs a square matrix, got 2×3".to_string())
+        );
+
+        // A zero in the corner only needs a row swap
+        let swapped = matrix(&[&[0, 1], &[1, 0]]);
+        assert_eq!(LinearAlgebra::inverse(&swapped), Ok(swapped.clone()));
+    }
+
+    #[test]
+    fn test_products() {
+        let m = matrix(&[&[1, 2], &[3, 4]]);
+        assert_eq!(LinearAlgebra::multiply(&m, &LinearAlgebra::identity(2)), Ok(m.clone()));
+        assert_eq!(LinearAlgebra::multiply(&m, &m), Ok(matrix(&[&[7, 10], &[15, 22]])));
+        assert_eq!(
+            LinearAlgebra::power(&m, &Number::from_integer(3)),
+            Ok(Value::Matrix(matrix(&[&[37, 54], &[81, 118]])))
+        );
+        let product = LinearAlgebra::power(&m, &Number::from_integer(-1))
+            .and_then(|inverse| LinearAlgebra::apply_binary('*', Value::Matrix(m.clone()), inverse));
+        assert_eq!(product, Ok(Value::Matrix(LinearAlgebra::identity(2))));
+        assert!(LinearAlgebra::multiply(&m, &matrix(&[&[1, 2, 3]])).is_err());
+        assert!(LinearAlgebra::power(&m, &Number::from_integer(100_000)).is_err());
+
+        let dot = LinearAlgebra::call_function("dot", vec![vector(&[1, 2, 3]), vector(&[4, 5, 6])]);
+        assert_eq!(dot, Ok(Value::Number(Number::from_integer(32))));
+        let cross = LinearAlgebra::call_function("cross", vec![vector(&[1, 0, 0]), vector(&[0, 1, 0])]);
+        assert_eq!(cross, Ok(vector(&[0, 0, 1])));
+        assert!(LinearAlgebra::call_function("cross", vec![vector(&[1, 0]), vector(&[0, 1])]).is_err());
+    }
+
+    #[test]
+    fn test_from_items() {
+        let rows = vec![vector(&[1, 2]), vector(&[3, 4])];
+        assert_eq!(LinearAlgebra::from_items(rows), Ok(Value::Matrix(matrix(&[&[1, 2], &[3, 4]]))));
+        let ragged = vec![vector(&[1, 2]), vector(&[3])];
+        assert_eq!(
+            LinearAlgebra::from_items(ragged),
+            Err("All matrix rows must have the same length".to_string())
+        );
+        let huge = vec![Value::Number(Number::from_integer(1)); MAX_DIMENSION + 1];
+        assert!(LinearAlgebra::from_items(huge).is_err());
+    }
+}
@@ -1,12 +1,28 @@
 mod app_launcher;
 mod calculator;
+mod calculator_history;
+mod clipboard;
+mod clipboard_history;
+mod command_index;
+mod completion;
 mod config;
+mod datetime;
 mod emoji_picker;
+mod inline_command;
+mod insertion;
+mod linalg;
+mod plotter;
 mod search;
 mod settings;
+mod shell_history;
+mod snippets;
+mod solver;
 mod terminal;
 mod ui;
+mod unicode_search;
+mod units;
 mod utils;
+mod virtual_keyboard;
 mod window_switcher;
 
 use gtk::prelude::*;
@@ -24,7 +40,15 @@ fn main() -> glib::ExitCode {
         .flags(gio::ApplicationFlags::NON_UNIQUE)
         .build();
 
+    // "--resident" runs in the background for the clipboard history, e.g. started with the session
+    let resident = std::env::args().skip(1).any(|arg| arg == "--resident");
+
     app.connect_activate(move |app| {
+        if resident {
+            ui::run_resident(app);
+            return;
+        }
+
         // Check if window already exists
         if let Some(window) = app.active_window() {
             window.present();
@@ -35,6 +59,7 @@ fn main() -> glib::ExitCode {
         ui::build_ui(app, config.clone());
     });
 
-    // Run the application
-    app.run()
+    // Run the application, GTK doesn't know our own option
+    let args: Vec<String> = std::env::args().filter(|arg| arg != "--resident").collect();
+    app.run_with_args(&args)
 }
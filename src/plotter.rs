@@ -0,0 +1,385 @@
+use crate::calculator::{Calculator, Expr, Number};
+use crate::config::CalculatorConfig;
+use gtk::cairo;
+use gtk::gdk;
+use gtk::glib;
+use gtk::prelude::*;
+use std::collections::HashMap;
+
+/// Function plots for the calculator ("plot sin(x)/x from -10 to 10")
+pub struct Plotter;
+
+#[derive(Debug, Clone)]
+pub struct Plot {
+    pub functions: Vec<PlotFunction>,
+    pub x_range: (f64, f64),
+    pub y_range: (f64, f64),
+}
+
+#[derive(Debug, Clone)]
+pub struct PlotFunction {
+    pub label: String,
+    /// Sampled points, `None` where the function is undefined
+    pub points: Vec<Option<(f64, f64)>>,
+}
+
+const SAMPLES: usize = 600;
+const DEFAULT_RANGE: (f64, f64) = (-10.0, 10.0);
+const MAX_FUNCTIONS: usize = 6;
+// Size of the image copied to the clipboard
+const PNG_SIZE: (i32, i32) = (800, 500);
+
+// Line colours after the theme's accent colour
+const PALETTE: [(f64, f64, f64); 5] = [
+    (0.90, 0.49, 0.13),
+    (0.36, 0.72, 0.36),
+    (0.85, 0.33, 0.58),
+    (0.60, 0.50, 0.90),
+    (0.95, 0.80, 0.25),
+];
+
+impl Plotter {
+    pub fn is_plot_query(query: &str) -> bool {
+        let lower = query.trim().to_lowercase();
+        lower.starts_with("plot ") && lower.len() > 5
+    }
+
+    /// Parse "plot f[, g ...] [from a to b]" and sample every function over the range
+    pub fn plot(query: &str, config: &CalculatorConfig) -> Result<Plot, String> {
+        let body = query.trim()[5..].trim();
+
+        let (functions, range) = match body.rfind(" from ") {
+            Some(pos) => (&body[..pos], Some(&body[pos + 6..])),
+            None => (body, None),
+        };
+
+        let x_range = match range {
+            Some(range) => {
+                let (from, to) = range
+                    .split_once(" to ")
+                    .or_else(|| range.split_once(".."))
+                    .ok_or_else(|| "Range must look like 'from -10 to 10'".to_string())?;
+                let from = Calculator::evaluate_number(from.trim(), config)?.to_f64();
+                let to = Calculator::evaluate_number(to.trim(), config)?.to_f64();
+                if !from.is_finite() || !to.is_finite() || from == to {
+                    return Err("Invalid plot range".to_string());
+                }
+                (from.min(to), from.max(to))
+            }
+            None => DEFAULT_RANGE,
+        };
+
+        let mut plotted = Vec::new();
+        for source in Self::split_functions(functions) {
+            let expr = Calculator::parse(&source, config)?;
+            let var = Self::free_variable(&expr)?;
+            plotted.push(PlotFunction {
+                label: source,
+                points: Self::sample(&expr, &var, x_range),
+            });
+        }
+
+        if plotted.is_empty() {
+            return Err("Nothing to plot".to_string());
+        }
+        if plotted.len() > MAX_FUNCTIONS {
+            return Err(format!("At most {} functions per plot", MAX_FUNCTIONS));
+        }
+
+        let y_range = Self::y_range(&plotted).ok_or_else(|| "The function is undefined on this range".to_string())?;
+
+        Ok(Plot { functions: plotted, x_range, y_range })
+    }
+
+    /// Split on top-level ',' / ';' / " and " so "plot max(x, 0), x^2" gives two functions
+    fn split_functions(functions: &str) -> Vec<String> {
+        let mut parts = Vec::new();
+        let mut current = String::new();
+        let mut depth = 0i32;
+        for c in functions.replace(" and ", ";").chars() {
+            match c {
+                '(' => depth += 1,
+                ')' => depth -= 1,
+                ',' | ';' if depth == 0 => {
+                    parts.push(std::mem::take(&mut current));
+                    continue;
+                }
+                _ => {}
+            }
+            current.push(c);
+        }
+        parts.push(current);
+        parts
+            .into_iter()
+            .map(|p| p.trim().to_string())
+            .filter(|p| !p.is_empty())
+            .collect()
+    }
+
+    /// The variable to sample over: the only unknown name, or "x"
+    fn free_variable(expr: &Expr) -> Result<String, String> {
+        let mut names = Vec::new();
+        Self::collect_variables(expr, &mut names);
+        match names.len() {
+            0 => Ok("x".to_string()),
+            1 => Ok(names.remove(0)),
+            _ => Err(format!("Plot one variable at a time ({})", names.join(", "))),
+        }
+    }
+
+    fn collect_variables(expr: &Expr, names: &mut Vec<String>) {
+        match expr {
+            Expr::Var(name) => {
+                // Constants such as pi evaluate on their own
+                if !Calculator::is_constant(name) && !names.contains(name) {
+                    names.push(name.clone());
+                }
+            }
+            Expr::Number(_) => {}
+            Expr::Neg(inner) | Expr::Factorial(inner) | Expr::Percent(inner) => Self::collect_variables(inner, names),
+            Expr::Binary(_, lhs, rhs) => {
+                Self::collect_variables(lhs, names);
+                Self::collect_variables(rhs, names);
+            }
+            Expr::Call(_, args) | Expr::Array(args) => {
+                for arg in args {
+                    Self::collect_variables(arg, names);
+                }
+            }
+        }
+    }
+
+    fn sample(expr: &Expr, var: &str, (from, to): (f64, f64)) -> Vec<Option<(f64, f64)>> {
+        let mut vars = HashMap::new();
+        (0..=SAMPLES)
+            .map(|i| {
+                let x = from + (to - from) * i as f64 / SAMPLES as f64;
+                vars.insert(var.to_string(), Number::Approx(x));
+                Calculator::eval(expr, &vars)
+                    .ok()
+                    .map(|y| y.to_f64())
+                    .filter(|y| y.is_finite())
+                    .map(|y| (x, y))
+            })
+            .collect()
+    }
+
+    /// Vertical range covering the functions, ignoring spikes near poles
+    fn y_range(functions: &[PlotFunction]) -> Option<(f64, f64)> {
+        let mut ys: Vec<f64> = functions
+            .iter()
+            .flat_map(|f| f.points.iter().flatten().map(|(_, y)| *y))
+            .collect();
+        if ys.is_empty() {
+            return None;
+        }
+        ys.sort_by(f64::total_cmp);
+
+        let (min, max) = (ys[0], ys[ys.len() - 1]);
+        let low = ys[ys.len() / 50];
+        let high = ys[ys.len() - 1 - ys.len() / 50];
+
+        // Use the full range unless a few samples dwarf the rest (tan(x), 1/x)
+        let (mut low, mut high) = if max - min <= 10.0 * (high - low).max(f64::EPSILON) {
+            (min, max)
+        } else {
+            (low, high)
+        };
+
+        if high - low < 1e-12 {
+            // Constant function
+            let pad = low.abs().max(1.0);
+            low -= pad;
+            high += pad;
+        } else {
+            let pad = (high - low) * 0.05;
+            low -= pad;
+            high += pad;
+        }
+        Some((low, high))
+    }
+
+    /// Draw the plot onto a cairo context of the given size
+    pub fn draw(plot: &Plot, cr: &cairo::Context, width: f64, height: f64, text_color: &str, accent_color: &str) {
+        let text = Self::parse_color(text_color).unwrap_or((1.0, 1.0, 1.0));
+        let accent = Self::parse_color(accent_color).unwrap_or((0.32, 0.58, 0.89));
+
+        let margin = (40.0, 10.0, 12.0, 22.0); // left, right, top, bottom
+        let area = (margin.0, margin.2, width - margin.0 - margin.1, height - margin.2 - margin.3);
+        if area.2 <= 0.0 || area.3 <= 0.0 {
+            return;
+        }
+
+        let (x_min, x_max) = plot.x_range;
+        let (y_min, y_max) = plot.y_range;
+        let to_screen = |x: f64, y: f64| {
+            (
+                area.0 + (x - x_min) / (x_max - x_min) * area.2,
+                area.1 + (y_max - y) / (y_max - y_min) * area.3,
+            )
+        };
+
+        cr.select_font_face("Sans", cairo::FontSlant::Normal, cairo::FontWeight::Normal);
+        cr.set_font_size(10.0);
+        cr.set_line_width(1.0);
+
+        // Grid lines and tick labels
+        for x in Self::ticks(x_min, x_max) {
+            let (sx, _) = to_screen(x, y_min);
+            cr.set_source_rgba(text.0, text.1, text.2, 0.1);
+            cr.move_to(sx.round() + 0.5, area.1);
+            cr.line_to(sx.round() + 0.5, area.1 + area.3);
+            let _ = cr.stroke();
+
+            let label = Self::tick_label(x, x_min, x_max);
+            let label_width = cr.text_extents(&label).map(|e| e.width()).unwrap_or(0.0);
+            cr.set_source_rgba(text.0, text.1, text.2, 0.6);
+            cr.move_to(sx - label_width / 2.0, height - 6.0);
+            let _ = cr.show_text(&label);
+        }
+        for y in Self::ticks(y_min, y_max) {
+            let (_, sy) = to_screen(x_min, y);
+            cr.set_source_rgba(text.0, text.1, text.2, 0.1);
+            cr.move_to(area.0, sy.round() + 0.5);
+            cr.line_to(area.0 + area.2, sy.round() + 0.5);
+            let _ = cr.stroke();
+
+            let label = Self::tick_label(y, y_min, y_max);
+            let label_width = cr.text_extents(&label).map(|e| e.width()).unwrap_or(0.0);
+            cr.set_source_rgba(text.0, text.1, text.2, 0.6);
+            cr.move_to(area.0 - label_width - 4.0, sy + 3.5);
+            let _ = cr.show_text(&label);
+        }
+
+        // Axes through the origin when it is visible
+        cr.set_source_rgba(text.0, text.1, text.2, 0.45);
+        if x_min <= 0.0 && x_max >= 0.0 {
+            let (sx, _) = to_screen(0.0, 0.0);
+            cr.move_to(sx.round() + 0.5, area.1);
+            cr.line_to(sx.round() + 0.5, area.1 + area.3);
+        }
+        if y_min <= 0.0 && y_max >= 0.0 {
+            let (_, sy) = to_screen(0.0, 0.0);
+            cr.move_to(area.0, sy.round() + 0.5);
+            cr.line_to(area.0 + area.2, sy.round() + 0.5);
+        }
+        let _ = cr.stroke();
+
+        // Curves, clipped to the plot area
+        let _ = cr.save();
+        cr.rectangle(area.0, area.1, area.2, area.3);
+        cr.clip();
+        cr.set_line_width(2.0);
+        cr.set_line_join(cairo::LineJoin::Round);
+        let span = y_max - y_min;
+        for (i, function) in plot.functions.iter().enumerate() {
+            let (r, g, b) = Self::color(i, accent);
+            cr.set_source_rgb(r, g, b);
+
+            let mut previous: Option<(f64, f64)> = None;
+            for point in &function.points {
+                match (*point, previous) {
+                    (Some((x, y)), Some((_, py))) if (y - py).abs() < 2.0 * span => {
+                        let (sx, sy) = to_screen(x, y);
+                        cr.line_to(sx, sy);
+                    }
+                    // Start a new segment after gaps and across poles
+                    (Some((x, y)), _) => {
+                        let (sx, sy) = to_screen(x, y);
+                        cr.move_to(sx, sy);
+                    }
+                    (None, _) => {}
+                }
+                previous = *point;
+            }
+            let _ = cr.stroke();
+        }
+        let _ = cr.restore();
+
+        // Legend when several functions share the plot
+        if plot.functions.len() > 1 {
+            for (i, function) in plot.functions.iter().enumerate() {
+                let (r, g, b) = Self::color(i, accent);
+                let y = area.1 + 12.0 + i as f64 * 14.0;
+                cr.set_source_rgb(r, g, b);
+                cr.rectangle(area.0 + 8.0, y - 7.0, 10.0, 3.0);
+                let _ = cr.fill();
+                cr.set_source_rgba(text.0, text.1, text.2, 0.85);
+                cr.move_to(area.0 + 24.0, y - 2.0);
+                let _ = cr.show_text(&function.label);
+            }
+        }
+    }
+
+    /// Render the plot to PNG bytes
+    pub fn to_png(plot: &Plot, text_color: &str, accent_color: &str, background_color: &str) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+        let (width, height) = PNG_SIZE;
+        let mut surface = cairo::ImageSurface::create(cairo::Format::ARgb32, width, height)?;
+        {
+            let cr = cairo::Context::new(&surface)?;
+            let (r, g, b) = Self::parse_color(background_color).unwrap_or((0.18, 0.18, 0.18));
+            cr.set_source_rgb(r, g, b);
+            cr.paint()?;
+            Self::draw(plot, &cr, width as f64, height as f64, text_color, accent_color);
+        }
+        surface.flush();
+
+        // ARGB32 is premultiplied BGRA in memory on little-endian machines
+        let stride = surface.stride() as usize;
+        let data = surface.data()?.to_vec();
+        let texture = gdk::MemoryTexture::new(
+            width,
+            height,
+            gdk::MemoryFormat::B8g8r8a8Premultiplied,
+            &glib::Bytes::from_owned(data),
+            stride,
+        );
+        Ok(texture.save_to_png_bytes().to_vec())
+    }
+
+    fn color(index: usize, accent: (f64, f64, f64)) -> (f64, f64, f64) {
+        match index {
+            0 => accent,
+            i => PALETTE[(i - 1) % PALETTE.len()],
+        }
+    }
+
+    /// "#rrggbb" to cairo components
+    fn parse_color(color: &str) -> Option<(f64, f64, f64)> {
+        let hex = color.trim().strip_prefix('#')?;
+        if hex.len() != 6 {
+            return None;
+        }
+        let channel = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).ok().map(|c| c as f64 / 255.0);
+        Some((channel(0)?, channel(2)?, channel(4)?))
+    }
+
+    /// Round tick positions (1, 2 or 5 times a power of ten)
+    fn ticks(min: f64, max: f64) -> Vec<f64> {
+        let step = Self::tick_step(min, max);
+        let first = (min / step).ceil() as i64;
+        let last = (max / step).floor() as i64;
+        (first..=last).map(|i| i as f64 * step).collect()
+    }
+
+    fn tick_step(min: f64, max: f64) -> f64 {
+        let rough = (max - min) / 6.0;
+        let magnitude = 10f64.powf(rough.log10().floor());
+        [1.0, 2.0, 5.0, 10.0]
+            .into_iter()
+            .map(|m| m * magnitude)
+            .find(|s| *s >= rough)
+            .unwrap_or(10.0 * magnitude)
+    }
+
+    fn tick_label(value: f64, min: f64, max: f64) -> String {
+        let step = Self::tick_step(min, max);
+        let decimals = (-step.log10().floor()).max(0.0) as usize;
+        let value = if value.abs() < step / 2.0 { 0.0 } else { value };
+        if value.abs() >= 1e6 || (value != 0.0 && value.abs() < 1e-4) {
+            format!("{:.0e}", value)
+        } else {
+            format!("{:.*}", decimals, value)
+        }
+    }
+}
@@ -4,22 +4,29 @@ pub struct WebSearch;
 
 impl WebSearch {
     pub fn search_youtube(query: &str) -> Result<(), Box<dyn std::error::Error>> {
-        let url = format!("https://www.youtube.com/results?search_query={}", 
-                         urlencoding::encode(query));
-        Self::open_url(&url)
+        Self::open_url(&Self::search_url("youtube", query).unwrap_or_default())
     }
 
-    pub fn search_chatgpt(_query: &str) -> Result<(), Box<dyn std::error::Error>> {
-        // Note: ChatGPT doesn't have a direct search URL, but we can open the main page
-        // Users would need to paste their query. Alternatively, we could use the API.
-        let url = "https://chat.openai.com/";
-        Self::open_url(url)
+    pub fn search_chatgpt(query: &str) -> Result<(), Box<dyn std::error::Error>> {
+        Self::open_url(&Self::search_url("chatgpt", query).unwrap_or_default())
     }
 
     pub fn search_google(query: &str) -> Result<(), Box<dyn std::error::Error>> {
-        let url = format!("https://www.google.com/search?q={}", 
-                         urlencoding::encode(query));
-        Self::open_url(&url)
+        Self::open_url(&Self::search_url("google", query).unwrap_or_default())
+    }
+
+    /// Address a search opens, also what is copied for it
+    pub fn search_url(engine: &str, query: &str) -> Option<String> {
+        match engine {
+            "youtube" => Some(format!("https://www.youtube.com/results?search_query={}",
+                                      urlencoding::encode(query))),
+            // Note: ChatGPT doesn't have a direct search URL, but we can open the main page
+            // Users would need to paste their query. Alternatively, we could use the API.
+            "chatgpt" => Some("https://chat.openai.com/".to_string()),
+            "google" => Some(format!("https://www.google.com/search?q={}",
+                                     urlencoding::encode(query))),
+            _ => None,
+        }
     }
 
     fn open_url(url: &str) -> Result<(), Box<dyn std::error::Error>> {
@@ -1,6 +1,6 @@
 use crate::config::Config;
 use gtk::prelude::*;
-use gtk::{Window, Box as GtkBox, Label, Entry, Button, SpinButton, CheckButton, Application};
+use gtk::{Window, Box as GtkBox, Label, Entry, Button, SpinButton, CheckButton, DropDown, Application};
 use std::sync::{Arc, Mutex};
 
 pub struct SettingsWindow;
@@ -162,6 +162,289 @@ impl SettingsWindow {
         }
         main_box.append(&calc_check);
 
+        // Exact arithmetic
+        let exact_check = CheckButton::with_label("Exact Arithmetic (big integers and fractions)");
+        {
+            let config_guard = config.lock().unwrap();
+            exact_check.set_active(config_guard.calculator.exact);
+        }
+        main_box.append(&exact_check);
+
+        // Precision
+        let precision_box = GtkBox::builder()
+            .orientation(gtk::Orientation::Horizontal)
+            .spacing(10)
+            .build();
+        let precision_label = Label::new(Some("Decimal Places:"));
+        precision_label.set_halign(gtk::Align::Start);
+        let precision_spin = SpinButton::with_range(0.0, 100.0, 1.0);
+        {
+            let config_guard = config.lock().unwrap();
+            precision_spin.set_value(config_guard.calculator.precision as f64);
+        }
+        precision_box.append(&precision_label);
+        precision_box.append(&precision_spin);
+        main_box.append(&precision_box);
+
+        // Notation
+        let notations = ["auto", "scientific", "engineering"];
+        let notation_box = GtkBox::builder()
+            .orientation(gtk::Orientation::Horizontal)
+            .spacing(10)
+            .build();
+        let notation_label = Label::new(Some("Notation:"));
+        notation_label.set_halign(gtk::Align::Start);
+        let notation_dropdown = DropDown::from_strings(&notations);
+        {
+            let config_guard = config.lock().unwrap();
+            let selected = notations
+                .iter()
+                .position(|n| *n == config_guard.calculator.notation)
+                .unwrap_or(0);
+            notation_dropdown.set_selected(selected as u32);
+        }
+        notation_box.append(&notation_label);
+        notation_box.append(&notation_dropdown);
+        main_box.append(&notation_box);
+
+        // Thousands separators
+        let grouping_check = CheckButton::with_label("Thousands Separators");
+        {
+            let config_guard = config.lock().unwrap();
+            grouping_check.set_active(config_guard.calculator.grouping);
+        }
+        main_box.append(&grouping_check);
+
+        // Number locale
+        let locale_box = GtkBox::builder()
+            .orientation(gtk::Orientation::Horizontal)
+            .spacing(10)
+            .build();
+        let locale_label = Label::new(Some("Number Locale:"));
+        locale_label.set_halign(gtk::Align::Start);
+        let locale_entry = Entry::new();
+        locale_entry.set_placeholder_text(Some("auto, en_US, de_DE, ..."));
+        {
+            let config_guard = config.lock().unwrap();
+            locale_entry.set_text(&config_guard.calculator.locale);
+        }
+        locale_box.append(&locale_label);
+        locale_box.append(&locale_entry);
+        main_box.append(&locale_box);
+
+        // History size
+        let history_box = GtkBox::builder()
+            .orientation(gtk::Orientation::Horizontal)
+            .spacing(10)
+            .build();
+        let history_label = Label::new(Some("History Size (0 = off):"));
+        history_label.set_halign(gtk::Align::Start);
+        let history_spin = SpinButton::with_range(0.0, 10000.0, 10.0);
+        {
+            let config_guard = config.lock().unwrap();
+            history_spin.set_value(config_guard.calculator.history_size as f64);
+        }
+        history_box.append(&history_label);
+        history_box.append(&history_spin);
+        main_box.append(&history_box);
+
+        // Emoji Section
+        let emoji_label = Label::new(Some("<b>Emoji</b>"));
+        emoji_label.set_use_markup(true);
+        emoji_label.set_halign(gtk::Align::Start);
+        emoji_label.set_margin_top(20);
+        main_box.append(&emoji_label);
+
+        // Default skin tone
+        let skin_tones = ["default", "light", "medium-light", "medium", "medium-dark", "dark"];
+        let skin_tone_box = GtkBox::builder()
+            .orientation(gtk::Orientation::Horizontal)
+            .spacing(10)
+            .build();
+        let skin_tone_label = Label::new(Some("Default Skin Tone:"));
+        skin_tone_label.set_halign(gtk::Align::Start);
+        let skin_tone_dropdown = DropDown::from_strings(&skin_tones);
+        {
+            let config_guard = config.lock().unwrap();
+            let selected = skin_tones
+                .iter()
+                .position(|t| *t == config_guard.emoji.skin_tone)
+                .unwrap_or(0);
+            skin_tone_dropdown.set_selected(selected as u32);
+        }
+        skin_tone_box.append(&skin_tone_label);
+        skin_tone_box.append(&skin_tone_dropdown);
+        main_box.append(&skin_tone_box);
+
+        // Search languages
+        let languages_box = GtkBox::builder()
+            .orientation(gtk::Orientation::Horizontal)
+            .spacing(10)
+            .build();
+        let languages_label = Label::new(Some("Search Languages:"));
+        languages_label.set_halign(gtk::Align::Start);
+        let languages_entry = Entry::new();
+        languages_entry.set_placeholder_text(Some("From locale, or e.g. de, fr"));
+        {
+            let config_guard = config.lock().unwrap();
+            languages_entry.set_text(&config_guard.emoji.languages.join(", "));
+        }
+        languages_box.append(&languages_label);
+        languages_box.append(&languages_entry);
+        main_box.append(&languages_box);
+
+        // Snippets Section
+        let snippets_label = Label::new(Some("<b>Snippets</b>"));
+        snippets_label.set_use_markup(true);
+        snippets_label.set_halign(gtk::Align::Start);
+        snippets_label.set_margin_top(20);
+        main_box.append(&snippets_label);
+
+        let snippet_prefix_box = GtkBox::builder()
+            .orientation(gtk::Orientation::Horizontal)
+            .spacing(10)
+            .build();
+        let snippet_prefix_label = Label::new(Some("Prefix:"));
+        snippet_prefix_label.set_halign(gtk::Align::Start);
+        let snippet_prefix_entry = Entry::new();
+        snippet_prefix_entry.set_placeholder_text(Some("Empty disables snippets"));
+        let builtin_snippets_check = CheckButton::with_label("Built-in Kaomoji and Symbols");
+        {
+            let config_guard = config.lock().unwrap();
+            snippet_prefix_entry.set_text(&config_guard.snippets.prefix);
+            builtin_snippets_check.set_active(config_guard.snippets.builtin);
+        }
+        snippet_prefix_box.append(&snippet_prefix_label);
+        snippet_prefix_box.append(&snippet_prefix_entry);
+        main_box.append(&snippet_prefix_box);
+        main_box.append(&builtin_snippets_check);
+
+        // Insertion Section
+        let insert_label = Label::new(Some("<b>Inserting Text</b>"));
+        insert_label.set_use_markup(true);
+        insert_label.set_halign(gtk::Align::Start);
+        insert_label.set_margin_top(20);
+        main_box.append(&insert_label);
+
+        // Emoji, characters and snippets are typed, pasted or only copied
+        let insert_methods = ["type", "paste", "clipboard"];
+        let insert_method_box = GtkBox::builder()
+            .orientation(gtk::Orientation::Horizontal)
+            .spacing(10)
+            .build();
+        let insert_method_label = Label::new(Some("Method:"));
+        insert_method_label.set_halign(gtk::Align::Start);
+        let insert_method_dropdown = DropDown::from_strings(&insert_methods);
+        let restore_clipboard_check = CheckButton::with_label("Restore Clipboard After Pasting");
+        {
+            let config_guard = config.lock().unwrap();
+            let selected = insert_methods
+                .iter()
+                .position(|m| *m == config_guard.insert.method)
+                .unwrap_or(0);
+            insert_method_dropdown.set_selected(selected as u32);
+            restore_clipboard_check.set_active(config_guard.insert.restore_clipboard);
+        }
+        insert_method_box.append(&insert_method_label);
+        insert_method_box.append(&insert_method_dropdown);
+        main_box.append(&insert_method_box);
+        main_box.append(&restore_clipboard_check);
+
+        // Clipboard Section
+        let clipboard_label = Label::new(Some("<b>Clipboard History</b>"));
+        clipboard_label.set_use_markup(true);
+        clipboard_label.set_halign(gtk::Align::Start);
+        clipboard_label.set_margin_top(20);
+        main_box.append(&clipboard_label);
+
+        let clipboard_history_check = CheckButton::with_label("Remember Copied Content (\"cb\")");
+        let encrypt_history_check = CheckButton::with_label("Encrypt History File");
+        let clipboard_size_box = GtkBox::builder()
+            .orientation(gtk::Orientation::Horizontal)
+            .spacing(10)
+            .build();
+        let clipboard_size_label = Label::new(Some("Entries Kept (besides pinned):"));
+        clipboard_size_label.set_halign(gtk::Align::Start);
+        let clipboard_size_spin = SpinButton::with_range(1.0, 1000.0, 10.0);
+        let exclude_apps_box = GtkBox::builder()
+            .orientation(gtk::Orientation::Horizontal)
+            .spacing(10)
+            .build();
+        let exclude_apps_label = Label::new(Some("Ignore Copies From:"));
+        exclude_apps_label.set_halign(gtk::Align::Start);
+        let exclude_apps_entry = Entry::new();
+        exclude_apps_entry.set_placeholder_text(Some("e.g. keepassxc, bitwarden"));
+        {
+            let config_guard = config.lock().unwrap();
+            clipboard_history_check.set_active(config_guard.clipboard.history);
+            encrypt_history_check.set_active(config_guard.clipboard.encrypt_history);
+            clipboard_size_spin.set_value(config_guard.clipboard.history_size as f64);
+            exclude_apps_entry.set_text(&config_guard.clipboard.exclude_apps.join(", "));
+        }
+        clipboard_size_box.append(&clipboard_size_label);
+        clipboard_size_box.append(&clipboard_size_spin);
+        exclude_apps_box.append(&exclude_apps_label);
+        exclude_apps_box.append(&exclude_apps_entry);
+        main_box.append(&clipboard_history_check);
+        main_box.append(&clipboard_size_box);
+        main_box.append(&exclude_apps_box);
+        main_box.append(&encrypt_history_check);
+
+        // Terminal Section
+        let terminal_label = Label::new(Some("<b>Terminal Commands</b>"));
+        terminal_label.set_use_markup(true);
+        terminal_label.set_halign(gtk::Align::Start);
+        terminal_label.set_margin_top(20);
+        main_box.append(&terminal_label);
+
+        // Empty picks the terminal from the xdg-terminal-exec preferences
+        let terminal_command_box = GtkBox::builder()
+            .orientation(gtk::Orientation::Horizontal)
+            .spacing(10)
+            .build();
+        let terminal_command_label = Label::new(Some("Terminal:"));
+        terminal_command_label.set_halign(gtk::Align::Start);
+        let terminal_command_entry = Entry::new();
+        terminal_command_entry.set_placeholder_text(Some("Automatic, or e.g. kitty --title {title} {argv}"));
+        terminal_command_entry.set_hexpand(true);
+        let working_directory_box = GtkBox::builder()
+            .orientation(gtk::Orientation::Horizontal)
+            .spacing(10)
+            .build();
+        let working_directory_label = Label::new(Some("Working Directory:"));
+        working_directory_label.set_halign(gtk::Align::Start);
+        let working_directory_entry = Entry::new();
+        working_directory_entry.set_placeholder_text(Some("e.g. ~/Projects"));
+        let terminal_title_box = GtkBox::builder()
+            .orientation(gtk::Orientation::Horizontal)
+            .spacing(10)
+            .build();
+        let terminal_title_label = Label::new(Some("Window Title:"));
+        terminal_title_label.set_halign(gtk::Align::Start);
+        let terminal_title_entry = Entry::new();
+        terminal_title_entry.set_placeholder_text(Some("{command}"));
+        let hold_check = CheckButton::with_label("Keep Window Open After the Command");
+        let shell_history_check = CheckButton::with_label("Suggest Commands From Shell History");
+        {
+            let config_guard = config.lock().unwrap();
+            terminal_command_entry.set_text(&config_guard.terminal.command);
+            working_directory_entry.set_text(&config_guard.terminal.working_directory);
+            terminal_title_entry.set_text(&config_guard.terminal.title);
+            hold_check.set_active(config_guard.terminal.hold);
+            shell_history_check.set_active(config_guard.terminal.shell_history);
+        }
+        terminal_command_box.append(&terminal_command_label);
+        terminal_command_box.append(&terminal_command_entry);
+        working_directory_box.append(&working_directory_label);
+        working_directory_box.append(&working_directory_entry);
+        terminal_title_box.append(&terminal_title_label);
+        terminal_title_box.append(&terminal_title_entry);
+        main_box.append(&terminal_command_box);
+        main_box.append(&working_directory_box);
+        main_box.append(&terminal_title_box);
+        main_box.append(&hold_check);
+        main_box.append(&shell_history_check);
+
         // Buttons
         let button_box = GtkBox::builder()
             .orientation(gtk::Orientation::Horizontal)
@@ -190,6 +473,27 @@ impl SettingsWindow {
         let yt_check_clone = yt_check.clone();
         let gpt_check_clone = gpt_check.clone();
         let calc_check_clone = calc_check.clone();
+        let exact_check_clone = exact_check.clone();
+        let precision_spin_clone = precision_spin.clone();
+        let notation_dropdown_clone = notation_dropdown.clone();
+        let grouping_check_clone = grouping_check.clone();
+        let locale_entry_clone = locale_entry.clone();
+        let history_spin_clone = history_spin.clone();
+        let skin_tone_dropdown_clone = skin_tone_dropdown.clone();
+        let languages_entry_clone = languages_entry.clone();
+        let snippet_prefix_entry_clone = snippet_prefix_entry.clone();
+        let builtin_snippets_check_clone = builtin_snippets_check.clone();
+        let insert_method_dropdown_clone = insert_method_dropdown.clone();
+        let restore_clipboard_check_clone = restore_clipboard_check.clone();
+        let clipboard_history_check_clone = clipboard_history_check.clone();
+        let encrypt_history_check_clone = encrypt_history_check.clone();
+        let clipboard_size_spin_clone = clipboard_size_spin.clone();
+        let exclude_apps_entry_clone = exclude_apps_entry.clone();
+        let terminal_command_entry_clone = terminal_command_entry.clone();
+        let working_directory_entry_clone = working_directory_entry.clone();
+        let terminal_title_entry_clone = terminal_title_entry.clone();
+        let hold_check_clone = hold_check.clone();
+        let shell_history_check_clone = shell_history_check.clone();
         
         let window_clone = window.clone();
         let config_clone = config.clone();
@@ -209,6 +513,57 @@ impl SettingsWindow {
             
             // Update calculator
             config_guard.calculator.enabled = calc_check_clone.is_active();
+            config_guard.calculator.exact = exact_check_clone.is_active();
+            config_guard.calculator.precision = precision_spin_clone.value() as usize;
+            config_guard.calculator.notation = notations
+                .get(notation_dropdown_clone.selected() as usize)
+                .unwrap_or(&"auto")
+                .to_string();
+            config_guard.calculator.grouping = grouping_check_clone.is_active();
+            let locale = locale_entry_clone.text().trim().to_string();
+            config_guard.calculator.locale = if locale.is_empty() { "auto".to_string() } else { locale };
+            config_guard.calculator.history_size = history_spin_clone.value() as usize;
+
+            // Update emoji
+            config_guard.emoji.skin_tone = skin_tones
+                .get(skin_tone_dropdown_clone.selected() as usize)
+                .unwrap_or(&"default")
+                .to_string();
+            config_guard.emoji.languages = languages_entry_clone
+                .text()
+                .split(',')
+                .map(|l| l.trim().to_string())
+                .filter(|l| !l.is_empty())
+                .collect();
+
+            // Update snippets
+            config_guard.snippets.prefix = snippet_prefix_entry_clone.text().trim().to_string();
+            config_guard.snippets.builtin = builtin_snippets_check_clone.is_active();
+
+            // Update insertion
+            config_guard.insert.method = insert_methods
+                .get(insert_method_dropdown_clone.selected() as usize)
+                .unwrap_or(&"type")
+                .to_string();
+            config_guard.insert.restore_clipboard = restore_clipboard_check_clone.is_active();
+
+            // Update clipboard history
+            config_guard.clipboard.history = clipboard_history_check_clone.is_active();
+            config_guard.clipboard.encrypt_history = encrypt_history_check_clone.is_active();
+            config_guard.clipboard.history_size = clipboard_size_spin_clone.value() as usize;
+            config_guard.clipboard.exclude_apps = exclude_apps_entry_clone
+                .text()
+                .split(',')
+                .map(|app| app.trim().to_string())
+                .filter(|app| !app.is_empty())
+                .collect();
+
+            // Update terminal
+            config_guard.terminal.command = terminal_command_entry_clone.text().trim().to_string();
+            config_guard.terminal.working_directory = working_directory_entry_clone.text().trim().to_string();
+            config_guard.terminal.title = terminal_title_entry_clone.text().to_string();
+            config_guard.terminal.hold = hold_check_clone.is_active();
+            config_guard.terminal.shell_history = shell_history_check_clone.is_active();
             
             // Save to file
             if let Err(e) = config_guard.save() {
@@ -0,0 +1,370 @@
+use crate::config::TerminalConfig;
+use crate::utils;
+use fuzzy_matcher::skim::SkimMatcherV2;
+use fuzzy_matcher::FuzzyMatcher;
+use rusqlite::{Connection, OpenFlags};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Commands the user ran before, for suggestions in terminal mode: the bash, zsh and fish
+/// history files, atuin's database, and the commands run from the launcher, which are kept in
+/// ~/.local/share/poppi_launcher/command_history.json
+#[derive(Debug, Clone, Default)]
+pub struct ShellHistory {
+    commands: Vec<HistoryCommand>, // Every source, one entry per command
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HistoryCommand {
+    pub command: String,
+    pub count: u32,     // Times it was run
+    pub last_used: u64, // Unix timestamp
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct LauncherHistory {
+    commands: Vec<HistoryCommand>,
+}
+
+// Commands run from the launcher that are remembered, the least recently used go first
+const LAUNCHER_HISTORY_SIZE: usize = 500;
+// Longer commands (pasted scripts) aren't suggested
+const MAX_COMMAND_CHARS: usize = 500;
+
+impl ShellHistory {
+    pub fn history_path() -> PathBuf {
+        dirs::data_dir()
+            .unwrap_or_else(|| PathBuf::from("."))
+            .join("poppi_launcher")
+            .join("command_history.json")
+    }
+
+    /// The launcher's history, and with `shell_history` every shell history there is; a
+    /// missing or unreadable one is left out
+    pub fn load(config: &TerminalConfig) -> Self {
+        let mut runs = Self::read_launcher().commands;
+        if config.shell_history {
+            let home = dirs::home_dir().unwrap_or_else(|| PathBuf::from("."));
+            let data_dir = dirs::data_dir().unwrap_or_else(|| home.join(".local/share"));
+            let zsh_dir = std::env::var_os("ZDOTDIR").map(PathBuf::from).unwrap_or_else(|| home.clone());
+            runs.extend(Self::read_bash(&home.join(".bash_history")));
+            runs.extend(Self::read_zsh(&zsh_dir.join(".zsh_history")));
+            runs.extend(Self::read_fish(&data_dir.join("fish").join("fish_history")));
+            runs.extend(Self::read_atuin(&data_dir.join("atuin").join("history.db")));
+        }
+
+        let mut history = Self::default();
+        history.merge(runs);
+        history
+    }
+
+    /// Remember a command run from the launcher. The file is read again first, another
+    /// launcher may have run commands since this one started.
+    pub fn record(&mut self, command: &str) -> Result<(), Box<dyn std::error::Error>> {
+        let command = command.trim();
+        if command.is_empty() {
+            return Ok(());
+        }
+        let run = HistoryCommand { command: command.to_string(), count: 1, last_used: Self::now() };
+        self.merge(vec![run.clone()]);
+
+        let mut launcher = Self::read_launcher();
+        match launcher.commands.iter_mut().find(|entry| entry.command == run.command) {
+            Some(entry) => {
+                entry.count += 1;
+                entry.last_used = run.last_used;
+            }
+            None => launcher.commands.push(run),
+        }
+        launcher.commands.sort_by_key(|entry| std::cmp::Reverse(entry.last_used));
+        launcher.commands.truncate(LAUNCHER_HISTORY_SIZE);
+
+        let path = Self::history_path();
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(&path, serde_json::to_string_pretty(&launcher)?)?;
+        Ok(())
+    }
+
+    fn read_launcher() -> LauncherHistory {
+        fs::read_to_string(Self::history_path())
+            .ok()
+            .and_then(|contents| serde_json::from_str(&contents).ok())
+            .unwrap_or_default()
+    }
+
+    /// Past commands matching what is typed, best first: a fuzzy match, raised for commands
+    /// that start with it and for frecency (run often and lately)
+    pub fn search(&self, query: &str) -> Vec<&HistoryCommand> {
+        let query = query.trim();
+        if query.is_empty() {
+            return Vec::new();
+        }
+
+        let matcher = SkimMatcherV2::default();
+        let now = Self::now();
+        let mut results: Vec<(&HistoryCommand, i64)> = self
+            .commands
+            .iter()
+            .filter(|entry| entry.command != query)
+            .filter_map(|entry| {
+                let score = matcher.fuzzy_match(&entry.command, query)?;
+                let prefix = if entry.command.starts_with(query) { 50 } else { 0 };
+                Some((entry, score + prefix + (entry.frecency(now) * 10.0) as i64))
+            })
+            .collect();
+        results.sort_by_key(|(_, score)| std::cmp::Reverse(*score));
+        results.into_iter().map(|(entry, _)| entry).collect()
+    }
+
+    /// Count runs of the same command together, keeping the latest time
+    fn merge(&mut self, runs: Vec<HistoryCommand>) {
+        let mut index: HashMap<String, usize> = self
+            .commands
+            .iter()
+            .enumerate()
+            .map(|(i, entry)| (entry.command.clone(), i))
+            .collect();
+        for run in runs {
+            let command = run.command.trim();
+            if command.is_empty() || command.chars().count() > MAX_COMMAND_CHARS {
+                continue;
+            }
+            match index.get(command) {
+                Some(&i) => {
+                    self.commands[i].count += run.count;
+                    self.commands[i].last_used = self.commands[i].last_used.max(run.last_used);
+                }
+                None => {
+                    index.insert(command.to_string(), self.commands.len());
+                    self.commands.push(HistoryCommand { command: command.to_string(), ..run });
+                }
+            }
+        }
+    }
+
+    /// One command per line; with HISTTIMEFORMAT set bash writes "#<timestamp>" before each
+    fn read_bash(path: &Path) -> Vec<HistoryCommand> {
+        let Ok(bytes) = fs::read(path) else {
+            return Vec::new();
+        };
+        let contents = String::from_utf8_lossy(&bytes);
+        let mut runs = Vec::new();
+        let mut timestamp = None;
+        for line in contents.lines() {
+            if let Some(seconds) = line.strip_prefix('#').and_then(|rest| rest.parse().ok()) {
+                timestamp = Some(seconds);
+                continue;
+            }
+            runs.push((line.to_string(), timestamp.take()));
+        }
+        Self::with_times(runs, path)
+    }
+
+    /// Plain lines, or ": <start>:<duration>;<command>" with EXTENDED_HISTORY. A command
+    /// continued on the next line ends in a backslash.
+    fn read_zsh(path: &Path) -> Vec<HistoryCommand> {
+        let Ok(bytes) = fs::read(path) else {
+            return Vec::new();
+        };
+        let contents = String::from_utf8_lossy(&Self::unmetafy(&bytes)).into_owned();
+        let mut runs = Vec::new();
+        let mut lines = contents.lines();
+        while let Some(line) = lines.next() {
+            let mut entry = line.to_string();
+            while entry.ends_with('\\') {
+                entry.pop();
+                entry.push('\n');
+                match lines.next() {
+                    Some(next) => entry.push_str(next),
+                    None => break,
+                }
+            }
+            let extended = entry
+                .strip_prefix(": ")
+                .and_then(|rest| rest.split_once(';'))
+                .and_then(|(times, command)| Some((command.to_string(), times.split(':').next()?.parse().ok()?)));
+            match extended {
+                Some((command, start)) => runs.push((command, Some(start))),
+                None => runs.push((entry, None)),
+            }
+        }
+        Self::with_times(runs, path)
+    }
+
+    /// zsh stores bytes that mean something to it as 0x83 followed by the byte xor 32
+    fn unmetafy(bytes: &[u8]) -> Vec<u8> {
+        let mut plain = Vec::with_capacity(bytes.len());
+        let mut iter = bytes.iter();
+        while let Some(&byte) = iter.next() {
+            match byte {
+                0x83 => plain.extend(iter.next().map(|next| next ^ 32)),
+                _ => plain.push(byte),
+            }
+        }
+        plain
+    }
+
+    /// fish's YAML-like file: "- cmd: <command>" then "  when: <timestamp>", newlines and
+    /// backslashes in the command escaped
+    fn read_fish(path: &Path) -> Vec<HistoryCommand> {
+        let Ok(contents) = fs::read_to_string(path) else {
+            return Vec::new();
+        };
+        let mut runs: Vec<HistoryCommand> = Vec::new();
+        for line in contents.lines() {
+            if let Some(command) = line.strip_prefix("- cmd: ") {
+                let command = command.replace("\\\\", "\u{0}").replace("\\n", "\n").replace('\u{0}', "\\");
+                runs.push(HistoryCommand { command, count: 1, last_used: 0 });
+            } else if let Some(when) = line.trim_start().strip_prefix("when: ") {
+                if let Some(run) = runs.last_mut() {
+                    run.last_used = when.trim().parse().unwrap_or(0);
+                }
+            }
+        }
+        runs
+    }
+
+    /// atuin's SQLite database, read-only; its timestamps are in nanoseconds
+    fn read_atuin(path: &Path) -> Vec<HistoryCommand> {
+        if !path.exists() {
+            return Vec::new();
+        }
+        let read = || -> rusqlite::Result<Vec<HistoryCommand>> {
+            let connection = Connection::open_with_flags(path, OpenFlags::SQLITE_OPEN_READ_ONLY)?;
+            let mut statement = connection.prepare(
+                "SELECT command, COUNT(*), MAX(timestamp) FROM history WHERE deleted_at IS NULL GROUP BY command",
+            )?;
+            let rows = statement.query_map([], |row| {
+                Ok(HistoryCommand {
+                    command: row.get(0)?,
+                    count: row.get(1)?,
+                    last_used: (row.get::<_, i64>(2)? / 1_000_000_000).max(0) as u64,
+                })
+            })?;
+            rows.collect()
+        };
+        read().unwrap_or_else(|e| {
+            eprintln!("Error reading atuin history: {}", e);
+            Vec::new()
+        })
+    }
+
+    /// Lines without a time are ordered oldest first and the file was written by the last
+    /// one, so they get the file's time, a second earlier for each line after them
+    fn with_times(runs: Vec<(String, Option<u64>)>, path: &Path) -> Vec<HistoryCommand> {
+        let written = fs::metadata(path)
+            .and_then(|metadata| metadata.modified())
+            .ok()
+            .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+            .map(|d| d.as_secs())
+            .unwrap_or(0);
+        let total = runs.len() as u64;
+        runs.into_iter()
+            .enumerate()
+            .map(|(i, (command, timestamp))| HistoryCommand {
+                command,
+                count: 1,
+                last_used: timestamp.unwrap_or_else(|| written.saturating_sub(total - 1 - i as u64)),
+            })
+            .collect()
+    }
+
+    fn now() -> u64 {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0)
+    }
+}
+
+impl HistoryCommand {
+    /// Grows with the number of runs, weighted by how recent the last one was
+    fn frecency(&self, now: u64) -> f64 {
+        let recency = match now.saturating_sub(self.last_used) {
+            0..=3599 => 4.0,
+            3600..=86399 => 2.0,
+            86400..=604799 => 1.0,
+            604800..=2591999 => 0.5,
+            _ => 0.25,
+        };
+        recency * (1.0 + f64::from(self.count).ln())
+    }
+
+    /// "12 runs · 5 min ago"
+    pub fn description(&self) -> String {
+        let runs = if self.count == 1 { "1 run".to_string() } else { format!("{} runs", self.count) };
+        format!("{} · {}", runs, utils::format_age(self.last_used))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Write `contents` to a file of its own in the temp directory
+    fn history_file(name: &str, contents: &[u8]) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("poppi_launcher-test-{}-{}", std::process::id(), name));
+        fs::write(&path, contents).unwrap();
+        path
+    }
+
+    fn commands(runs: &[HistoryCommand]) -> Vec<&str> {
+        runs.iter().map(|run| run.command.as_str()).collect()
+    }
+
+    #[test]
+    fn test_read_bash() {
+        let path = history_file("bash", b"ls -la\n#1700000000\ngit status\nmake\n");
+        let runs = ShellHistory::read_bash(&path);
+        fs::remove_file(&path).unwrap();
+        assert_eq!(commands(&runs), ["ls -la", "git status", "make"]);
+        assert_eq!(runs[1].last_used, 1_700_000_000);
+        // Lines without a time count back from the file's, oldest first
+        assert!(runs[0].last_used < runs[2].last_used);
+    }
+
+    #[test]
+    fn test_read_zsh() {
+        let mut contents = b": 1700000000:0;echo one\\\ntwo\nplain\n: 1700000100:3;echo ".to_vec();
+        // "é" is 0xC3 0xA9; zsh writes bytes from 0x83 up metafied
+        contents.extend([0x83, 0xC3 ^ 32, 0x83, 0xA9 ^ 32, b'\n']);
+        let path = history_file("zsh", &contents);
+        let runs = ShellHistory::read_zsh(&path);
+        fs::remove_file(&path).unwrap();
+        assert_eq!(commands(&runs), ["echo one\ntwo", "plain", "echo é"]);
+        assert_eq!(runs[0].last_used, 1_700_000_000);
+        assert_eq!(runs[2].last_used, 1_700_000_100);
+        assert_eq!(ShellHistory::unmetafy(&[b'a', 0x83, 0xA3]), [b'a', 0x83]);
+    }
+
+    #[test]
+    fn test_read_fish() {
+        let path = history_file(
+            "fish",
+            b"- cmd: cargo build\n  when: 1700000000\n- cmd: echo a\\nb \\\\n\n  when: 1700000050\n  paths:\n    - src\n",
+        );
+        let runs = ShellHistory::read_fish(&path);
+        fs::remove_file(&path).unwrap();
+        assert_eq!(commands(&runs), ["cargo build", "echo a\nb \\n"]);
+        assert_eq!(runs[1].last_used, 1_700_000_050);
+    }
+
+    #[test]
+    fn test_merge_and_search() {
+        let run = |command: &str, last_used| HistoryCommand { command: command.to_string(), count: 1, last_used };
+        let mut history = ShellHistory::default();
+        history.merge(vec![run("git status", 10), run("git push", 20), run(" git status ", 30), run("", 40)]);
+        assert_eq!(history.commands.len(), 2);
+        assert_eq!(history.commands[0], HistoryCommand { command: "git status".to_string(), count: 2, last_used: 30 });
+
+        assert_eq!(history.search("git s")[0].command, "git status");
+        // The command already typed out isn't suggested again
+        assert!(history.search("git push").is_empty());
+        assert!(history.search("  ").is_empty());
+    }
+}
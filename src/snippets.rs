@@ -0,0 +1,301 @@
+use crate::config::SnippetConfig;
+use crate::datetime::DateTimeCalculator;
+use crate::insertion::Insertion;
+use fuzzy_matcher::skim::SkimMatcherV2;
+use fuzzy_matcher::FuzzyMatcher;
+use serde::{Deserialize, Serialize};
+use std::fmt::Write;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// A piece of text inserted by name, e.g. a signature or a kaomoji
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Snippet {
+    pub name: String,
+    #[serde(default)]
+    pub keywords: Vec<String>,
+    pub content: String,
+    /// Set the snippet belongs to, the file's `name` or file name
+    #[serde(skip)]
+    pub collection: String,
+}
+
+/// A snippet file in ~/.config/poppi_launcher/snippets/ (TOML or JSON)
+#[derive(Debug, Deserialize)]
+struct SnippetFile {
+    #[serde(default)]
+    name: Option<String>,
+    #[serde(default)]
+    snippets: Vec<Snippet>,
+}
+
+pub struct Snippets {
+    snippets: Vec<Snippet>,
+    matcher: SkimMatcherV2,
+}
+
+// Name, keywords and content of the built-in sets
+const KAOMOJI: &[(&str, &str, &str)] = &[
+    ("shrug", "whatever idk", "¯\\_(ツ)_/¯"),
+    ("table flip", "angry rage flip", "(╯°□°)╯︵ ┻━┻"),
+    ("table back", "calm put back", "┬─┬ノ( º _ ºノ)"),
+    ("lenny", "smirk", "( ͡° ͜ʖ ͡°)"),
+    ("disapproval", "look", "ಠ_ಠ"),
+    ("happy", "joy smile", "(＾▽＾)"),
+    ("excited", "yay happy", "＼(^o^)／"),
+    ("hug", "love", "(づ｡◕‿‿◕｡)づ"),
+    ("sparkles", "magic happy", "(ﾉ◕ヮ◕)ﾉ*:･ﾟ✧"),
+    ("cry", "sad tears", "(╥﹏╥)"),
+    ("sad", "unhappy", "(︶︹︺)"),
+    ("wink", "", "(^_~)"),
+    ("love", "heart", "(♥ω♥*)"),
+    ("bear", "cute", "ʕ•ᴥ•ʔ"),
+    ("cat", "meow", "(=^･ω･^=)"),
+    ("dog", "woof", "U・ᴥ・U"),
+    ("fight", "fists", "(ง'̀-'́)ง"),
+    ("sunglasses", "cool deal with it", "(•_•) ( •_•)>⌐■-■ (⌐■_■)"),
+    ("confused", "what", "(・・ ) ?"),
+    ("sleepy", "tired", "(－_－) zzZ"),
+    ("wave", "hello hi bye", "(^_^)/"),
+    ("bow", "sorry thanks", "m(_ _)m"),
+    ("surprised", "shock wow", "(⊙_⊙)"),
+    ("dance", "party", "└(＾＾)┐┌(＾＾)┘"),
+    ("strong", "flex muscle", "ᕦ(ò_óˇ)ᕤ"),
+];
+
+const SYMBOLS: &[(&str, &str, &str)] = &[
+    ("em dash", "dash long", "—"),
+    ("en dash", "dash range", "–"),
+    ("ellipsis", "dots", "…"),
+    ("bullet", "dot", "•"),
+    ("right arrow", "arrow", "→"),
+    ("left arrow", "arrow", "←"),
+    ("check mark", "tick yes done", "✓"),
+    ("cross mark", "no wrong", "✗"),
+    ("degree", "temperature", "°"),
+    ("plus minus", "tolerance", "±"),
+    ("multiplication", "times", "×"),
+    ("division", "divide", "÷"),
+    ("not equal", "unequal", "≠"),
+    ("less or equal", "le", "≤"),
+    ("greater or equal", "ge", "≥"),
+    ("infinity", "", "∞"),
+    ("euro", "currency money", "€"),
+    ("pound", "currency money sterling", "£"),
+    ("copyright", "", "©"),
+    ("registered", "trademark", "®"),
+    ("trademark", "tm", "™"),
+    ("section", "paragraph law", "§"),
+    ("non-breaking space", "nbsp", "\u{A0}"),
+    ("guillemets", "quotes french", "«{cursor}»"),
+    ("german quotes", "quotes", "„{cursor}“"),
+];
+
+// Results shown for a query
+const MAX_RESULTS: usize = 20;
+
+impl Snippets {
+    /// User snippet files first, then the built-in kaomoji and symbols
+    pub fn load(config: &SnippetConfig) -> Self {
+        let mut snippets = Self::load_user_snippets(&Self::snippets_dir());
+        if config.builtin {
+            snippets.extend(Self::builtin("Kaomoji", KAOMOJI));
+            snippets.extend(Self::builtin("Symbols", SYMBOLS));
+        }
+        Self { snippets, matcher: SkimMatcherV2::default() }
+    }
+
+    pub fn snippets_dir() -> PathBuf {
+        dirs::config_dir()
+            .unwrap_or_else(|| PathBuf::from("."))
+            .join("poppi_launcher")
+            .join("snippets")
+    }
+
+    /// Snippets matching a query by name, keywords or set; all of them in order without one
+    pub fn search(&self, query: &str) -> Vec<&Snippet> {
+        let query = query.trim().to_lowercase();
+        if query.is_empty() {
+            return self.snippets.iter().take(MAX_RESULTS).collect();
+        }
+
+        let mut results: Vec<(&Snippet, i64)> = self
+            .snippets
+            .iter()
+            .filter_map(|snippet| {
+                let name = self.matcher.fuzzy_match(&snippet.name, &query);
+                let keyword = snippet
+                    .keywords
+                    .iter()
+                    .filter_map(|kw| self.matcher.fuzzy_match(kw, &query))
+                    .max();
+                let collection = self.matcher.fuzzy_match(&snippet.collection, &query).map(|s| s / 2);
+                let score = name.max(keyword).max(collection)?;
+                Some((snippet, score))
+            })
+            .collect();
+
+        results.sort_by_key(|(_, score)| std::cmp::Reverse(*score));
+        results.into_iter().take(MAX_RESULTS).map(|(snippet, _)| snippet).collect()
+    }
+
+    /// The snippet with its placeholders filled in, leaving the cursor at `{cursor}` if
+    /// there is one. `clipboard` is the clipboard text from before the launcher opened.
+    pub fn insertion(snippet: &Snippet, clipboard: Option<&str>) -> Insertion {
+        let (text, cursor_back) = Self::expand(&snippet.content, clipboard.unwrap_or_default());
+        Insertion { cursor_back, ..Insertion::new(&text) }
+    }
+
+    /// Replace `{date}`, `{time}`, `{date:%d.%m.%Y}` and `{clipboard}`. Returns the text
+    /// and how many characters follow `{cursor}`. `{{` and `}}` are literal braces.
+    pub fn expand(content: &str, clipboard: &str) -> (String, usize) {
+        let mut text = String::with_capacity(content.len());
+        let mut cursor = None;
+        let mut rest = content;
+
+        while let Some(start) = rest.find(['{', '}']) {
+            text.push_str(&rest[..start]);
+            let after = &rest[start..];
+
+            if after.starts_with("{{") || after.starts_with("}}") {
+                text.push_str(&after[..1]);
+                rest = &after[2..];
+                continue;
+            }
+
+            let placeholder = after.strip_prefix('{').and_then(|p| p.split_once('}'));
+            let Some((name, remainder)) = placeholder else {
+                text.push_str(&after[..1]);
+                rest = &after[1..];
+                continue;
+            };
+
+            match Self::placeholder(name, clipboard) {
+                Some(value) => text.push_str(&value),
+                None if name == "cursor" => cursor = Some(text.len()),
+                // Unknown placeholders are kept as written
+                None => text.push_str(&after[..name.len() + 2]),
+            }
+            rest = remainder;
+        }
+        text.push_str(rest);
+
+        let cursor_back = cursor.map(|position| text[position..].chars().count()).unwrap_or(0);
+        (text, cursor_back)
+    }
+
+    fn placeholder(name: &str, clipboard: &str) -> Option<String> {
+        let (kind, format) = match name.split_once(':') {
+            Some((kind, format)) => (kind, Some(format)),
+            None => (name, None),
+        };
+
+        // An invalid format makes chrono fail, the placeholder is then left as written
+        let now = |default: &str| {
+            let mut value = String::new();
+            write!(value, "{}", DateTimeCalculator::local_now().format(format.unwrap_or(default))).ok()?;
+            Some(value)
+        };
+
+        match kind {
+            "date" => now("%Y-%m-%d"),
+            "time" => now("%H:%M"),
+            "clipboard" => Some(clipboard.to_string()),
+            _ => None,
+        }
+    }
+
+    fn load_user_snippets(dir: &Path) -> Vec<Snippet> {
+        let Ok(entries) = fs::read_dir(dir) else {
+            return Vec::new();
+        };
+        let mut paths: Vec<PathBuf> = entries.filter_map(|e| e.ok()).map(|e| e.path()).collect();
+        paths.sort();
+
+        let mut snippets = Vec::new();
+        for path in paths {
+            match Self::load_file(&path) {
+                Ok(Some(file_snippets)) => snippets.extend(file_snippets),
+                Ok(None) => {}
+                Err(e) => eprintln!("Error loading snippets from {}: {}", path.display(), e),
+            }
+        }
+        snippets
+    }
+
+    /// Snippets of one file, `None` for files that are neither TOML nor JSON
+    fn load_file(path: &Path) -> Result<Option<Vec<Snippet>>, Box<dyn std::error::Error>> {
+        let contents = match path.extension().and_then(|e| e.to_str()) {
+            Some("toml") | Some("json") => fs::read_to_string(path)?,
+            _ => return Ok(None),
+        };
+        let file: SnippetFile = if path.extension().is_some_and(|e| e == "json") {
+            serde_json::from_str(&contents)?
+        } else {
+            toml::from_str(&contents)?
+        };
+
+        let collection = file.name.unwrap_or_else(|| {
+            path.file_stem().map(|s| s.to_string_lossy().to_string()).unwrap_or_default()
+        });
+        Ok(Some(
+            file.snippets
+                .into_iter()
+                .map(|snippet| Snippet { collection: collection.clone(), ..snippet })
+                .collect(),
+        ))
+    }
+
+    fn builtin(collection: &str, set: &[(&str, &str, &str)]) -> Vec<Snippet> {
+        set.iter()
+            .map(|(name, keywords, content)| Snippet {
+                name: name.to_string(),
+                keywords: keywords.split_whitespace().map(str::to_string).collect(),
+                content: content.to_string(),
+                collection: collection.to_string(),
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_expand() {
+        assert_eq!(Snippets::expand("Hi {clipboard}!", "Ann"), ("Hi Ann!".to_string(), 0));
+        assert_eq!(Snippets::expand("<b>{cursor}</b>", ""), ("<b></b>".to_string(), 4));
+        assert_eq!(Snippets::expand("é{cursor}→x", ""), ("é→x".to_string(), 2));
+        assert_eq!(Snippets::expand("{{cursor}} {nope} }", ""), ("{cursor} {nope} }".to_string(), 0));
+        assert_eq!(Snippets::expand("{date:%Q} {", ""), ("{date:%Q} {".to_string(), 0));
+
+        let (date, _) = Snippets::expand("{date}", "");
+        assert_eq!(date, DateTimeCalculator::local_now().format("%Y-%m-%d").to_string());
+        let (custom, _) = Snippets::expand("{date:%d.%m.%Y}", "");
+        assert_eq!(custom.len(), 10);
+        assert_eq!(&custom[2..3], ".");
+    }
+
+    #[test]
+    fn test_load_and_search() {
+        let dir = std::env::temp_dir().join(format!("poppi_launcher-test-{}-snippets", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(
+            dir.join("work.toml"),
+            "[[snippets]]\nname = \"Signature\"\nkeywords = [\"sig\"]\ncontent = \"Best,\\n{cursor}\"\n",
+        )
+        .unwrap();
+        fs::write(dir.join("notes.txt"), "not a snippet file").unwrap();
+        let mut snippets = Snippets::load_user_snippets(&dir);
+        fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(snippets.len(), 1);
+        assert_eq!(snippets[0].collection, "work");
+
+        snippets.extend(Snippets::builtin("Kaomoji", KAOMOJI));
+        let snippets = Snippets { snippets, matcher: SkimMatcherV2::default() };
+        assert_eq!(snippets.search("sig")[0].name, "Signature");
+        let kaomoji = snippets.search("kaomoji");
+        assert!(!kaomoji.is_empty() && kaomoji.iter().all(|snippet| snippet.collection == "Kaomoji"));
+    }
+}
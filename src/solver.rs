@@ -0,0 +1,1073 @@
+use crate::calculator::{Calculation, Calculator, Expr, Number, Representation, Value};
+use crate::config::CalculatorConfig;
+use num_bigint::BigInt;
+use num_complex::Complex64;
+use num_rational::BigRational;
+use num_traits::{Signed, ToPrimitive, Zero};
+use std::collections::{BTreeSet, HashMap};
+
+/// Equation solving and calculus helpers on top of the calculator's expression tree.
+///
+/// Polynomials are solved exactly where possible (rational roots, quadratic formula)
+/// and numerically otherwise (Durand-Kerner for all complex roots). Other equations
+/// fall back to a sign-change scan with bisection, linear systems use exact Gaussian
+/// elimination and non-linear systems Newton's method.
+pub struct Solver;
+
+/// A solution as shown to the user
+#[derive(Debug, Clone, PartialEq)]
+enum Root {
+    Exact(Number),
+    Complex(Complex64),
+}
+
+// Range scanned for roots of non-polynomial equations
+const SCAN_LIMITS: [(f64, usize); 2] = [(10.0, 4000), (1000.0, 4000)];
+const MAX_LISTED_ROOTS: usize = 12;
+const MAX_POLY_DEGREE: i64 = 64;
+
+impl Solver {
+    pub fn is_solve_query(query: &str) -> bool {
+        let lower = query.trim().to_lowercase();
+        lower.starts_with("solve ") && lower.contains('=')
+    }
+
+    pub fn solve(query: &str, config: &CalculatorConfig) -> Result<Calculation, String> {
+        let body = query.trim()[6..].trim();
+
+        // "solve x + y = 3, x - y = 1" / "solve x + y = 3 and x - y = 1"
+        let mut equations = Vec::new();
+        for equation in Self::split_equations(&body.replace(" and ", ";")) {
+            let (lhs, rhs) = equation
+                .split_once('=')
+                .ok_or_else(|| format!("'{}' is not an equation", equation))?;
+            let lhs = Calculator::parse(lhs, config)?;
+            let rhs = Calculator::parse(rhs, config)?;
+            // lhs = rhs  <=>  lhs - rhs = 0
+            equations.push(Expr::Binary('-', Box::new(lhs), Box::new(rhs)));
+        }
+
+        let mut variables = BTreeSet::new();
+        for equation in &equations {
+            Self::collect_variables(equation, &mut variables);
+        }
+        let variables: Vec<String> = variables.into_iter().collect();
+
+        if variables.is_empty() {
+            return Err("Nothing to solve for".to_string());
+        }
+
+        if variables.len() == 1 && equations.len() == 1 {
+            let var = &variables[0];
+            let roots = Self::solve_single(&equations[0], var)?;
+            return Ok(Self::format_roots(var, &roots, config));
+        }
+
+        if variables.len() != equations.len() {
+            return Err(format!(
+                "{} unknowns need {} equations",
+                variables.len(),
+                variables.len()
+            ));
+        }
+
+        let solution = Self::solve_system(&equations, &variables)?;
+        let parts: Vec<String> = variables
+            .iter()
+            .zip(&solution)
+            .map(|(var, value)| format!("{} = {}", var, Calculator::format_number(value, config).value))
+            .collect();
+        let alternatives = parts
+            .iter()
+            .zip(&variables)
+            .map(|(part, var)| Representation { label: var.clone(), value: part.clone() })
+            .take(4)
+            .collect();
+
+        Ok(Calculation { value: parts.join(",  "), alternatives })
+    }
+
+    /// Symbolic derivative of `expr` with respect to `var`, simplified
+    pub(crate) fn derivative(expr: &Expr, var: &str) -> Result<Expr, String> {
+        Ok(Self::simplify(Self::differentiate(expr, var)?))
+    }
+
+    /// deriv(f, x, a) and integrate(f, a, b) / integrate(f, x, a, b) inside expressions
+    pub(crate) fn call_calculus(name: &str, args: &[Expr], vars: &HashMap<String, Number>) -> Result<Number, String> {
+        match (name, args) {
+            ("deriv" | "diff", [f, Expr::Var(var), point]) => {
+                let derivative = Self::derivative(f, var)?;
+                let point = Calculator::eval(point, vars)?;
+                let mut inner = vars.clone();
+                inner.insert(var.clone(), point);
+                Calculator::eval(&derivative, &inner)
+            }
+            ("deriv" | "diff", [_, _]) => {
+                Err("deriv(f, x) gives a formula; add a point to get a value: deriv(f, x, 2)".to_string())
+            }
+            ("integrate" | "int", [f, a, b]) => {
+                let var = Self::single_variable(f)?;
+                Self::integrate(f, &var, a, b, vars)
+            }
+            ("integrate" | "int", [f, Expr::Var(var), a, b]) => Self::integrate(f, var, a, b, vars),
+            _ => Err(format!("{}() has the wrong number of arguments", name)),
+        }
+    }
+
+    /// Format an expression tree back into calculator syntax
+    pub(crate) fn format_expr(expr: &Expr) -> String {
+        Self::format_with_precedence(expr, 0)
+    }
+
+    /// Split on top-level ',' and ';', keeping commas inside calls and decimal commas
+    fn split_equations(body: &str) -> Vec<String> {
+        let mut parts: Vec<String> = Vec::new();
+        let mut current = String::new();
+        let mut depth = 0i32;
+        for c in body.chars() {
+            match c {
+                '(' | '[' => depth += 1,
+                ')' | ']' => depth -= 1,
+                ',' | ';' if depth == 0 => {
+                    parts.push(std::mem::take(&mut current));
+                    continue;
+                }
+                _ => {}
+            }
+            current.push(c);
+        }
+        parts.push(current);
+
+        // A piece without '=' was part of a number like "3,5"
+        let mut equations: Vec<String> = Vec::new();
+        for part in parts {
+            match equations.last_mut() {
+                Some(last) if !part.contains('=') || !last.contains('=') => {
+                    last.push(',');
+                    last.push_str(&part);
+                }
+                _ => equations.push(part),
+            }
+        }
+        equations
+            .into_iter()
+            .map(|e| e.trim().to_string())
+            .filter(|e| !e.is_empty())
+            .collect()
+    }
+
+    fn collect_variables(expr: &Expr, variables: &mut BTreeSet<String>) {
+        match expr {
+            Expr::Var(name) => {
+                // Named constants are not unknowns
+                if !Calculator::is_constant(name) {
+                    variables.insert(name.clone());
+                }
+            }
+            Expr::Number(_) => {}
+            Expr::Neg(inner) | Expr::Factorial(inner) | Expr::Percent(inner) => {
+                Self::collect_variables(inner, variables)
+            }
+            Expr::Binary(_, lhs, rhs) => {
+                Self::collect_variables(lhs, variables);
+                Self::collect_variables(rhs, variables);
+            }
+            Expr::Call(_, args) | Expr::Array(args) => {
+                for arg in args {
+                    Self::collect_variables(arg, variables);
+                }
+            }
+        }
+    }
+
+    fn single_variable(expr: &Expr) -> Result<String, String> {
+        let mut variables = BTreeSet::new();
+        Self::collect_variables(expr, &mut variables);
+        match variables.len() {
+            0 => Ok("x".to_string()),
+            1 => Ok(variables.into_iter().next().unwrap()),
+            _ => Err("Name the integration variable: integrate(f, x, a, b)".to_string()),
+        }
+    }
+
+    fn eval_at(expr: &Expr, var: &str, x: f64) -> f64 {
+        let mut vars = HashMap::new();
+        vars.insert(var.to_string(), Number::Approx(x));
+        Calculator::eval(expr, &vars).map(|n| n.to_f64()).unwrap_or(f64::NAN)
+    }
+
+    // ---- Single equations ----
+
+    fn solve_single(expr: &Expr, var: &str) -> Result<Vec<Root>, String> {
+        if let Some(coefficients) = Self::to_polynomial(expr, var) {
+            return Self::solve_polynomial(coefficients);
+        }
+
+        let roots = Self::scan_roots(expr, var);
+        if roots.is_empty() {
+            return Err("No real solution found".to_string());
+        }
+        Ok(roots.into_iter().map(|x| Root::Exact(Number::Approx(x))).collect())
+    }
+
+    /// Coefficients (lowest power first) if `expr` is a polynomial in `var`
+    fn to_polynomial(expr: &Expr, var: &str) -> Option<Vec<Number>> {
+        let zero = || Number::from_integer(0);
+        let poly = match expr {
+            Expr::Number(n) => vec![n.clone()],
+            Expr::Var(name) if name == var => vec![zero(), Number::from_integer(1)],
+            Expr::Neg(inner) => Self::to_polynomial(inner, var)?
+                .into_iter()
+                .map(|c| Calculator::apply_binary('-', zero(), c))
+                .collect::<Result<_, _>>()
+                .ok()?,
+            Expr::Binary(op @ ('+' | '-'), lhs, rhs) => {
+                let (a, b) = (Self::to_polynomial(lhs, var)?, Self::to_polynomial(rhs, var)?);
+                let len = a.len().max(b.len());
+                (0..len)
+                    .map(|i| {
+                        let x = a.get(i).cloned().unwrap_or_else(zero);
+                        let y = b.get(i).cloned().unwrap_or_else(zero);
+                        Calculator::apply_binary(*op, x, y)
+                    })
+                    .collect::<Result<_, _>>()
+                    .ok()?
+            }
+            Expr::Binary('*', lhs, rhs) => {
+                Self::multiply_polynomials(&Self::to_polynomial(lhs, var)?, &Self::to_polynomial(rhs, var)?)?
+            }
+            Expr::Binary('/', lhs, rhs) => {
+                let divisor = Self::to_polynomial(rhs, var)?;
+                if divisor.len() != 1 || divisor[0].is_zero() {
+                    return None;
+                }
+                Self::to_polynomial(lhs, var)?
+                    .into_iter()
+                    .map(|c| Calculator::apply_binary('/', c, divisor[0].clone()))
+                    .collect::<Result<_, _>>()
+                    .ok()?
+            }
+            Expr::Binary('^', base, exponent) => {
+                let exponent = Calculator::eval(exponent, &HashMap::new()).ok()?.as_integer()?.to_i64()?;
+                if !(0..=MAX_POLY_DEGREE).contains(&exponent) {
+                    return None;
+                }
+                let base = Self::to_polynomial(base, var)?;
+                let mut result = vec![Number::from_integer(1)];
+                for _ in 0..exponent {
+                    result = Self::multiply_polynomials(&result, &base)?;
+                }
+                result
+            }
+            // Anything else must be a constant
+            _ => vec![Calculator::eval(expr, &HashMap::new()).ok()?],
+        };
+        Some(Self::trim_polynomial(poly))
+    }
+
+    fn multiply_polynomials(a: &[Number], b: &[Number]) -> Option<Vec<Number>> {
+        if a.len() + b.len() > MAX_POLY_DEGREE as usize + 2 {
+            return None;
+        }
+        let mut result = vec![Number::from_integer(0); a.len() + b.len() - 1];
+        for (i, x) in a.iter().enumerate() {
+            for (j, y) in b.iter().enumerate() {
+                let product = Calculator::apply_binary('*', x.clone(), y.clone()).ok()?;
+                result[i + j] = Calculator::apply_binary('+', result[i + j].clone(), product).ok()?;
+            }
+        }
+        Some(result)
+    }
+
+    fn trim_polynomial(mut poly: Vec<Number>) -> Vec<Number> {
+        while poly.len() > 1 && poly.last().is_some_and(|c| c.is_zero()) {
+            poly.pop();
+        }
+        poly
+    }
+
+    fn solve_polynomial(mut coefficients: Vec<Number>) -> Result<Vec<Root>, String> {
+        if coefficients.len() == 1 {
+            return Err(if coefficients[0].is_zero() {
+                "Every value is a solution".to_string()
+            } else {
+                "No solution".to_string()
+            });
+        }
+
+        let mut roots = Vec::new();
+
+        // x = 0 roots
+        while coefficients.len() > 1 && coefficients[0].is_zero() {
+            coefficients.remove(0);
+            roots.push(Root::Exact(Number::from_integer(0)));
+        }
+
+        // Exact rational roots first (rational root theorem), deflating as we go
+        if coefficients.len() > 3 {
+            while let Some(root) = Self::find_rational_root(&coefficients) {
+                coefficients = Self::deflate(&coefficients, &root);
+                roots.push(Root::Exact(Number::Exact(root)));
+                if coefficients.len() <= 3 {
+                    break;
+                }
+            }
+        }
+
+        match coefficients.len() {
+            1 => {}
+            2 => {
+                // a*x + b = 0
+                let negated = Calculator::apply_binary('-', Number::from_integer(0), coefficients[0].clone())?;
+                roots.push(Root::Exact(Calculator::apply_binary('/', negated, coefficients[1].clone())?));
+            }
+            3 => roots.extend(Self::solve_quadratic(&coefficients)?),
+            _ => roots.extend(Self::durand_kerner(&coefficients)),
+        }
+
+        Ok(roots)
+    }
+
+    /// Quadratic formula, exact when the discriminant is a perfect square
+    fn solve_quadratic(coefficients: &[Number]) -> Result<Vec<Root>, String> {
+        let (c, b, a) = (coefficients[0].clone(), coefficients[1].clone(), coefficients[2].clone());
+        let op = Calculator::apply_binary;
+
+        let b_squared = op('*', b.clone(), b.clone())?;
+        let four_ac = op('*', Number::from_integer(4), op('*', a.clone(), c)?)?;
+        let discriminant = op('-', b_squared, four_ac)?;
+        let two_a = op('*', Number::from_integer(2), a)?;
+        let minus_b = op('-', Number::from_integer(0), b)?;
+
+        if discriminant.to_f64() < 0.0 {
+            let re = op('/', minus_b, two_a.clone())?.to_f64();
+            let im = ((-discriminant.to_f64()).sqrt() / two_a.to_f64()).abs();
+            return Ok(vec![
+                Root::Complex(Complex64::new(re, im)),
+                Root::Complex(Complex64::new(re, -im)),
+            ]);
+        }
+
+        let root = Calculator::eval(
+            &Expr::Call("sqrt".to_string(), vec![Expr::Number(discriminant.clone())]),
+            &HashMap::new(),
+        )?;
+        if discriminant.is_zero() {
+            return Ok(vec![Root::Exact(op('/', minus_b, two_a)?)]);
+        }
+        let first = op('/', op('-', minus_b.clone(), root.clone())?, two_a.clone())?;
+        let second = op('/', op('+', minus_b, root)?, two_a)?;
+        Ok(vec![Root::Exact(first), Root::Exact(second)])
+    }
+
+    /// Try p/q for p | a0, q | an when all coefficients are exact
+    fn find_rational_root(coefficients: &[Number]) -> Option<BigRational> {
+        let exact: Vec<BigRational> = coefficients
+            .iter()
+            .map(|c| match c {
+                Number::Exact(r) => Some(r.clone()),
+                Number::Approx(_) => None,
+            })
+            .collect::<Option<_>>()?;
+
+        // Scale to integer coefficients
+        let denominator = exact
+            .iter()
+            .fold(BigInt::from(1), |acc, r| num_integer::Integer::lcm(&acc, r.denom()));
+        let integers: Vec<BigInt> = exact
+            .iter()
+            .map(|r| (r * BigRational::from_integer(denominator.clone())).to_integer())
+            .collect();
+
+        let constant = integers.first()?.abs().to_u64()?;
+        let leading = integers.last()?.abs().to_u64()?;
+        if constant == 0 || constant > 1_000_000 || leading > 1_000_000 {
+            return None;
+        }
+
+        for p in Self::divisors(constant) {
+            for q in Self::divisors(leading) {
+                for sign in [1i64, -1] {
+                    let candidate = BigRational::new(BigInt::from(sign * p as i64), BigInt::from(q));
+                    let value = exact
+                        .iter()
+                        .rev()
+                        .fold(BigRational::zero(), |acc, c| acc * &candidate + c);
+                    if value.is_zero() {
+                        return Some(candidate);
+                    }
+                }
+            }
+        }
+        None
+    }
+
+    fn divisors(n: u64) -> Vec<u64> {
+        let mut result = Vec::new();
+        let mut i = 1;
+        while i * i <= n {
+            if n.is_multiple_of(i) {
+                result.push(i);
+                if i != n / i {
+                    result.push(n / i);
+                }
+            }
+            i += 1;
+        }
+        result.sort_unstable();
+        result
+    }
+
+    /// Divide the polynomial by (x - root)
+    fn deflate(coefficients: &[Number], root: &BigRational) -> Vec<Number> {
+        let root = Number::Exact(root.clone());
+        let mut result = vec![Number::from_integer(0); coefficients.len() - 1];
+        let mut carry = Number::from_integer(0);
+        for i in (1..coefficients.len()).rev() {
+            let scaled = Calculator::apply_binary('*', carry, root.clone()).unwrap_or(Number::Approx(f64::NAN));
+            carry = Calculator::apply_binary('+', coefficients[i].clone(), scaled).unwrap_or(Number::Approx(f64::NAN));
+            result[i - 1] = carry.clone();
+        }
+        result
+    }
+
+    /// All complex roots of a polynomial at once (Weierstrass / Durand-Kerner iteration)
+    fn durand_kerner(coefficients: &[Number]) -> Vec<Root> {
+        let leading = coefficients.last().map(Number::to_f64).unwrap_or(1.0);
+        let monic: Vec<Complex64> = coefficients
+            .iter()
+            .map(|c| Complex64::new(c.to_f64() / leading, 0.0))
+            .collect();
+        let degree = monic.len() - 1;
+
+        let eval = |x: Complex64| monic.iter().rev().fold(Complex64::new(0.0, 0.0), |acc, c| acc * x + c);
+
+        // Standard starting points: powers of 0.4 + 0.9i
+        let seed = Complex64::new(0.4, 0.9);
+        let mut roots: Vec<Complex64> = (1..=degree as i32).map(|k| seed.powi(k)).collect();
+
+        for _ in 0..1000 {
+            let mut change: f64 = 0.0;
+            for i in 0..degree {
+                let mut denominator = Complex64::new(1.0, 0.0);
+                for j in 0..degree {
+                    if i != j {
+                        denominator *= roots[i] - roots[j];
+                    }
+                }
+                let delta = eval(roots[i]) / denominator;
+                roots[i] -= delta;
+                change = change.max(delta.norm());
+            }
+            if change < 1e-14 {
+                break;
+            }
+        }
+
+        let mut result: Vec<Root> = roots
+            .into_iter()
+            .map(|z| {
+                // Snap numerical noise: 2.0000000000001 - 1e-17i is the real root 2
+                let scale = z.norm().max(1.0);
+                let re = if (z.re - z.re.round()).abs() < 1e-10 * scale { z.re.round() } else { z.re };
+                if z.im.abs() < 1e-10 * scale {
+                    Root::Exact(Number::Approx(re))
+                } else {
+                    Root::Complex(Complex64::new(re, z.im))
+                }
+            })
+            .collect();
+        result.sort_by(|a, b| Self::root_key(a).total_cmp(&Self::root_key(b)));
+        result
+    }
+
+    fn root_key(root: &Root) -> f64 {
+        match root {
+            Root::Exact(n) => n.to_f64(),
+            Root::Complex(z) => 1e300 + z.re,
+        }
+    }
+
+    /// Find real roots of an arbitrary function by scanning for sign changes
+    fn scan_roots(expr: &Expr, var: &str) -> Vec<f64> {
+        let mut points = Vec::new();
+        for (limit, steps) in SCAN_LIMITS {
+            for i in 0..=steps {
+                points.push(-limit + 2.0 * limit * i as f64 / steps as f64);
+            }
+        }
+        points.sort_by(f64::total_cmp);
+        points.dedup();
+
+        let f = |x: f64| Self::eval_at(expr, var, x);
+        let mut roots: Vec<f64> = Vec::new();
+        let push = |x: f64, roots: &mut Vec<f64>| {
+            // Ignore poles (tan(x) = 0 changes sign at pi/2 too)
+            if f(x).abs() > 1e-6 {
+                return;
+            }
+            // + 0.0 turns -0 into 0
+            let x = if (x - x.round()).abs() < 1e-9 { x.round() + 0.0 } else { x };
+            if !roots.iter().any(|r| (r - x).abs() < 1e-7 * x.abs().max(1.0)) {
+                roots.push(x);
+            }
+        };
+
+        for pair in points.windows(2) {
+            let (mut a, mut b) = (pair[0], pair[1]);
+            let (mut fa, fb) = (f(a), f(b));
+            if !fa.is_finite() || !fb.is_finite() {
+                continue;
+            }
+            if fa == 0.0 {
+                push(a, &mut roots);
+                continue;
+            }
+            if fa.signum() == fb.signum() {
+                continue;
+            }
+            for _ in 0..200 {
+                let mid = 0.5 * (a + b);
+                let fm = f(mid);
+                if fm == 0.0 || (b - a).abs() < 1e-15 * mid.abs().max(1.0) {
+                    a = mid;
+                    b = mid;
+                    break;
+                }
+                if fm.signum() == fa.signum() {
+                    a = mid;
+                    fa = fm;
+                } else {
+                    b = mid;
+                }
+            }
+            push(0.5 * (a + b), &mut roots);
+        }
+
+        // Closest to zero first
+        roots.sort_by(|a, b| a.abs().total_cmp(&b.abs()));
+        roots
+    }
+
+    fn format_roots(var: &str, roots: &[Root], config: &CalculatorConfig) -> Calculation {
+        // Repeated roots are listed once
+        let mut unique: Vec<&Root> = Vec::new();
+        for root in roots {
+            if !unique.contains(&root) {
+                unique.push(root);
+            }
+        }
+
+        let parts: Vec<String> = unique
+            .iter()
+            .take(MAX_LISTED_ROOTS)
+            .map(|root| {
+                let approx = matches!(root, Root::Complex(_) | Root::Exact(Number::Approx(_)));
+                let sign = if approx { "≈" } else { "=" };
+                format!("{} {} {}", var, sign, Self::format_root(root, config))
+            })
+            .collect();
+
+        let mut value = parts.join(",  ");
+        if unique.len() > MAX_LISTED_ROOTS {
+            value.push_str(",  …");
+        }
+
+        let alternatives = parts
+            .iter()
+            .enumerate()
+            .map(|(i, part)| Representation { label: format!("root {}", i + 1), value: part.clone() })
+            .take(4)
+            .collect();
+
+        Calculation { value, alternatives }
+    }
+
+    fn format_root(root: &Root, config: &CalculatorConfig) -> String {
+        match root {
+            Root::Exact(n) => Calculator::format_number(n, config).value,
+            Root::Complex(z) => Calculator::format_value(&Value::Complex(*z), config).value,
+        }
+    }
+
+    // ---- Systems ----
+
+    fn solve_system(equations: &[Expr], variables: &[String]) -> Result<Vec<Number>, String> {
+        if let Some(rows) = equations
+            .iter()
+            .map(|e| Self::to_linear(e, variables))
+            .collect::<Option<Vec<_>>>()
+        {
+            return Self::gaussian_elimination(rows);
+        }
+        Self::newton_system(equations, variables)
+    }
+
+    /// Coefficients of each variable plus the constant term, if `expr` is linear
+    fn to_linear(expr: &Expr, variables: &[String]) -> Option<Vec<Number>> {
+        let op = Calculator::apply_binary;
+        let zeros = || vec![Number::from_integer(0); variables.len() + 1];
+        let is_constant = |row: &[Number]| row[..variables.len()].iter().all(Number::is_zero);
+
+        let row = match expr {
+            Expr::Var(name) => match variables.iter().position(|v| v == name) {
+                Some(i) => {
+                    let mut row = zeros();
+                    row[i] = Number::from_integer(1);
+                    row
+                }
+                None => {
+                    let mut row = zeros();
+                    row[variables.len()] = Calculator::eval(expr, &HashMap::new()).ok()?;
+                    row
+                }
+            },
+            Expr::Neg(inner) => Self::to_linear(inner, variables)?
+                .into_iter()
+                .map(|c| op('-', Number::from_integer(0), c))
+                .collect::<Result<_, _>>()
+                .ok()?,
+            Expr::Binary(o @ ('+' | '-'), lhs, rhs) => {
+                let (a, b) = (Self::to_linear(lhs, variables)?, Self::to_linear(rhs, variables)?);
+                a.into_iter()
+                    .zip(b)
+                    .map(|(x, y)| op(*o, x, y))
+                    .collect::<Result<_, _>>()
+                    .ok()?
+            }
+            Expr::Binary(o @ ('*' | '/'), lhs, rhs) => {
+                let (a, b) = (Self::to_linear(lhs, variables)?, Self::to_linear(rhs, variables)?);
+                // One side has to be a constant
+                let (row, factor) = if is_constant(&b) {
+                    (a, b[variables.len()].clone())
+                } else if *o == '*' && is_constant(&a) {
+                    (b, a[variables.len()].clone())
+                } else {
+                    return None;
+                };
+                row.into_iter()
+                    .map(|c| op(*o, c, factor.clone()))
+                    .collect::<Result<_, _>>()
+                    .ok()?
+            }
+            _ => {
+                let mut row = zeros();
+                row[variables.len()] = Calculator::eval(expr, &HashMap::new()).ok()?;
+                row
+            }
+        };
+        Some(row)
+    }
+
+    /// Solve rows of [a1 .. an | c] meaning a1*x1 + .. + an*xn + c = 0
+    fn gaussian_elimination(mut rows: Vec<Vec<Number>>) -> Result<Vec<Number>, String> {
+        let op = Calculator::apply_binary;
+        let n = rows.len();
+
+        for col in 0..n {
+            // Exact pivots: any non-zero entry, approximate ones: the largest
+            let pivot = (col..n)
+                .filter(|&r| !rows[r][col].is_zero())
+                .max_by(|&a, &b| rows[a][col].to_f64().abs().total_cmp(&rows[b][col].to_f64().abs()))
+                .ok_or_else(|| "The system has no unique solution".to_string())?;
+            rows.swap(col, pivot);
+
+            for r in 0..n {
+                if r == col || rows[r][col].is_zero() {
+                    continue;
+                }
+                let factor = op('/', rows[r][col].clone(), rows[col][col].clone())?;
+                let pivot_row = rows[col].clone();
+                for (cell, pivot_cell) in rows[r].iter_mut().zip(pivot_row).skip(col) {
+                    let scaled = op('*', factor.clone(), pivot_cell)?;
+                    *cell = op('-', cell.clone(), scaled)?;
+                }
+            }
+        }
+
+        (0..n)
+            .map(|i| {
+                let negated = op('-', Number::from_integer(0), rows[i][n].clone())?;
+                op('/', negated, rows[i][i].clone())
+            })
+            .collect()
+    }
+
+    /// Newton's method with a numeric Jacobian, from a few starting points
+    fn newton_system(equations: &[Expr], variables: &[String]) -> Result<Vec<Number>, String> {
+        let n = variables.len();
+        let evaluate = |point: &[f64]| -> Vec<f64> {
+            let vars: HashMap<String, Number> = variables
+                .iter()
+                .cloned()
+                .zip(point.iter().map(|x| Number::Approx(*x)))
+                .collect();
+            equations
+                .iter()
+                .map(|e| Calculator::eval(e, &vars).map(|v| v.to_f64()).unwrap_or(f64::NAN))
+                .collect()
+        };
+
+        for start in [1.0, -1.0, 0.5, 2.0, -2.0, 10.0] {
+            let mut x: Vec<f64> = (0..n).map(|i| start + 0.1 * i as f64).collect();
+            for _ in 0..100 {
+                let fx = evaluate(&x);
+                if fx.iter().any(|v| !v.is_finite()) {
+                    break;
+                }
+                if fx.iter().all(|v| v.abs() < 1e-12) {
+                    return Ok(x.into_iter().map(Number::Approx).collect());
+                }
+
+                // Jacobian by forward differences, as exact-free rows for elimination
+                let mut rows = Vec::with_capacity(n);
+                for (i, f_i) in fx.iter().enumerate() {
+                    let mut row = Vec::with_capacity(n + 1);
+                    for j in 0..n {
+                        let h = 1e-7 * x[j].abs().max(1.0);
+                        let mut shifted = x.clone();
+                        shifted[j] += h;
+                        row.push(Number::Approx((evaluate(&shifted)[i] - f_i) / h));
+                    }
+                    row.push(Number::Approx(*f_i));
+                    rows.push(row);
+                }
+
+                let Ok(step) = Self::gaussian_elimination(rows) else { break };
+                for (xi, si) in x.iter_mut().zip(step) {
+                    *xi += si.to_f64();
+                }
+            }
+        }
+        Err("No solution found".to_string())
+    }
+
+    // ---- Calculus ----
+
+    fn integrate(f: &Expr, var: &str, a: &Expr, b: &Expr, vars: &HashMap<String, Number>) -> Result<Number, String> {
+        let a = Calculator::eval(a, vars)?.to_f64();
+        let b = Calculator::eval(b, vars)?.to_f64();
+        if !a.is_finite() || !b.is_finite() {
+            return Err("Integration bounds must be finite".to_string());
+        }
+
+        let g = |x: f64| {
+            let mut inner = vars.clone();
+            inner.insert(var.to_string(), Number::Approx(x));
+            Calculator::eval(f, &inner).map(|n| n.to_f64()).unwrap_or(f64::NAN)
+        };
+
+        // Adaptive Simpson's rule over (x, f(x)) triples of start, middle and end
+        fn simpson(g: &dyn Fn(f64) -> f64, points: [(f64, f64); 3], whole: f64, eps: f64, depth: u32) -> f64 {
+            let [(a, fa), (m, fm), (b, fb)] = points;
+            let (lm, rm) = (0.5 * (a + m), 0.5 * (m + b));
+            let (flm, frm) = (g(lm), g(rm));
+            let left = (m - a) / 6.0 * (fa + 4.0 * flm + fm);
+            let right = (b - m) / 6.0 * (fm + 4.0 * frm + fb);
+            if depth == 0 || (left + right - whole).abs() <= 15.0 * eps {
+                return left + right + (left + right - whole) / 15.0;
+            }
+            simpson(g, [(a, fa), (lm, flm), (m, fm)], left, eps / 2.0, depth - 1)
+                + simpson(g, [(m, fm), (rm, frm), (b, fb)], right, eps / 2.0, depth - 1)
+        }
+
+        let m = 0.5 * (a + b);
+        let points = [(a, g(a)), (m, g(m)), (b, g(b))];
+        let whole = (b - a) / 6.0 * (points[0].1 + 4.0 * points[1].1 + points[2].1);
+        let result = simpson(&g, points, whole, 1e-10, 18);
+        if !result.is_finite() {
+            return Err("The integral does not converge".to_string());
+        }
+        // Tidy up results like 1.9999999999999998
+        let rounded = (result * 1e10).round() / 1e10;
+        Ok(Number::Approx(if (result - rounded).abs() < 1e-11 { rounded } else { result }))
+    }
+
+    fn differentiate(expr: &Expr, var: &str) -> Result<Expr, String> {
+        use Expr::*;
+        let num = |n: i64| Number(self::Number::from_integer(n));
+        let bin = |op: char, a: Expr, b: Expr| Binary(op, Box::new(a), Box::new(b));
+        let call = |name: &str, arg: Expr| Call(name.to_string(), vec![arg]);
+        let depends = |e: &Expr| {
+            let mut variables = BTreeSet::new();
+            Self::collect_variables(e, &mut variables);
+            variables.contains(var)
+        };
+
+        Ok(match expr {
+            Number(_) => num(0),
+            Var(name) => num(if name == var { 1 } else { 0 }),
+            Neg(inner) => Neg(Box::new(Self::differentiate(inner, var)?)),
+            Percent(inner) => bin('/', Self::differentiate(inner, var)?, num(100)),
+            Binary(op @ ('+' | '-'), a, b) => bin(*op, Self::differentiate(a, var)?, Self::differentiate(b, var)?),
+            Binary('*', a, b) => bin(
+                '+',
+                bin('*', Self::differentiate(a, var)?, (**b).clone()),
+                bin('*', (**a).clone(), Self::differentiate(b, var)?),
+            ),
+            Binary('/', a, b) => bin(
+                '/',
+                bin(
+                    '-',
+                    bin('*', Self::differentiate(a, var)?, (**b).clone()),
+                    bin('*', (**a).clone(), Self::differentiate(b, var)?),
+                ),
+                bin('^', (**b).clone(), num(2)),
+            ),
+            Binary('^', base, exponent) if !depends(exponent) => {
+                // Power rule: n * u^(n-1) * u'
+                bin(
+                    '*',
+                    bin('*', (**exponent).clone(), bin('^', (**base).clone(), bin('-', (**exponent).clone(), num(1)))),
+                    Self::differentiate(base, var)?,
+                )
+            }
+            Binary('^', base, exponent) => {
+                // u^v * (v' ln(u) + v u' / u)
+                bin(
+                    '*',
+                    expr.clone(),
+                    bin(
+                        '+',
+                        bin('*', Self::differentiate(exponent, var)?, call("ln", (**base).clone())),
+                        bin(
+                            '/',
+                            bin('*', (**exponent).clone(), Self::differentiate(base, var)?),
+                            (**base).clone(),
+                        ),
+                    ),
+                )
+            }
+            Call(name, args) if args.len() == 1 => {
+                let u = args[0].clone();
+                let du = Self::differentiate(&u, var)?;
+                let outer = match name.as_str() {
+                    "sin" => call("cos", u),
+                    "cos" => Neg(Box::new(call("sin", u))),
+                    "tan" => bin('/', num(1), bin('^', call("cos", u), num(2))),
+                    "exp" => call("exp", u),
+                    "ln" => bin('/', num(1), u),
+                    "log" | "log10" => bin('/', num(1), bin('*', u, call("ln", num(10)))),
+                    "log2" => bin('/', num(1), bin('*', u, call("ln", num(2)))),
+                    "sqrt" => bin('/', num(1), bin('*', num(2), call("sqrt", u))),
+                    "asin" => bin('/', num(1), call("sqrt", bin('-', num(1), bin('^', u, num(2))))),
+                    "acos" => Neg(Box::new(bin('/', num(1), call("sqrt", bin('-', num(1), bin('^', u, num(2))))))),
+                    "atan" => bin('/', num(1), bin('+', num(1), bin('^', u, num(2)))),
+                    "sinh" => call("cosh", u),
+                    "cosh" => call("sinh", u),
+                    "tanh" => bin('/', num(1), bin('^', call("cosh", u), num(2))),
+                    "abs" => call("sign", u),
+                    _ if !depends(expr) => return Ok(num(0)),
+                    _ => return Err(format!("Can't differentiate {}()", name)),
+                };
+                bin('*', outer, du)
+            }
+            _ if !depends(expr) => num(0),
+            _ => return Err("Can't differentiate this expression".to_string()),
+        })
+    }
+
+    /// Fold constants and drop neutral elements so derivatives stay readable
+    fn simplify(expr: Expr) -> Expr {
+        use Expr::*;
+        let is = |e: &Expr, n: i64| matches!(e, Number(v) if *v == self::Number::from_integer(n));
+
+        match expr {
+            Neg(inner) => match Self::simplify(*inner) {
+                Number(n) => Number(Calculator::apply_binary('-', self::Number::from_integer(0), n).unwrap()),
+                Neg(inner) => *inner,
+                inner => Neg(Box::new(inner)),
+            },
+            Binary(op, a, b) => {
+                let (a, b) = (Self::simplify(*a), Self::simplify(*b));
+                if let (Number(x), Number(y)) = (&a, &b) {
+                    if let Ok(value @ self::Number::Exact(_)) = Calculator::apply_binary(op, x.clone(), y.clone()) {
+                        return Number(value);
+                    }
+                }
+                match op {
+                    '+' if is(&a, 0) => b,
+                    '+' | '-' if is(&b, 0) => a,
+                    '-' if is(&a, 0) => Self::simplify(Neg(Box::new(b))),
+                    // a + (-b) -> a - b
+                    '+' => match b {
+                        Neg(inner) => Binary('-', Box::new(a), inner),
+                        b => Binary('+', Box::new(a), Box::new(b)),
+                    },
+                    '*' if is(&a, 0) || is(&b, 0) => Number(self::Number::from_integer(0)),
+                    '*' if is(&a, 1) => b,
+                    '*' if is(&b, 1) => a,
+                    '*' if is(&a, -1) => Neg(Box::new(b)),
+                    // Keep numbers in front: x * 3 -> 3x
+                    '*' if matches!(b, Number(_)) && !matches!(a, Number(_)) => Binary('*', Box::new(b), Box::new(a)),
+                    '*' => match (a, b) {
+                        // 3 * (2 * x) -> 6x
+                        (Number(x), Binary('*', inner_a, inner_b)) if matches!(*inner_a, Number(_)) => {
+                            let Number(y) = *inner_a else { unreachable!() };
+                            Self::simplify(Binary(
+                                '*',
+                                Box::new(Number(Calculator::apply_binary('*', x, y).unwrap())),
+                                inner_b,
+                            ))
+                        }
+                        (Neg(a), b) => Neg(Box::new(Self::simplify(Binary('*', a, Box::new(b))))),
+                        (a, Neg(b)) => Neg(Box::new(Self::simplify(Binary('*', Box::new(a), b)))),
+                        (a, b) => Binary('*', Box::new(a), Box::new(b)),
+                    },
+                    '/' if is(&b, 1) => a,
+                    '/' if is(&a, 0) => Number(self::Number::from_integer(0)),
+                    '^' if is(&b, 1) => a,
+                    '^' if is(&b, 0) => Number(self::Number::from_integer(1)),
+                    _ => Binary(op, Box::new(a), Box::new(b)),
+                }
+            }
+            Call(name, args) => match (name.as_str(), args.as_slice()) {
+                ("ln", [Var(e)]) if e == "e" => Number(self::Number::from_integer(1)),
+                _ => Call(name, args.into_iter().map(Self::simplify).collect()),
+            },
+            Percent(inner) => Percent(Box::new(Self::simplify(*inner))),
+            Factorial(inner) => Factorial(Box::new(Self::simplify(*inner))),
+            other => other,
+        }
+    }
+
+    fn format_with_precedence(expr: &Expr, parent: u8) -> String {
+        let (text, precedence) = match expr {
+            Expr::Number(n) => {
+                let text = match n {
+                    Number::Exact(r) if r.is_integer() => r.to_string(),
+                    Number::Exact(r) => return Self::wrap(format!("{}/{}", r.numer(), r.denom()), 2, parent),
+                    Number::Approx(f) => Calculator::format_result(*f),
+                };
+                let negative = text.starts_with('-');
+                (text, if negative { 3 } else { 5 })
+            }
+            Expr::Var(name) => (name.clone(), 5),
+            Expr::Neg(inner) => (format!("-{}", Self::format_with_precedence(inner, 4)), 3),
+            Expr::Percent(inner) => (format!("{}%", Self::format_with_precedence(inner, 5)), 5),
+            Expr::Factorial(inner) => (format!("{}!", Self::format_with_precedence(inner, 5)), 5),
+            Expr::Call(name, args) => {
+                let args: Vec<String> = args.iter().map(Self::format_expr).collect();
+                (format!("{}({})", name, args.join(", ")), 5)
+            }
+            Expr::Array(items) => {
+                let items: Vec<String> = items.iter().map(Self::format_expr).collect();
+                (format!("[{}]", items.join(", ")), 5)
+            }
+            Expr::Binary(op, lhs, rhs) => {
+                let precedence = match op {
+                    '+' | '-' => 1,
+                    '*' | '/' | 'm' | 'f' => 2,
+                    _ => 4,
+                };
+                let left = Self::format_with_precedence(lhs, precedence);
+                // Right operand of - and / binds tighter; ^ is right associative
+                let right_precedence = match op {
+                    '-' | '/' => precedence + 1,
+                    '^' => precedence,
+                    _ => precedence,
+                };
+                let left = if *op == '^' { Self::format_with_precedence(lhs, 5) } else { left };
+                let right = Self::format_with_precedence(rhs, right_precedence);
+                let text = match op {
+                    // Implicit multiplication: 3x^2, 2cos(x)
+                    '*' if matches!(**lhs, Expr::Number(_))
+                        && right.starts_with(|c: char| c.is_alphabetic()) => format!("{}{}", left, right),
+                    '^' => format!("{}^{}", left, right),
+                    'm' => format!("{} mod {}", left, right),
+                    'f' => format!("{} off {}", left, right),
+                    _ => format!("{} {} {}", left, op, right),
+                };
+                (text, precedence)
+            }
+        };
+        Self::wrap(text, precedence, parent)
+    }
+
+    fn wrap(text: String, precedence: u8, parent: u8) -> String {
+        if precedence < parent {
+            format!("({})", text)
+        } else {
+            text
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config() -> CalculatorConfig {
+        CalculatorConfig {
+            locale: "en_US".to_string(),
+            ..CalculatorConfig::default()
+        }
+    }
+
+    fn solve(query: &str) -> Result<String, String> {
+        Solver::solve(query, &config()).map(|calculation| calculation.value)
+    }
+
+    fn integers(values: &[i64]) -> Vec<Number> {
+        values.iter().map(|&n| Number::from_integer(n)).collect()
+    }
+
+    #[test]
+    fn test_split_equations() {
+        assert_eq!(Solver::split_equations("x + y = 3, x - y = 1"), ["x + y = 3", "x - y = 1"]);
+        assert_eq!(Solver::split_equations("x = max(1, 2); y = 3"), ["x = max(1, 2)", "y = 3"]);
+        // Decimal commas stay with their number
+        assert_eq!(Solver::split_equations("x = 3,5"), ["x = 3,5"]);
+    }
+
+    #[test]
+    fn test_no_solution() {
+        assert_eq!(solve("solve x + 1 = x"), Err("No solution".to_string()));
+        assert_eq!(solve("solve sin(x) = 2"), Err("No real solution found".to_string()));
+        assert_eq!(solve("solve 5 = 5"), Err("Nothing to solve for".to_string()));
+        assert_eq!(solve("solve x + y = 3"), Err("2 unknowns need 2 equations".to_string()));
+        assert!(solve("solve x + 1").is_err());
+
+        // Inconsistent and dependent systems
+        assert_eq!(solve("solve x + y = 1, x + y = 2"), Err("The system has no unique solution".to_string()));
+        assert_eq!(solve("solve x + y = 1, 2x + 2y = 2"), Err("The system has no unique solution".to_string()));
+    }
+
+    #[test]
+    fn test_systems() {
+        assert_eq!(solve("solve x + y = 3 and x - y = 1"), Ok("x = 2,  y = 1".to_string()));
+        assert_eq!(solve("solve x^2 + y^2 = 25, x - y = 1"), Ok("x = 4,  y = 3".to_string()));
+
+        // 2x + y - 5 = 0, x - y - 1 = 0, exactly
+        let rows = vec![integers(&[2, 1, -5]), integers(&[1, -1, -1])];
+        assert_eq!(Solver::gaussian_elimination(rows), Ok(integers(&[2, 1])));
+        let singular = vec![integers(&[1, 2, 3]), integers(&[2, 4, 6])];
+        assert!(Solver::gaussian_elimination(singular).is_err());
+
+        let variables = ["x".to_string(), "y".to_string()];
+        let linear = Calculator::parse("2x - y / 2 + 3", &config()).unwrap();
+        let minus_half = Number::Exact(BigRational::new(BigInt::from(-1), BigInt::from(2)));
+        let row = vec![Number::from_integer(2), minus_half, Number::from_integer(3)];
+        assert_eq!(Solver::to_linear(&linear, &variables), Some(row));
+        let product = Calculator::parse("x * y", &config()).unwrap();
+        assert_eq!(Solver::to_linear(&product, &variables), None);
+    }
+
+    #[test]
+    fn test_polynomials() {
+        assert_eq!(solve("solve x^4 = 16"), Ok("x = 2,  x = -2,  x ≈ 2i,  x ≈ -2i".to_string()));
+        assert_eq!(Solver::divisors(12), [1, 2, 3, 4, 6, 12]);
+        let square = Calculator::parse("(x - 1)^2", &config()).unwrap();
+        assert_eq!(Solver::to_polynomial(&square, "x"), Some(integers(&[1, -2, 1])));
+    }
+
+    #[test]
+    fn test_calculus() {
+        let derivative = |expression: &str| {
+            let expr = Calculator::parse(expression, &config()).unwrap();
+            Solver::format_expr(&Solver::derivative(&expr, "x").unwrap())
+        };
+        assert_eq!(derivative("x^2"), "2x");
+        assert_eq!(derivative("3x + 1"), "3");
+    }
+}
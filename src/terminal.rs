@@ -1,147 +1,264 @@
+use crate::app_launcher::parse_desktop_file;
+use crate::command_index::CommandIndex;
+use crate::config::TerminalConfig;
+use crate::utils;
+use std::path::{Path, PathBuf};
 use std::process::Command;
 
 pub struct Terminal;
 
+/// A terminal emulator and how it takes the command to run, a title and a directory
+#[derive(Debug, Clone, PartialEq)]
+struct TerminalLauncher {
+    argv: Vec<String>,     // The terminal and arguments that always come first
+    exec: Vec<String>,     // Before the command: "-e", "--" or nothing
+    joined: bool,          // The command after `exec` is one quoted string, not its arguments
+    title: Option<String>, // "--title" takes the next argument, "--title=" the rest of the word
+    dir: Option<String>,
+}
+
+/// Command line conventions of terminals without them in their desktop entry
+struct KnownTerminal {
+    name: &'static str,
+    args: &'static [&'static str],
+    exec: &'static [&'static str],
+    joined: bool,
+    title: Option<&'static str>,
+    dir: Option<&'static str>,
+}
+
+// Also tried in this order when nothing else names a terminal
+const KNOWN_TERMINALS: &[KnownTerminal] = &[
+    KnownTerminal { name: "gnome-terminal", args: &[], exec: &["--"], joined: false, title: Some("--title="), dir: Some("--working-directory=") },
+    KnownTerminal { name: "kgx", args: &[], exec: &["--"], joined: false, title: Some("--title="), dir: Some("--working-directory=") },
+    KnownTerminal { name: "konsole", args: &[], exec: &["-e"], joined: false, title: None, dir: Some("--workdir") },
+    KnownTerminal { name: "kitty", args: &[], exec: &[], joined: false, title: Some("--title"), dir: Some("--directory") },
+    KnownTerminal { name: "alacritty", args: &[], exec: &["-e"], joined: false, title: Some("--title"), dir: Some("--working-directory") },
+    KnownTerminal { name: "foot", args: &[], exec: &[], joined: false, title: Some("--title="), dir: Some("--working-directory=") },
+    KnownTerminal { name: "wezterm", args: &["start"], exec: &["--"], joined: false, title: None, dir: Some("--cwd") },
+    KnownTerminal { name: "tilix", args: &[], exec: &["-e"], joined: true, title: Some("--title="), dir: Some("--working-directory=") },
+    KnownTerminal { name: "xfce4-terminal", args: &[], exec: &["-x"], joined: false, title: Some("--title="), dir: Some("--working-directory=") },
+    KnownTerminal { name: "mate-terminal", args: &[], exec: &["-x"], joined: false, title: Some("--title="), dir: Some("--working-directory=") },
+    KnownTerminal { name: "lxterminal", args: &[], exec: &["-e"], joined: true, title: Some("--title="), dir: Some("--working-directory=") },
+    KnownTerminal { name: "terminology", args: &[], exec: &["-e"], joined: true, title: Some("--title="), dir: Some("--current-directory=") },
+    KnownTerminal { name: "xterm", args: &[], exec: &["-e"], joined: false, title: Some("-T"), dir: None },
+    KnownTerminal { name: "urxvt", args: &[], exec: &["-e"], joined: false, title: Some("-title"), dir: Some("-cd") },
+];
+
+// Runs the command with the user's shell, then waits for Enter. The command is an argument,
+// never part of the script.
+const HOLD_SCRIPT: &str = r#""$1" -c "$2"; printf '\nPress Enter to close...'; read -r _"#;
+
 impl Terminal {
-    pub fn execute_command(command: &str) -> Result<(), Box<dyn std::error::Error>> {
-        // Get the shell
-        let shell = std::env::var("SHELL").unwrap_or_else(|_| "/bin/bash".to_string());
-        
-        // Create a command that waits for user input before closing
-        let full_command = format!("{}; echo ''; echo 'Press Enter to close...'; read", command);
-        
-        // Get the user's default terminal
-        let terminal = Self::get_default_terminal()?;
-        
-        // Execute based on terminal type (different terminals have different args)
-        Self::run_in_terminal(&terminal, &shell, &full_command)?;
-        
+    /// Run a command line with the user's shell in a new terminal window
+    pub fn execute_command(command: &str, config: &TerminalConfig) -> Result<(), Box<dyn std::error::Error>> {
+        let shell = std::env::var("SHELL").unwrap_or_else(|_| "/bin/sh".to_string());
+        let command_argv: Vec<String> = if config.hold {
+            ["/bin/sh", "-c", HOLD_SCRIPT, "sh", &shell, command].map(str::to_string).to_vec()
+        } else {
+            vec![shell, "-c".to_string(), command.to_string()]
+        };
+        let title = config.title.replace("{command}", command);
+        let dir = Self::working_directory(config);
+
+        let argv = if config.command.trim().is_empty() {
+            Self::find_terminal()?.argv(&command_argv, &title, dir.as_deref())
+        } else {
+            Self::from_template(&config.command, &command_argv, &title, dir.as_deref())
+        };
+        let (program, args) = argv.split_first().ok_or("The terminal command is empty")?;
+
+        let mut process = Command::new(program);
+        process.args(args);
+        // Terminals that don't take a directory start their command in their own
+        if let Some(dir) = &dir {
+            process.current_dir(dir);
+        }
+        process.spawn()?;
         Ok(())
     }
-    
-    fn get_default_terminal() -> Result<String, Box<dyn std::error::Error>> {
-        // 1. Check $TERMINAL environment variable (user preference)
-        if let Ok(term) = std::env::var("TERMINAL") {
-            if !term.is_empty() && Self::has_command(&term) {
-                return Ok(term);
+
+    /// The configured directory with "~" expanded, None when unset or missing
+    pub fn working_directory(config: &TerminalConfig) -> Option<PathBuf> {
+        let dir = config.working_directory.trim();
+        let dir = match dir.strip_prefix('~') {
+            _ if dir.is_empty() => return None,
+            Some(rest) => dirs::home_dir()?.join(rest.trim_start_matches('/')).components().collect(),
+            None => PathBuf::from(dir),
+        };
+        dir.is_dir().then_some(dir)
+    }
+
+    /// The configured command line: {argv} is replaced by the arguments, {command} by them
+    /// as one quoted string, {title} and {cwd} inside any word. Without either command
+    /// placeholder the arguments go at the end.
+    fn from_template(template: &str, command: &[String], title: &str, dir: Option<&Path>) -> Vec<String> {
+        let cwd = dir.map(|dir| dir.to_string_lossy().into_owned()).unwrap_or_default();
+        let mut argv = Vec::new();
+        let mut placed = false;
+        for word in utils::split_words(template) {
+            match word.as_str() {
+                "{argv}" => argv.extend(command.iter().cloned()),
+                "{command}" => argv.push(Self::quote(command)),
+                _ => {
+                    argv.push(word.replace("{title}", title).replace("{cwd}", &cwd));
+                    continue;
+                }
             }
+            placed = true;
         }
-        
-        // 2. Try x-terminal-emulator (Debian/Ubuntu system default)
-        if Self::has_command("x-terminal-emulator") {
-            // Get the actual terminal it points to
-            if let Ok(output) = Command::new("readlink")
-                .arg("-f")
-                .arg("/usr/bin/x-terminal-emulator")
-                .output()
-            {
-                if output.status.success() {
-                    let path = String::from_utf8_lossy(&output.stdout).trim().to_string();
-                    if !path.is_empty() {
-                        return Ok(path);
-                    }
-                }
+        if !placed {
+            argv.extend(command.iter().cloned());
+        }
+        argv
+    }
+
+    /// The terminal to use: the xdg-terminal-exec preferences, the xdg-terminal-exec program,
+    /// $TERMINAL, the system's default terminal, then the first known one installed
+    fn find_terminal() -> Result<TerminalLauncher, Box<dyn std::error::Error>> {
+        if let Some(terminal) = Self::preferred_terminal() {
+            return Ok(terminal);
+        }
+
+        if CommandIndex::contains("xdg-terminal-exec") {
+            return Ok(TerminalLauncher {
+                argv: vec!["xdg-terminal-exec".to_string()],
+                exec: vec!["--".to_string()],
+                joined: false,
+                title: None,
+                dir: None,
+            });
+        }
+
+        if let Ok(terminal) = std::env::var("TERMINAL") {
+            // May carry its own arguments, "kitty -1"
+            let argv = utils::split_words(&terminal);
+            if argv.first().is_some_and(|program| CommandIndex::contains(program)) {
+                return Ok(TerminalLauncher::known(argv));
             }
-            return Ok("x-terminal-emulator".to_string());
         }
-        
-        // 3. Try to get GNOME's preferred terminal via gsettings
+
+        // Debian's alternatives link, named after the terminal it points to
+        if CommandIndex::contains("x-terminal-emulator") {
+            let target = std::fs::canonicalize("/usr/bin/x-terminal-emulator")
+                .map(|path| path.to_string_lossy().into_owned())
+                .unwrap_or_else(|_| "x-terminal-emulator".to_string());
+            return Ok(TerminalLauncher::known(vec![target]));
+        }
+
+        // GNOME's preferred terminal
         if let Ok(output) = Command::new("gsettings")
             .args(["get", "org.gnome.desktop.default-applications.terminal", "exec"])
             .output()
         {
-            if output.status.success() {
-                let term = String::from_utf8_lossy(&output.stdout)
-                    .trim()
-                    .trim_matches('\'')
-                    .to_string();
-                if !term.is_empty() && Self::has_command(&term) {
-                    return Ok(term);
-                }
-            }
-        }
-        
-        // 4. Fallback: check common terminals
-        let terminals = ["gnome-terminal", "konsole", "kitty", "alacritty", "tilix", "xterm"];
-        for term in terminals {
-            if Self::has_command(term) {
-                return Ok(term.to_string());
+            let terminal = String::from_utf8_lossy(&output.stdout).trim().trim_matches('\'').to_string();
+            if output.status.success() && !terminal.is_empty() && CommandIndex::contains(&terminal) {
+                return Ok(TerminalLauncher::known(vec![terminal]));
             }
         }
-        
-        Err("No terminal emulator found".into())
+
+        KNOWN_TERMINALS
+            .iter()
+            .find(|known| CommandIndex::contains(known.name))
+            .map(|known| TerminalLauncher::known(vec![known.name.to_string()]))
+            .ok_or_else(|| "No terminal emulator found".into())
     }
-    
-    fn run_in_terminal(terminal: &str, shell: &str, command: &str) -> Result<(), Box<dyn std::error::Error>> {
-        // Get the terminal name (without path)
-        let term_name = terminal.rsplit('/').next().unwrap_or(terminal);
-        
-        match term_name {
-            "gnome-terminal" | "gnome-terminal-server" => {
-                Command::new(terminal)
-                    .arg("--")
-                    .arg(shell)
-                    .arg("-c")
-                    .arg(command)
-                    .spawn()?;
-            }
-            "kitty" => {
-                Command::new(terminal)
-                    .arg(shell)
-                    .arg("-c")
-                    .arg(command)
-                    .spawn()?;
-            }
-            "alacritty" | "konsole" | "xterm" | "urxvt" | "rxvt" | "terminator" | "mate-terminal" | "xfce4-terminal" | "lxterminal" => {
-                Command::new(terminal)
-                    .arg("-e")
-                    .arg(shell)
-                    .arg("-c")
-                    .arg(command)
-                    .spawn()?;
-            }
-            "tilix" | "terminology" => {
-                Command::new(terminal)
-                    .arg("-e")
-                    .arg(format!("{} -c '{}'", shell, command))
-                    .spawn()?;
-            }
-            "kgx" | "console" => {
-                // GNOME Console
-                Command::new(terminal)
-                    .arg("-e")
-                    .arg(shell)
-                    .arg("-c")
-                    .arg(command)
-                    .spawn()?;
-            }
-            "wezterm" => {
-                Command::new(terminal)
-                    .arg("start")
-                    .arg("--")
-                    .arg(shell)
-                    .arg("-c")
-                    .arg(command)
-                    .spawn()?;
-            }
-            // Default: try -e flag (most common)
-            _ => {
-                Command::new(terminal)
-                    .arg("-e")
-                    .arg(format!("{} -c '{}'", shell, command))
-                    .spawn()?;
+
+    /// The first installed terminal in the xdg-terminal-exec preference lists: per desktop
+    /// ("gnome-xdg-terminals.list") before the general one, the user's config before the
+    /// system's, then the lists in the data directories. Entries name desktop files; an
+    /// entry starting with "-" excludes that terminal.
+    fn preferred_terminal() -> Option<TerminalLauncher> {
+        let home = dirs::home_dir().unwrap_or_default();
+        let env_dirs = |name: &str, default: &str| -> Vec<PathBuf> {
+            std::env::var(name)
+                .ok()
+                .filter(|dirs| !dirs.is_empty())
+                .unwrap_or_else(|| default.to_string())
+                .split(':')
+                .map(PathBuf::from)
+                .collect()
+        };
+        let config_home = dirs::config_dir().unwrap_or_else(|| home.join(".config"));
+        let data_home = dirs::data_dir().unwrap_or_else(|| home.join(".local/share"));
+        let data_dirs = env_dirs("XDG_DATA_DIRS", "/usr/local/share:/usr/share");
+
+        let mut list_dirs = vec![config_home];
+        list_dirs.extend(env_dirs("XDG_CONFIG_DIRS", "/etc/xdg"));
+        list_dirs.push(data_home.join("xdg-terminal-exec"));
+        list_dirs.extend(data_dirs.iter().map(|dir| dir.join("xdg-terminal-exec")));
+
+        let mut list_names: Vec<String> = std::env::var("XDG_CURRENT_DESKTOP")
+            .unwrap_or_default()
+            .split(':')
+            .filter(|desktop| !desktop.is_empty())
+            .map(|desktop| format!("{}-xdg-terminals.list", desktop.to_lowercase()))
+            .collect();
+        list_names.push("xdg-terminals.list".to_string());
+
+        let mut application_dirs = vec![data_home.join("applications")];
+        application_dirs.extend(data_dirs.iter().map(|dir| dir.join("applications")));
+
+        let mut excluded: Vec<String> = Vec::new();
+        for dir in &list_dirs {
+            for name in &list_names {
+                let Ok(contents) = std::fs::read_to_string(dir.join(name)) else {
+                    continue;
+                };
+                for line in contents.lines().map(str::trim) {
+                    if line.is_empty() || line.starts_with('#') {
+                        continue;
+                    }
+                    if let Some(id) = line.strip_prefix('-') {
+                        excluded.push(id.to_string());
+                        continue;
+                    }
+                    // "+id" is the same as "id"; actions ("id:new-window") aren't used
+                    let id = line.trim_start_matches('+').split(':').next().unwrap_or(line);
+                    if excluded.iter().any(|excluded| excluded == id) {
+                        continue;
+                    }
+                    let terminal = application_dirs
+                        .iter()
+                        .find_map(|dir| TerminalLauncher::from_desktop_file(&dir.join(id)));
+                    if terminal.is_some() {
+                        return terminal;
+                    }
+                }
             }
         }
-        
-        Ok(())
+        None
     }
 
-    fn has_command(cmd: &str) -> bool {
-        Command::new("which")
-            .arg(cmd)
-            .output()
-            .map(|o| o.status.success())
-            .unwrap_or(false)
+    /// Arguments as one string for terminals that run it with a shell, each quoted so the
+    /// shell sees it unchanged
+    fn quote(argv: &[String]) -> String {
+        argv.iter()
+            .map(|arg| {
+                if !arg.is_empty() && arg.chars().all(|c| c.is_ascii_alphanumeric() || "-_./=:,+@%".contains(c)) {
+                    arg.clone()
+                } else {
+                    format!("'{}'", arg.replace('\'', r"'\''"))
+                }
+            })
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
+    /// The command without an explicit prefix ("> ls" runs "ls")
+    pub fn strip_prefix(query: &str) -> &str {
+        Self::split_prefix(query).1.trim_end()
+    }
+
+    /// The explicit prefix with its spaces, and the command after it with trailing spaces
+    /// kept, which matter for completion
+    pub fn split_prefix(query: &str) -> (&str, &str) {
+        let query = query.trim_start();
+        ["> ", "$ ", "! ", "term ", "cmd "]
+            .iter()
+            .find_map(|prefix| query.strip_prefix(prefix))
+            .map(|command| query.split_at(query.len() - command.trim_start().len()))
+            .unwrap_or(("", query))
     }
 
     pub fn is_terminal_command(query: &str) -> bool {
@@ -169,26 +286,132 @@ impl Terminal {
         
         // Check if it's a valid command in PATH
         // This supports ALL terminal commands, not just hardcoded ones
-        Self::is_valid_command(first_word)
-    }
-    
-    fn is_valid_command(cmd: &str) -> bool {
-        // Check if command exists using 'command -v' (POSIX compliant, faster than 'which')
-        // This works for all commands in PATH, aliases, functions, and builtins
-        let output = Command::new("sh")
-            .arg("-c")
-            .arg(&format!("command -v {}", cmd))
-            .output();
-        
-        if let Ok(output) = output {
-            if output.status.success() {
-                let path = String::from_utf8_lossy(&output.stdout).trim().to_string();
-                // Make sure it's not empty and is actually a command (not just whitespace)
-                return !path.is_empty() && !path.contains('\n');
-            }
+        CommandIndex::contains(first_word)
+    }
+}
+
+impl TerminalLauncher {
+    /// A terminal given by its command line, with the conventions we know for it
+    fn known(argv: Vec<String>) -> Self {
+        let program = argv.first().map(|program| program.rsplit('/').next().unwrap_or(program)).unwrap_or_default();
+        let to_strings = |words: &[&str]| words.iter().map(|word| word.to_string()).collect::<Vec<_>>();
+        match KNOWN_TERMINALS.iter().find(|known| known.name == program) {
+            Some(known) => Self {
+                argv: [argv, to_strings(known.args)].concat(),
+                exec: to_strings(known.exec),
+                joined: known.joined,
+                title: known.title.map(str::to_string),
+                dir: known.dir.map(str::to_string),
+            },
+            // Most terminals run what follows -e
+            None => Self { argv, exec: vec!["-e".to_string()], joined: false, title: None, dir: None },
         }
-        
-        false
+    }
+
+    /// A terminal's desktop entry, if it is installed. Its X-TerminalArg* keys say how it
+    /// takes a command, title and directory; without them what we know is used.
+    fn from_desktop_file(path: &Path) -> Option<Self> {
+        let entry = parse_desktop_file(path)?;
+        if entry.get("Hidden").is_some_and(|hidden| hidden == "true") {
+            return None;
+        }
+        if entry.get("TryExec").is_some_and(|try_exec| !CommandIndex::contains(try_exec)) {
+            return None;
+        }
+        // Field codes like %u have nothing to expand to here
+        let argv: Vec<String> = utils::split_words(entry.get("Exec")?)
+            .into_iter()
+            .filter(|word| !word.starts_with('%'))
+            .collect();
+        if !CommandIndex::contains(argv.first()?) {
+            return None;
+        }
+
+        let mut terminal = Self::known(argv);
+        let arg = |key: &str| entry.get(key).map(|value| value.trim().to_string());
+        if let Some(exec) = arg("X-TerminalArgExec") {
+            terminal.exec = if exec.is_empty() { Vec::new() } else { vec![exec] };
+            terminal.joined = false;
+        }
+        if let Some(title) = arg("X-TerminalArgTitle").filter(|title| !title.is_empty()) {
+            terminal.title = Some(title);
+        }
+        if let Some(dir) = arg("X-TerminalArgDir").filter(|dir| !dir.is_empty()) {
+            terminal.dir = Some(dir);
+        }
+        Some(terminal)
+    }
+
+    /// The whole command line for running `command`
+    fn argv(&self, command: &[String], title: &str, dir: Option<&Path>) -> Vec<String> {
+        let mut argv = self.argv.clone();
+        let mut option = |option: &Option<String>, value: &str| match option {
+            Some(option) if option.ends_with('=') => argv.push(format!("{}{}", option, value)),
+            Some(option) => argv.extend([option.clone(), value.to_string()]),
+            None => {}
+        };
+        if !title.is_empty() {
+            option(&self.title, title);
+        }
+        if let Some(dir) = dir {
+            option(&self.dir, &dir.to_string_lossy());
+        }
+        argv.extend(self.exec.iter().cloned());
+        if self.joined {
+            argv.push(Terminal::quote(command));
+        } else {
+            argv.extend(command.iter().cloned());
+        }
+        argv
     }
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn strings(words: &[&str]) -> Vec<String> {
+        words.iter().map(|word| word.to_string()).collect()
+    }
+
+    #[test]
+    fn test_from_template() {
+        let command = strings(&["echo", "it's here"]);
+        let dir = Path::new("/home/me/src");
+        assert_eq!(
+            Terminal::from_template("foot --title={title} -D {cwd} {argv}", &command, "echo", Some(dir)),
+            ["foot", "--title=echo", "-D", "/home/me/src", "echo", "it's here"]
+        );
+        assert_eq!(
+            Terminal::from_template("tilix -e {command}", &command, "", None),
+            ["tilix", "-e", r"echo 'it'\''s here'"]
+        );
+        // Without a command placeholder the arguments go at the end
+        assert_eq!(Terminal::from_template("'my term' -x", &command, "", None), ["my term", "-x", "echo", "it's here"]);
+    }
+
+    #[test]
+    fn test_launcher_argv() {
+        let command = strings(&["htop"]);
+        let dir = Some(Path::new("/tmp"));
+        let foot = TerminalLauncher::known(strings(&["/usr/bin/foot"]));
+        assert_eq!(foot.argv(&command, "htop", dir), ["/usr/bin/foot", "--title=htop", "--working-directory=/tmp", "htop"]);
+        let kitty = TerminalLauncher::known(strings(&["kitty"]));
+        assert_eq!(kitty.argv(&command, "htop", dir), ["kitty", "--title", "htop", "--directory", "/tmp", "htop"]);
+        let wezterm = TerminalLauncher::known(strings(&["wezterm"]));
+        assert_eq!(wezterm.argv(&command, "", None), ["wezterm", "start", "--", "htop"]);
+        let lxterminal = TerminalLauncher::known(strings(&["lxterminal"]));
+        assert_eq!(lxterminal.argv(&strings(&["ls", "-l"]), "", None), ["lxterminal", "-e", "ls -l"]);
+        // Unknown terminals get -e and nothing else
+        let unknown = TerminalLauncher::known(strings(&["myterm"]));
+        assert_eq!(unknown.argv(&command, "htop", dir), ["myterm", "-e", "htop"]);
+    }
+
+    #[test]
+    fn test_prefixes() {
+        assert_eq!(Terminal::split_prefix("  >  ls -la "), (">  ", "ls -la "));
+        assert_eq!(Terminal::strip_prefix("$ git status  "), "git status");
+        assert_eq!(Terminal::strip_prefix("htop"), "htop");
+        assert_eq!(Terminal::quote(&strings(&["a b", "c=d", ""])), "'a b' c=d ''");
+    }
+}
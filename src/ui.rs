@@ -1,22 +1,48 @@
 use crate::app_launcher::{App, AppLauncher};
-use crate::calculator::Calculator;
-use crate::config::Config;
-use crate::emoji_picker::{Emoji, EmojiPicker};
+use crate::calculator::{Calculation, Calculator, Constant, Representation};
+use crate::calculator_history::{CalculatorHistory, HistoryEntry};
+use crate::clipboard::{Clipboard, ClipboardContent};
+use crate::clipboard_history::{ClipboardEntry, ClipboardHistory};
+use crate::command_index::CommandIndex;
+use crate::completion::{Completer, Completion};
+use crate::config::{ClipboardConfig, Config, ThemeConfig};
+use crate::datetime::{DateTimeCalculator, DateTimeResult};
+use crate::emoji_picker::{self, Emoji, EmojiPicker};
+use crate::inline_command::InlineCommand;
+use crate::insertion::{InsertMethod, Insertion};
+use crate::plotter::{Plot, Plotter};
 use crate::search::WebSearch;
+use crate::shell_history::{HistoryCommand, ShellHistory};
 use crate::settings::SettingsWindow;
+use crate::snippets::{Snippet, Snippets};
 use crate::terminal::Terminal;
+use crate::unicode_search::{CharInfo, UnicodeSearch};
 use crate::window_switcher::{OpenWindow, WindowSwitcher};
 use gtk::prelude::*;
 use gtk::glib;
 use gtk::gdk;
-use gtk::{Application, Entry, ListBox, ListBoxRow, Box as GtkBox, Label, Window, ScrolledWindow, EventControllerKey, Grid, Button, Image};
+use gtk::gio;
+use gtk::{Application, Entry, ListBox, ListBoxRow, Box as GtkBox, Label, Window, ScrolledWindow, EventControllerKey, Grid, Button, Image, DrawingArea};
+use std::path::PathBuf;
 use std::sync::{Arc, Mutex};
-use std::io::Write;
 use std::thread;
 
 pub struct LauncherState {
+    pub config: Config,
     pub app_launcher: AppLauncher,
+    pub calculator_history: CalculatorHistory,
+    pub calculator_expression: String, // Expression behind the calculator results shown
     pub emoji_picker: EmojiPicker,
+    pub emoji_tab: usize,      // Group tab shown when browsing, index into `emoji_picker::TABS`
+    pub browsing_emoji: bool,  // ":" without a search shows the group tabs
+    pub emoji_buffer: Vec<String>, // Emoji collected with Ctrl+Enter, inserted together
+    pub unicode_search: UnicodeSearch,
+    pub snippets: Snippets,
+    pub inline_command: Option<InlineCommand>, // Started with "!!", stopped when its query goes
+    pub shell_history: ShellHistory,
+    pub completer: Completer,
+    pub clipboard_text: Option<String>, // Clipboard text from when the launcher opened
+    pub clipboard_history: ClipboardHistory,
     pub window_switcher: WindowSwitcher,
     pub open_windows: Vec<OpenWindow>, // Cached list of open windows
     pub current_mode: Mode,
@@ -29,7 +55,11 @@ pub struct LauncherState {
 pub enum Mode {
     Apps,
     Calculator,
+    Clipboard,
+    DateTime,
     Emoji,
+    Unicode,
+    Snippet,
     Terminal,
     Search,
     WindowSwitch,
@@ -38,19 +68,50 @@ pub enum Mode {
 #[derive(Clone, Debug)]
 pub enum ResultItem {
     App(App),
-    CalculatorResult(String),
+    CalculatorResult(Calculation),
+    CalculatorAlternative(Representation),
+    DateTimeResult(DateTimeResult),
+    Plot(Plot),
+    CalculatorHistory(HistoryEntry),
+    ClearCalculatorHistory,
+    CalculatorConstant { constant: Constant, value: String },
+    ClipboardEntry(ClipboardEntry),
+    ClearClipboardHistory,
     Emoji(Emoji),
+    UnicodeCharacter(CharInfo),
+    UnicodeDetail { label: String, value: String },
+    Snippet(Snippet),
     TerminalCommand(String),
+    HistoryCommand(HistoryCommand),
+    Completion(Completion),
+    InlineCommand(String),
+    CopyCommandOutput,
     SearchQuery { engine: String, query: String },
     OpenWindow(OpenWindow),
     Settings,
 }
 
 impl LauncherState {
-    pub fn new() -> Self {
+    pub fn new(config: Config) -> Self {
+        let emoji_picker = EmojiPicker::new(&config.emoji);
+        let emoji_tab = emoji_picker.default_tab();
+        let snippets = Snippets::load(&config.snippets);
         Self {
+            config,
             app_launcher: AppLauncher::empty(), // Start with empty launcher for lazy loading
-            emoji_picker: EmojiPicker::new(),
+            calculator_history: CalculatorHistory::load(),
+            calculator_expression: String::new(),
+            emoji_picker,
+            emoji_tab,
+            browsing_emoji: false,
+            emoji_buffer: Vec::new(),
+            unicode_search: UnicodeSearch::new(),
+            snippets,
+            inline_command: None,
+            shell_history: ShellHistory::default(), // Loaded in the background
+            completer: Completer::new(),
+            clipboard_text: None,
+            clipboard_history: ClipboardHistory::load(),
             window_switcher: WindowSwitcher::new(),
             open_windows: Vec::new(),
             current_mode: Mode::Apps,
@@ -69,7 +130,15 @@ impl LauncherState {
     }
 
     pub fn update_query(&mut self, query: &str) {
+        let untrimmed = query;
         let query = query.trim();
+        self.browsing_emoji = false;
+
+        // A command run with "!!" stays while its query is shown
+        let inline_query = query.strip_prefix("!!").map(str::trim);
+        if self.inline_command.as_ref().is_some_and(|inline| Some(inline.command.as_str()) != inline_query) {
+            self.inline_command = None;
+        }
         
         // Determine mode based on query
         if query.is_empty() {
@@ -150,6 +219,38 @@ impl LauncherState {
             self.open_windows.clear();
         }
 
+        // "!! date" runs the command in the launcher and shows its output (before the calculator,
+        // "!! sleep 5" would be a factorial)
+        if let Some(command) = inline_query {
+            self.current_mode = Mode::Terminal;
+            self.results = Vec::new();
+            if command.is_empty() {
+                return;
+            }
+            if self.inline_command.is_some() {
+                self.results.push(ResultItem::CopyCommandOutput);
+            }
+            self.results.push(ResultItem::InlineCommand(command.to_string()));
+            self.results.push(ResultItem::TerminalCommand(command.to_string()));
+            return;
+        }
+
+        // Clipboard history: "cb" lists what was copied, "cb text" searches it
+        if query == "cb" || query.starts_with("cb ") {
+            self.current_mode = Mode::Clipboard;
+            // Leave room for the clear action within the eight visible rows
+            self.results = self.clipboard_history
+                .search(query[2..].trim())
+                .into_iter()
+                .take(7)
+                .map(|entry| ResultItem::ClipboardEntry(entry.clone()))
+                .collect();
+            if self.clipboard_history.entries.iter().any(|entry| !entry.pinned) {
+                self.results.push(ResultItem::ClearClipboardHistory);
+            }
+            return;
+        }
+
         // Check for search prefixes
         if query.starts_with("yt ") || query.starts_with("youtube ") {
             self.current_mode = Mode::Search;
@@ -181,10 +282,32 @@ impl LauncherState {
             return;
         }
 
+        // Snippets and kaomoji behind their own prefix (";sig", ";shrug")
+        let snippet_prefix = self.config.snippets.prefix.as_str();
+        if !snippet_prefix.is_empty() && query.starts_with(snippet_prefix) {
+            self.current_mode = Mode::Snippet;
+            self.results = self.snippets
+                .search(&query[snippet_prefix.len()..])
+                .into_iter()
+                .map(|snippet| ResultItem::Snippet(snippet.clone()))
+                .collect();
+            return;
+        }
+
         // Check for emoji mode
         if query.starts_with("emoji ") || query.starts_with(":") {
             self.current_mode = Mode::Emoji;
             let emoji_query = query.strip_prefix("emoji ").unwrap_or(query.strip_prefix(":").unwrap_or(query));
+            if emoji_query.is_empty() {
+                // Nothing typed yet: browse the whole group of the current tab
+                self.browsing_emoji = true;
+                self.results = self.emoji_picker
+                    .browse(self.emoji_tab)
+                    .into_iter()
+                    .map(|emoji| ResultItem::Emoji(emoji.clone()))
+                    .collect();
+                return;
+            }
             self.results = self.emoji_picker
                 .search(emoji_query)
                 .into_iter()
@@ -193,14 +316,95 @@ impl LauncherState {
             return;
         }
 
+        // "u:arrow" searches every Unicode character, "u:→" or "u:U+2192" inspects one
+        if let Some(unicode_query) = query.strip_prefix("u:") {
+            self.current_mode = Mode::Unicode;
+            let characters = self.unicode_search.search(unicode_query);
+            self.results = characters.iter().cloned().map(ResultItem::UnicodeCharacter).collect();
+            if let [info] = characters.as_slice() {
+                self.results.extend(info.details().into_iter().map(|(label, value)| ResultItem::UnicodeDetail {
+                    label: label.to_string(),
+                    value,
+                }));
+            }
+            return;
+        }
+
+        // Calculator history: "=" lists recent calculations, "= text" searches them
+        if self.config.calculator.enabled && query.starts_with('=') {
+            self.current_mode = Mode::Calculator;
+            let search = query[1..].trim();
+            self.results = Vec::new();
+
+            // "= 2 + 2" still calculates
+            let expanded = self.calculator_history.expand_answer(search);
+            if Calculator::is_calculation(&expanded, &self.config.calculator) {
+                if let Ok(calculation) = Calculator::calculate(&expanded, &self.config.calculator) {
+                    self.calculator_expression = search.to_string();
+                    self.results.push(ResultItem::CalculatorResult(calculation));
+                }
+            }
+
+            // Leave room for the clear action within the five visible rows
+            let room = 4 - self.results.len();
+            self.results.extend(
+                self.calculator_history
+                    .search(search)
+                    .into_iter()
+                    .take(room)
+                    .map(|entry| ResultItem::CalculatorHistory(entry.clone())),
+            );
+            if !self.calculator_history.entries.is_empty() {
+                self.results.push(ResultItem::ClearCalculatorHistory);
+            }
+            return;
+        }
+
+        // "const planck" looks up physical and mathematical constants
+        if self.config.calculator.enabled && (query == "const" || query.starts_with("const ")) {
+            self.current_mode = Mode::Calculator;
+            let config = &self.config.calculator;
+            self.results = Calculator::find_constants(query["const".len()..].trim(), config)
+                .into_iter()
+                .map(|constant| {
+                    let value = Calculator::constant_value(&constant, config);
+                    ResultItem::CalculatorConstant { constant, value }
+                })
+                .collect();
+            return;
+        }
+
+        // "ans" continues from the last calculation in the history
+        let expanded = self.calculator_history.expand_answer(query);
+
+        // Check for date/time queries (before the calculator, "2026-10-16 - 2025-01-01" looks like maths)
+        if DateTimeCalculator::is_datetime_query(query) {
+            if let Ok(result) = DateTimeCalculator::evaluate(query) {
+                self.current_mode = Mode::DateTime;
+                self.results = vec![ResultItem::DateTimeResult(result)];
+                return;
+            }
+        }
+
+        // Check for plots ("plot x^2" would otherwise look like a calculation)
+        if self.config.calculator.enabled && Plotter::is_plot_query(&expanded) {
+            if let Ok(plot) = Plotter::plot(&expanded, &self.config.calculator) {
+                self.current_mode = Mode::Calculator;
+                self.results = vec![ResultItem::Plot(plot)];
+                return;
+            }
+        }
+
         // Check for calculator
-        if Calculator::is_calculation(query) {
+        if self.config.calculator.enabled && Calculator::is_calculation(&expanded, &self.config.calculator) {
             self.current_mode = Mode::Calculator;
-            match Calculator::evaluate(query) {
-                Ok(result) => {
-                    self.results = vec![ResultItem::CalculatorResult(
-                        Calculator::format_result(result)
-                    )];
+            match Calculator::calculate(&expanded, &self.config.calculator) {
+                Ok(calculation) => {
+                    self.calculator_expression = query.to_string();
+                    // Main result first, then the other renderings (fraction, hex, ...)
+                    let alternatives = calculation.alternatives.clone();
+                    self.results = vec![ResultItem::CalculatorResult(calculation)];
+                    self.results.extend(alternatives.into_iter().map(ResultItem::CalculatorAlternative));
                 }
                 Err(_) => {
                     // If calculation fails, fall back to app search
@@ -218,7 +422,22 @@ impl LauncherState {
         // Check for terminal commands
         if Terminal::is_terminal_command(query) {
             self.current_mode = Mode::Terminal;
-            self.results = vec![ResultItem::TerminalCommand(query.to_string())];
+            let command = Terminal::strip_prefix(query);
+            self.results = vec![ResultItem::TerminalCommand(command.to_string())];
+            // A few commands run before, then completions of the word being typed
+            self.results.extend(
+                self.shell_history
+                    .search(command)
+                    .into_iter()
+                    .take(3)
+                    .map(|entry| ResultItem::HistoryCommand(entry.clone())),
+            );
+            let (prefix, line) = Terminal::split_prefix(untrimmed);
+            // Relative paths from where the command will run, the launcher's own directory by default
+            let dir = Terminal::working_directory(&self.config.terminal).unwrap_or_else(|| PathBuf::from("."));
+            self.results.extend(self.completer.complete(line, &dir).into_iter().map(|completion| {
+                ResultItem::Completion(Completion { line: format!("{}{}", prefix, completion.line), ..completion })
+            }));
             return;
         }
 
@@ -251,33 +470,93 @@ impl LauncherState {
         }
     }
 
-    pub fn execute_selected(&self, index: usize) -> Result<(), Box<dyn std::error::Error>> {
+    /// Run the result; text for the previous window is returned to be inserted once the
+    /// launcher is hidden
+    pub fn execute_selected(&mut self, index: usize) -> Result<Option<Insertion>, Box<dyn std::error::Error>> {
         // Use displayed_results instead of results
         if index >= self.displayed_results.len() {
             return Err("Index out of bounds".into());
         }
 
         match &self.displayed_results[index] {
+            ResultItem::ClearCalculatorHistory => {
+                self.calculator_history.clear();
+                self.calculator_history.save()?;
+            }
+            ResultItem::CalculatorConstant { value, .. } => {
+                Clipboard::set_text(value)?;
+            }
+            ResultItem::ClearClipboardHistory => {
+                // Read again first, another launcher may have recorded entries since
+                let mut history = ClipboardHistory::load();
+                history.clear();
+                history.save(&self.config.clipboard)?;
+                self.clipboard_history = history;
+            }
+            ResultItem::ClipboardEntry(entry) => {
+                // Pasted and left on the clipboard, and moved to the top of the history
+                let entry = entry.clone();
+                let mut history = ClipboardHistory::load();
+                if history.record(entry.content.clone(), entry.app.as_deref(), &self.config.clipboard) {
+                    if let Err(e) = history.save(&self.config.clipboard) {
+                        eprintln!("Error saving clipboard history: {}", e);
+                    }
+                }
+                self.clipboard_history = history;
+                return Ok(Some(Insertion::paste(entry.content)));
+            }
+            ResultItem::CalculatorHistory(_) | ResultItem::Completion(_) => {
+                // Re-inserted into the entry by the UI for editing
+            }
             ResultItem::App(app) => {
                 self.app_launcher.launch(app)?;
             }
-            ResultItem::CalculatorResult(result) => {
-                // Copy result to clipboard
-                use std::process::{Command, Stdio};
-                let mut child = Command::new("xclip")
-                    .arg("-selection")
-                    .arg("clipboard")
-                    .stdin(Stdio::piped())
-                    .spawn()?;
-                if let Some(stdin) = child.stdin.as_mut() {
-                    stdin.write_all(result.as_bytes())?;
+            ResultItem::CalculatorResult(Calculation { value, .. })
+            | ResultItem::CalculatorAlternative(Representation { value, .. }) => {
+                self.calculator_history.record(&self.calculator_expression, value, &self.config.calculator);
+                if let Err(e) = self.calculator_history.save() {
+                    eprintln!("Error saving calculator history: {}", e);
                 }
+
+                Clipboard::set_text(value)?;
+            }
+            ResultItem::DateTimeResult(result) => {
+                Clipboard::set_text(&result.value)?;
+            }
+            ResultItem::Plot(plot) => {
+                let theme = &self.config.theme;
+                let png = Plotter::to_png(plot, &theme.text_color, &theme.accent_color, &theme.background_color)?;
+                Clipboard::set(&ClipboardContent::Image(png))?;
             }
             ResultItem::Emoji(emoji) => {
-                EmojiPicker::insert_emoji(&emoji.emoji)?;
+                EmojiPicker::record_use(&emoji.base);
+                return Ok(Some(Insertion::new(&emoji.emoji)));
+            }
+            ResultItem::UnicodeCharacter(info) => {
+                return Ok(Some(Insertion::new(&info.character.to_string())));
+            }
+            ResultItem::UnicodeDetail { value, .. } => {
+                Clipboard::set_text(value)?;
+            }
+            ResultItem::Snippet(snippet) => {
+                return Ok(Some(Snippets::insertion(snippet, self.clipboard_text.as_deref())));
             }
-            ResultItem::TerminalCommand(cmd) => {
-                Terminal::execute_command(cmd)?;
+            ResultItem::TerminalCommand(command) | ResultItem::HistoryCommand(HistoryCommand { command, .. }) => {
+                let command = command.clone();
+                Terminal::execute_command(&command, &self.config.terminal)?;
+                self.record_command(&command);
+            }
+            ResultItem::InlineCommand(command) => {
+                // Runs while the launcher stays open, the UI shows the output
+                let command = command.clone();
+                let timeout = std::time::Duration::from_secs(self.config.terminal.inline_timeout_secs);
+                self.inline_command = Some(InlineCommand::start(&command, timeout)?);
+                self.record_command(&command);
+            }
+            ResultItem::CopyCommandOutput => {
+                if let Some(inline) = &self.inline_command {
+                    Clipboard::set_text(&inline.output())?;
+                }
             }
             ResultItem::SearchQuery { engine, query } => {
                 match engine.as_str() {
@@ -296,12 +575,51 @@ impl LauncherState {
             }
         }
 
-        Ok(())
+        Ok(None)
+    }
+
+    /// Remember a command run from the launcher, suggested in terminal mode later
+    fn record_command(&mut self, command: &str) {
+        if let Err(e) = self.shell_history.record(command) {
+            eprintln!("Error saving command history: {}", e);
+        }
+    }
+
+    /// What Ctrl+C copies for a result instead of running it
+    pub fn copy_content(&self, index: usize) -> Option<ClipboardContent> {
+        let text = |text: &str| Some(ClipboardContent::Text(text.to_string()));
+        match self.displayed_results.get(index)? {
+            ResultItem::App(app) => Some(ClipboardContent::Uris(vec![gio::File::for_path(&app.desktop_file).uri().to_string()])),
+            ResultItem::CalculatorResult(Calculation { value, .. })
+            | ResultItem::CalculatorAlternative(Representation { value, .. })
+            | ResultItem::CalculatorConstant { value, .. }
+            | ResultItem::UnicodeDetail { value, .. } => text(value),
+            ResultItem::DateTimeResult(result) => text(&result.value),
+            ResultItem::Plot(plot) => {
+                let theme = &self.config.theme;
+                Plotter::to_png(plot, &theme.text_color, &theme.accent_color, &theme.background_color)
+                    .ok()
+                    .map(ClipboardContent::Image)
+            }
+            ResultItem::CalculatorHistory(entry) => text(&entry.result),
+            ResultItem::ClipboardEntry(entry) => Some(entry.content.clone()),
+            ResultItem::Emoji(emoji) => text(&emoji.emoji),
+            ResultItem::UnicodeCharacter(info) => text(&info.character.to_string()),
+            ResultItem::Snippet(snippet) => text(&Snippets::insertion(snippet, self.clipboard_text.as_deref()).text),
+            ResultItem::TerminalCommand(command)
+            | ResultItem::InlineCommand(command)
+            | ResultItem::HistoryCommand(HistoryCommand { command, .. }) => text(command),
+            ResultItem::Completion(completion) => text(completion.line.trim_end()),
+            ResultItem::CopyCommandOutput => text(&self.inline_command.as_ref()?.output()),
+            ResultItem::SearchQuery { engine, query } => Some(ClipboardContent::Uris(vec![WebSearch::search_url(engine, query)?])),
+            ResultItem::OpenWindow(window) => text(&window.title),
+            ResultItem::ClearCalculatorHistory | ResultItem::ClearClipboardHistory | ResultItem::Settings => None,
+        }
     }
 }
 
 pub fn build_ui(app: &Application, config: Config) {
-    let state = Arc::new(Mutex::new(LauncherState::new()));
+    let state = Arc::new(Mutex::new(LauncherState::new(config.clone())));
     let config_arc = Arc::new(Mutex::new(config.clone()));
     let app_clone = app.clone();
     let config_for_css = config.clone();
@@ -310,7 +628,12 @@ pub fn build_ui(app: &Application, config: Config) {
     let window_width = 600;
     let entry_height = 60; // Height for just the search bar
     let row_height = 50; // Approximate height per result row
+    let plot_height = 240; // Height of the graph row for "plot ..." queries
+    let command_output_height = 240; // Output of a command run with "!!"
     let max_results = 5;
+    let browse_rows = 6; // Emoji rows visible before the grid scrolls when browsing a group
+    let emoji_tabs_height = 44;
+    let emoji_buffer_height = 40;
 
     // Create main window - fixed width, tall height for positioning (GNOME centers windows)
     // The window background is transparent so only the content shows
@@ -365,6 +688,103 @@ pub fn build_ui(app: &Application, config: Config) {
     // Initially hide the results list
     scrolled.set_visible(false);
 
+    // Group tabs above the emoji grid while browsing
+    let emoji_tabs = GtkBox::builder()
+        .orientation(gtk::Orientation::Horizontal)
+        .homogeneous(true)
+        .margin_start(10)
+        .margin_end(10)
+        .build();
+    for (index, (icon, name)) in emoji_picker::TABS.iter().enumerate() {
+        let button = Button::builder()
+            .label(*icon)
+            .tooltip_text(*name)
+            .has_frame(false)
+            .build();
+        button.add_css_class("emoji-tab");
+        let state_clone = state.clone();
+        let entry_weak = entry.downgrade();
+        button.connect_clicked(move |_| {
+            state_clone.lock().unwrap().emoji_tab = index;
+            if let Some(entry) = entry_weak.upgrade() {
+                entry.emit_by_name::<()>("changed", &[]);
+                entry.grab_focus();
+            }
+        });
+        emoji_tabs.append(&button);
+    }
+    emoji_tabs.set_visible(false);
+
+    // Emoji collected so far with Ctrl+Enter or Ctrl+click
+    let emoji_buffer_label = Label::new(None);
+    emoji_buffer_label.set_xalign(0.0);
+    emoji_buffer_label.set_hexpand(true);
+    emoji_buffer_label.add_css_class("emoji-buffer");
+    let emoji_buffer_hint = Label::new(Some("Enter inserts · Ctrl+Backspace removes the last"));
+    emoji_buffer_hint.add_css_class("app-description");
+    let emoji_buffer_box = GtkBox::builder()
+        .orientation(gtk::Orientation::Horizontal)
+        .spacing(12)
+        .margin_start(12)
+        .margin_end(12)
+        .build();
+    emoji_buffer_box.append(&emoji_buffer_label);
+    emoji_buffer_box.append(&emoji_buffer_hint);
+    emoji_buffer_box.set_visible(false);
+
+    // Output of a command run with "!!", above its actions
+    let command_status = Label::new(None);
+    command_status.set_xalign(0.0);
+    command_status.add_css_class("app-description");
+    let command_output = gtk::TextView::builder()
+        .editable(false)
+        .cursor_visible(false)
+        .monospace(true)
+        .wrap_mode(gtk::WrapMode::WordChar)
+        .build();
+    command_output.add_css_class("command-output");
+    let command_output_scrolled = ScrolledWindow::builder()
+        .child(&command_output)
+        .hscrollbar_policy(gtk::PolicyType::Never)
+        .min_content_height(command_output_height - 30)
+        .build();
+    let command_output_box = GtkBox::builder()
+        .orientation(gtk::Orientation::Vertical)
+        .spacing(4)
+        .margin_start(12)
+        .margin_end(12)
+        .build();
+    command_output_box.append(&command_status);
+    command_output_box.append(&command_output_scrolled);
+    command_output_box.set_visible(false);
+
+    // Picking an emoji from the grid or the variant popover: insert it together with
+    // the composition, or add it to the composition and start a new search
+    let pick_emoji_action = gio::SimpleAction::new("pick-emoji", Some(&<(String, String, bool)>::static_variant_type()));
+    let state_clone = state.clone();
+    let entry_weak = entry.downgrade();
+    let window_clone = window.clone();
+    pick_emoji_action.connect_activate(move |_, parameter| {
+        let Some((emoji, base, compose)) = parameter.and_then(|p| p.get::<(String, String, bool)>()) else {
+            return;
+        };
+        EmojiPicker::record_use(&base);
+        let mut state = state_clone.lock().unwrap();
+        state.emoji_buffer.push(emoji);
+        if compose {
+            drop(state);
+            if let Some(entry) = entry_weak.upgrade() {
+                reset_emoji_search(&entry);
+            }
+        } else {
+            let text = std::mem::take(&mut state.emoji_buffer).concat();
+            close_and_insert(&window_clone, Some(Insertion::new(&text)), &state);
+        }
+    });
+    let actions = gio::SimpleActionGroup::new();
+    actions.add_action(&pick_emoji_action);
+    window.insert_action_group("launcher", Some(&actions));
+
     // Apply CSS styling with animations
     let css = format!(
         r#"
@@ -488,6 +908,12 @@ pub fn build_ui(app: &Application, config: Config) {
             opacity: 0.9;
         }}
         
+        textview.command-output, textview.command-output text {{
+            background-color: rgba(0, 0, 0, 0.25);
+            color: rgba(255, 255, 255, 0.85);
+            font-size: 9pt;
+        }}
+        
         button.emoji-button {{
             background-color: transparent;
             background: transparent;
@@ -515,6 +941,32 @@ pub fn build_ui(app: &Application, config: Config) {
         button.emoji-button.emoji-placeholder:hover {{
             background-color: transparent;
         }}
+        
+        button.emoji-button.favorite {{
+            border-bottom: 2px solid {};
+        }}
+        
+        .emoji-buffer {{
+            font-size: 22px;
+        }}
+        
+        button.emoji-tab {{
+            background: transparent;
+            border: none;
+            border-radius: 0px;
+            padding: 6px 0px;
+            font-size: 18px;
+            opacity: 0.6;
+        }}
+        
+        button.emoji-tab.selected {{
+            opacity: 1.0;
+            border-bottom: 2px solid {};
+        }}
+        
+        .unicode-char {{
+            font-size: 24px;
+        }}
         "#,
         config_for_css.theme.background_color, // launcher-box background
         config_for_css.theme.font_size,         // entry font-size
@@ -525,6 +977,8 @@ pub fn build_ui(app: &Application, config: Config) {
         config_for_css.theme.font_size,         // label font-size
         config_for_css.theme.text_color,        // app-name color
         config_for_css.theme.font_size,         // app-name font-size
+        config_for_css.theme.accent_color,      // favorite emoji underline
+        config_for_css.theme.accent_color,      // selected emoji tab underline
     );
 
     let provider = gtk::CssProvider::new();
@@ -541,6 +995,11 @@ pub fn build_ui(app: &Application, config: Config) {
     let emoji_grid_clone = emoji_grid.clone();
     let results_container_clone = results_container.clone();
     let scrolled_clone = scrolled.clone();
+    let emoji_tabs_clone = emoji_tabs.clone();
+    let emoji_buffer_box_clone = emoji_buffer_box.clone();
+    let command_output_box_clone = command_output_box.clone();
+    let command_output_clone = command_output.clone();
+    let command_status_clone = command_status.clone();
     let window_clone = window.clone();
     let row_height_clone = row_height;
     let entry_height_clone = entry_height;
@@ -553,9 +1012,21 @@ pub fn build_ui(app: &Application, config: Config) {
         
         // Check if emoji mode
         let is_emoji_mode = matches!(state.current_mode, Mode::Emoji);
-        let limit = if is_emoji_mode { 
+        let limit = if is_emoji_mode && state.browsing_emoji {
+            // The whole group, the grid scrolls
+            usize::MAX
+        } else if is_emoji_mode { 
             // Always show exactly 24 emojis (3 rows of 8)
             24
+        } else if matches!(state.current_mode, Mode::Unicode) {
+            // A character and its seven details
+            8
+        } else if matches!(state.current_mode, Mode::Clipboard) {
+            // Seven entries and the clear action
+            8
+        } else if matches!(state.current_mode, Mode::Terminal) {
+            // The command, a few from history and completions
+            8
         } else { 
             max_results_clone 
         };
@@ -566,30 +1037,55 @@ pub fn build_ui(app: &Application, config: Config) {
         state.displayed_results = displayed.clone();
         state.selected_index = 0; // Reset selection when query changes
         let selected_idx = state.selected_index;
-        update_results_list(&list_box_clone, &emoji_grid_clone, &results_container_clone, &displayed, is_emoji_mode, &window_clone, selected_idx);
+        update_results_list(&list_box_clone, &emoji_grid_clone, &results_container_clone, &displayed, is_emoji_mode, selected_idx, &state.config.theme);
         
         // Clear selection when results change
         list_box_clone.unselect_all();
+
+        // Tabs and a scrolling grid while browsing emoji groups
+        let browsing = state.browsing_emoji;
+        update_emoji_tabs(&emoji_tabs_clone, browsing.then_some(state.emoji_tab));
+        let browse_height = (state.displayed_results.len().div_ceil(8).min(browse_rows) * 50) as i32 + 20;
+        scrolled_clone.set_vscrollbar_policy(if browsing { gtk::PolicyType::Automatic } else { gtk::PolicyType::Never });
+        scrolled_clone.set_min_content_height(if browsing { browse_height } else { -1 });
+        scrolled_clone.vadjustment().set_value(0.0);
+
+        // The composition stays visible while in emoji mode
+        let composing = is_emoji_mode && !state.emoji_buffer.is_empty();
+        emoji_buffer_label.set_text(&state.emoji_buffer.concat());
+        emoji_buffer_box_clone.set_visible(composing);
+        let buffer_height = if composing { emoji_buffer_height } else { 0 };
+
+        // The output pane while a "!!" command's query is shown
+        let inline_timeout = std::time::Duration::from_secs(state.config.terminal.inline_timeout_secs);
+        update_command_output(&command_output_clone, &command_status_clone, state.inline_command.as_ref(), inline_timeout);
+        command_output_box_clone.set_visible(state.inline_command.is_some());
+        let buffer_height = buffer_height + if state.inline_command.is_some() { command_output_height } else { 0 };
         
         // Calculate height based on number of results
         let num_results = state.displayed_results.len();
         if num_results > 0 {
             // Show results and adjust height
             scrolled_clone.set_visible(true);
-            let total_height = if is_emoji_mode {
+            let total_height = if browsing {
+                entry_height_clone + emoji_tabs_height + browse_height + 20
+            } else if is_emoji_mode {
                 // For emoji grid, calculate height based on grid rows (assuming 8 columns)
                 let rows = (num_results + 7) / 8; // Round up division
                 entry_height_clone + (rows as i32 * 50) + 40
             } else {
-                entry_height_clone + (num_results as i32 * row_height_clone) + 20
-            };
+                // Plot rows are taller than the others
+                let plots = displayed.iter().filter(|r| matches!(r, ResultItem::Plot(_))).count() as i32;
+                entry_height_clone + (num_results as i32 * row_height_clone) + plots * (plot_height - row_height_clone) + 20
+            } + buffer_height;
             window_clone.set_default_size(window_width_clone, total_height);
             window_clone.set_size_request(window_width_clone, total_height);
         } else {
-            // Hide results, just show search bar
+            // Hide results, just show search bar (and the tabs of an empty group)
             scrolled_clone.set_visible(false);
-            window_clone.set_default_size(window_width_clone, entry_height_clone);
-            window_clone.set_size_request(window_width_clone, entry_height_clone);
+            let height = if browsing { entry_height_clone + emoji_tabs_height } else { entry_height_clone } + buffer_height;
+            window_clone.set_default_size(window_width_clone, height);
+            window_clone.set_size_request(window_width_clone, height);
         }
     });
 
@@ -601,8 +1097,18 @@ pub fn build_ui(app: &Application, config: Config) {
     let results_container_clone = results_container.clone();
     let config_clone = config_arc.clone();
     let app_for_settings = app_clone.clone();
-    entry.connect_activate(move |_entry| {
-        let state = state_clone.lock().unwrap();
+    let command_output_clone = command_output.clone();
+    let command_status_clone = command_status.clone();
+    entry.connect_activate(move |entry| {
+        let mut state = state_clone.lock().unwrap();
+
+        // Enter inserts the emoji collected with Ctrl+Enter
+        if matches!(state.current_mode, Mode::Emoji) && !state.emoji_buffer.is_empty() {
+            let text = std::mem::take(&mut state.emoji_buffer).concat();
+            close_and_insert(&window_clone, Some(Insertion::new(&text)), &state);
+            return;
+        }
+
         if !state.displayed_results.is_empty() {
             // Check if emoji mode
             let is_emoji_mode = matches!(state.current_mode, Mode::Emoji);
@@ -617,14 +1123,38 @@ pub fn build_ui(app: &Application, config: Config) {
                 }
             };
             
-            // Check if settings
-            if let Some(ResultItem::Settings) = state.displayed_results.get(selected_index) {
-                SettingsWindow::open(&app_for_settings, config_clone.clone());
-                window_clone.close();
-            } else if let Err(e) = state.execute_selected(selected_index) {
-                eprintln!("Error executing: {}", e);
-            } else {
-                window_clone.close();
+            match state.displayed_results.get(selected_index).cloned() {
+                Some(ResultItem::Settings) => {
+                    SettingsWindow::open(&app_for_settings, config_clone.clone());
+                    window_clone.close();
+                }
+                Some(ResultItem::CalculatorHistory(history_entry)) => {
+                    // Put the calculation back into the entry for editing
+                    drop(state);
+                    entry.set_text(&history_entry.expression);
+                    entry.set_position(-1);
+                }
+                Some(ResultItem::Completion(completion)) => {
+                    drop(state);
+                    entry.set_text(&completion.line);
+                    entry.set_position(-1);
+                }
+                Some(ResultItem::InlineCommand(_)) => {
+                    drop(state);
+                    run_inline_command(&state_clone, selected_index, entry, &command_output_clone, &command_status_clone);
+                }
+                Some(ResultItem::ClearCalculatorHistory | ResultItem::ClearClipboardHistory) => {
+                    if let Err(e) = state.execute_selected(selected_index) {
+                        eprintln!("Error executing: {}", e);
+                    }
+                    // Refresh the now empty list, the lock must be released first
+                    drop(state);
+                    entry.emit_by_name::<()>("changed", &[]);
+                }
+                _ => match state.execute_selected(selected_index) {
+                    Ok(insertion) => close_and_insert(&window_clone, insertion, &state),
+                    Err(e) => eprintln!("Error executing: {}", e),
+                },
             }
         }
     });
@@ -638,13 +1168,191 @@ pub fn build_ui(app: &Application, config: Config) {
     let emoji_grid_clone = emoji_grid.clone();
     let results_container_clone = results_container.clone();
     let state_clone = state.clone();
-    entry_key_controller.connect_key_pressed(move |_, keyval, _, _| {
+    let scrolled_clone = scrolled.clone();
+    let entry_weak = entry.downgrade();
+    entry_key_controller.connect_key_pressed(move |_, keyval, _, modifier| {
+        // Tab and Ctrl+PageDown go to the next emoji group, Shift+Tab and Ctrl+PageUp back
+        let control = modifier.contains(gdk::ModifierType::CONTROL_MASK);
+        let tab_step = match keyval {
+            gdk::Key::Tab => Some(1),
+            gdk::Key::ISO_Left_Tab => Some(emoji_picker::TABS.len() - 1),
+            gdk::Key::Page_Down if control => Some(1),
+            gdk::Key::Page_Up if control => Some(emoji_picker::TABS.len() - 1),
+            _ => None,
+        };
+        if let Some(step) = tab_step {
+            let mut state = state_clone.lock().unwrap();
+            if keyval == gdk::Key::Tab && matches!(state.current_mode, Mode::Terminal) {
+                // Tab accepts the selected completion of a command, else the first one
+                let selected = list_box_clone.selected_row().map(|row| row.index() as usize);
+                let completion = selected
+                    .and_then(|index| state.displayed_results.get(index))
+                    .into_iter()
+                    .chain(&state.displayed_results)
+                    .find_map(|result| match result {
+                        ResultItem::Completion(completion) => Some(completion.line.clone()),
+                        _ => None,
+                    });
+                drop(state);
+                if let (Some(line), Some(entry)) = (completion, entry_weak.upgrade()) {
+                    entry.set_text(&line);
+                    entry.set_position(-1);
+                }
+                return glib::Propagation::Stop;
+            }
+            if !state.browsing_emoji {
+                return glib::Propagation::Proceed;
+            }
+            state.emoji_tab = (state.emoji_tab + step) % emoji_picker::TABS.len();
+            drop(state);
+            if let Some(entry) = entry_weak.upgrade() {
+                entry.emit_by_name::<()>("changed", &[]);
+            }
+            return glib::Propagation::Stop;
+        }
+
         match keyval {
             gdk::Key::Escape => {
                 // Close window
                 window_clone.close();
                 glib::Propagation::Stop
             }
+            gdk::Key::Return | gdk::Key::KP_Enter if modifier.contains(gdk::ModifierType::CONTROL_MASK) => {
+                // Ctrl+Enter adds the selected emoji to the composition
+                let state = state_clone.lock().unwrap();
+                if !matches!(state.current_mode, Mode::Emoji) {
+                    return glib::Propagation::Proceed;
+                }
+                if let Some(ResultItem::Emoji(emoji)) = state.displayed_results.get(state.selected_index).cloned() {
+                    drop(state);
+                    if let Some(entry) = entry_weak.upgrade() {
+                        pick_emoji(&entry, &emoji.emoji, &emoji.base, true);
+                    }
+                }
+                glib::Propagation::Stop
+            }
+            gdk::Key::BackSpace if modifier.contains(gdk::ModifierType::CONTROL_MASK) => {
+                // Ctrl+Backspace takes the last emoji back out of the composition
+                let mut state = state_clone.lock().unwrap();
+                if !matches!(state.current_mode, Mode::Emoji) || state.emoji_buffer.pop().is_none() {
+                    return glib::Propagation::Proceed;
+                }
+                drop(state);
+                if let Some(entry) = entry_weak.upgrade() {
+                    entry.emit_by_name::<()>("changed", &[]);
+                }
+                glib::Propagation::Stop
+            }
+            gdk::Key::Return | gdk::Key::KP_Enter if modifier.contains(gdk::ModifierType::SHIFT_MASK) => {
+                let state = state_clone.lock().unwrap();
+                if matches!(state.current_mode, Mode::Unicode) {
+                    // Shift+Enter on a character lists its details
+                    let index = list_box_clone.selected_row().map(|row| row.index() as usize).unwrap_or(0);
+                    if let Some(ResultItem::UnicodeCharacter(info)) = state.displayed_results.get(index).cloned() {
+                        drop(state);
+                        if let Some(entry) = entry_weak.upgrade() {
+                            entry.set_text(&format!("u:{}", info.codepoint));
+                            entry.set_position(-1);
+                        }
+                    }
+                    return glib::Propagation::Stop;
+                }
+
+                // Shift+Enter in the emoji grid opens the variant popover
+                if !matches!(state.current_mode, Mode::Emoji) {
+                    return glib::Propagation::Proceed;
+                }
+                let index = state.selected_index;
+                if let Some(ResultItem::Emoji(emoji)) = state.displayed_results.get(index).cloned() {
+                    drop(state);
+                    if let Some(button) = emoji_grid_clone.child_at((index % 8) as i32, (index / 8) as i32) {
+                        show_variant_popover(&button, &emoji);
+                    }
+                }
+                glib::Propagation::Stop
+            }
+            gdk::Key::c if control => {
+                // Ctrl+C copies the selected result instead of running it, unless text is selected
+                if entry_weak.upgrade().is_some_and(|entry| entry.selection_bounds().is_some()) {
+                    return glib::Propagation::Proceed;
+                }
+                let state = state_clone.lock().unwrap();
+                // Ctrl+C stops a command running with "!!", as in a terminal
+                if let Some(inline) = state.inline_command.as_ref().filter(|inline| inline.is_running()) {
+                    inline.stop();
+                    return glib::Propagation::Stop;
+                }
+                let index = if matches!(state.current_mode, Mode::Emoji) {
+                    state.selected_index
+                } else {
+                    list_box_clone.selected_row().map(|row| row.index() as usize).unwrap_or(0)
+                };
+                let Some(content) = state.copy_content(index) else {
+                    return glib::Propagation::Stop;
+                };
+                drop(state);
+                match Clipboard::set(&content) {
+                    Ok(()) => window_clone.close(),
+                    Err(e) => eprintln!("Error copying: {}", e),
+                }
+                glib::Propagation::Stop
+            }
+            gdk::Key::Delete | gdk::Key::KP_Delete if modifier.contains(gdk::ModifierType::SHIFT_MASK) => {
+                // Shift+Delete forgets the selected clipboard history entry
+                let index = list_box_clone.selected_row().map(|row| row.index() as usize).unwrap_or(0);
+                let mut state = state_clone.lock().unwrap();
+                let Some(ResultItem::ClipboardEntry(clipboard_entry)) = state.displayed_results.get(index).cloned() else {
+                    return glib::Propagation::Proceed;
+                };
+                let mut history = ClipboardHistory::load();
+                history.remove(&clipboard_entry.content);
+                if let Err(e) = history.save(&state.config.clipboard) {
+                    eprintln!("Error saving clipboard history: {}", e);
+                }
+                state.clipboard_history = history;
+                drop(state);
+                if let Some(entry) = entry_weak.upgrade() {
+                    entry.emit_by_name::<()>("changed", &[]);
+                }
+                glib::Propagation::Stop
+            }
+            gdk::Key::p if modifier.contains(gdk::ModifierType::CONTROL_MASK) => {
+                let mut state = state_clone.lock().unwrap();
+                if matches!(state.current_mode, Mode::Clipboard) {
+                    // Ctrl+P pins or unpins the selected clipboard history entry
+                    let index = list_box_clone.selected_row().map(|row| row.index() as usize).unwrap_or(0);
+                    if let Some(ResultItem::ClipboardEntry(clipboard_entry)) = state.displayed_results.get(index).cloned() {
+                        let mut history = ClipboardHistory::load();
+                        history.toggle_pin(&clipboard_entry.content);
+                        if let Err(e) = history.save(&state.config.clipboard) {
+                            eprintln!("Error saving clipboard history: {}", e);
+                        }
+                        state.clipboard_history = history;
+                        drop(state);
+                        if let Some(entry) = entry_weak.upgrade() {
+                            entry.emit_by_name::<()>("changed", &[]);
+                        }
+                    }
+                    return glib::Propagation::Stop;
+                }
+
+                // Ctrl+P pins or unpins the selected emoji as favorite
+                if !matches!(state.current_mode, Mode::Emoji) {
+                    return glib::Propagation::Proceed;
+                }
+                let index = state.selected_index;
+                if let Some(ResultItem::Emoji(emoji)) = state.displayed_results.get(index).cloned() {
+                    if let Err(e) = state.emoji_picker.toggle_favorite(&emoji.base) {
+                        eprintln!("Error saving favorites: {}", e);
+                    }
+                    // Refresh the grid, the lock must be released first
+                    drop(state);
+                    if let Some(entry) = entry_weak.upgrade() {
+                        entry.emit_by_name::<()>("changed", &[]);
+                    }
+                }
+                glib::Propagation::Stop
+            }
             gdk::Key::Down | gdk::Key::KP_Down => {
                 let mut state = state_clone.lock().unwrap();
                 let is_emoji_mode = matches!(state.current_mode, Mode::Emoji);
@@ -658,8 +1366,10 @@ pub fn build_ui(app: &Application, config: Config) {
                         // Update UI
                         let displayed: Vec<_> = state.displayed_results.clone();
                         let selected_idx = state.selected_index;
+                        let theme = state.config.theme.clone();
                         drop(state);
-                        update_results_list(&list_box_clone, &emoji_grid_clone, &results_container_clone, &displayed, is_emoji_mode, &window_clone, selected_idx);
+                        update_results_list(&list_box_clone, &emoji_grid_clone, &results_container_clone, &displayed, is_emoji_mode, selected_idx, &theme);
+                        scroll_to_emoji(&scrolled_clone, selected_idx);
                     } else {
                         // List navigation
                         if let Some(selected_row) = list_box_clone.selected_row() {
@@ -690,8 +1400,10 @@ pub fn build_ui(app: &Application, config: Config) {
                         // Update UI
                         let displayed: Vec<_> = state.displayed_results.clone();
                         let selected_idx = state.selected_index;
+                        let theme = state.config.theme.clone();
                         drop(state);
-                        update_results_list(&list_box_clone, &emoji_grid_clone, &results_container_clone, &displayed, is_emoji_mode, &window_clone, selected_idx);
+                        update_results_list(&list_box_clone, &emoji_grid_clone, &results_container_clone, &displayed, is_emoji_mode, selected_idx, &theme);
+                        scroll_to_emoji(&scrolled_clone, selected_idx);
                     } else {
                         // List navigation
                         if let Some(selected_row) = list_box_clone.selected_row() {
@@ -742,8 +1454,10 @@ pub fn build_ui(app: &Application, config: Config) {
                     // Update UI
                     let displayed: Vec<_> = state.displayed_results.clone();
                     let selected_idx = state.selected_index;
+                    let theme = state.config.theme.clone();
                     drop(state);
-                    update_results_list(&list_box_clone, &emoji_grid_clone, &results_container_clone, &displayed, is_emoji_mode, &window_clone, selected_idx);
+                    update_results_list(&list_box_clone, &emoji_grid_clone, &results_container_clone, &displayed, is_emoji_mode, selected_idx, &theme);
+                    scroll_to_emoji(&scrolled_clone, selected_idx);
                     glib::Propagation::Stop
                 } else {
                     glib::Propagation::Proceed
@@ -777,8 +1491,10 @@ pub fn build_ui(app: &Application, config: Config) {
                     // Update UI
                     let displayed: Vec<_> = state.displayed_results.clone();
                     let selected_idx = state.selected_index;
+                    let theme = state.config.theme.clone();
                     drop(state);
-                    update_results_list(&list_box_clone, &emoji_grid_clone, &results_container_clone, &displayed, is_emoji_mode, &window_clone, selected_idx);
+                    update_results_list(&list_box_clone, &emoji_grid_clone, &results_container_clone, &displayed, is_emoji_mode, selected_idx, &theme);
+                    scroll_to_emoji(&scrolled_clone, selected_idx);
                     glib::Propagation::Stop
                 } else {
                     glib::Propagation::Proceed
@@ -792,13 +1508,38 @@ pub fn build_ui(app: &Application, config: Config) {
     // Handle list box row activation
     let state_clone = state.clone();
     let window_clone = window.clone();
+    let entry_clone = entry.clone();
+    let command_output_clone = command_output.clone();
+    let command_status_clone = command_status.clone();
     list_box.connect_row_activated(move |_, row| {
         let index = row.index();
-        let state = state_clone.lock().unwrap();
-        if let Err(e) = state.execute_selected(index as usize) {
-            eprintln!("Error executing: {}", e);
+        let mut state = state_clone.lock().unwrap();
+        if let Some(ResultItem::InlineCommand(_)) = state.displayed_results.get(index as usize) {
+            drop(state);
+            run_inline_command(&state_clone, index as usize, &entry_clone, &command_output_clone, &command_status_clone);
+            return;
+        }
+        if let Some(ResultItem::CalculatorHistory(history_entry)) = state.displayed_results.get(index as usize).cloned() {
+            drop(state);
+            entry_clone.set_text(&history_entry.expression);
+            entry_clone.set_position(-1);
+            entry_clone.grab_focus();
+            return;
+        }
+        if let Some(ResultItem::Completion(completion)) = state.displayed_results.get(index as usize).cloned() {
+            drop(state);
+            entry_clone.set_text(&completion.line);
+            entry_clone.set_position(-1);
+            entry_clone.grab_focus();
+            return;
+        }
+        match state.execute_selected(index as usize) {
+            Ok(insertion) => close_and_insert(&window_clone, insertion, &state),
+            Err(e) => {
+                eprintln!("Error executing: {}", e);
+                window_clone.close();
+            }
         }
-        window_clone.close();
     });
 
     // Initial state - compact, no results
@@ -810,13 +1551,62 @@ pub fn build_ui(app: &Application, config: Config) {
 
     // Assemble UI
     main_box.append(&entry);
+    main_box.append(&emoji_buffer_box);
+    main_box.append(&emoji_tabs);
+    main_box.append(&command_output_box);
     main_box.append(&scrolled);
     window.set_child(Some(&main_box));
 
+    // Copied content has to outlive the window, the process stays until it is pasted elsewhere
+    let keep_alive = std::time::Duration::from_secs(config.clipboard.keep_alive_secs);
+    let app_for_clipboard = app_clone.clone();
+    window.connect_close_request(move |_| {
+        Clipboard::keep_alive(&app_for_clipboard, keep_alive);
+        glib::Propagation::Proceed
+    });
+
+    // A "!!" command still running dies with the launcher. On shutdown, since the window may
+    // be closed while the state is locked.
+    let state_clone = state.clone();
+    app.connect_shutdown(move |_| {
+        if let Ok(mut state) = state_clone.lock() {
+            state.inline_command = None;
+        }
+    });
+
     // Show window with fade-in animation
     window.set_opacity(0.0);
     window.present();
     entry.grab_focus();
+
+    // The clipboard as it was before, for snippets and for restoring it after pasting
+    let state_clone = state.clone();
+    glib::MainContext::default().spawn_local(async move {
+        let text = Clipboard::read_text().await;
+        state_clone.lock().unwrap().clipboard_text = text;
+    });
+
+    // Copies are remembered while the launcher is open too: on Wayland only the focused window
+    // hears of them, so the resident process misses what was copied in other apps
+    let record = {
+        let state = state.clone();
+        let clipboard_config = config.clipboard.clone();
+        move || {
+            let state = state.clone();
+            let clipboard_config = clipboard_config.clone();
+            // The window focused now is most likely the one the copy was made in
+            let app = WindowSwitcher::focused_window().map(|window| window.app_name);
+            glib::MainContext::default().spawn_local(async move {
+                if let Some(history) = record_clipboard(&clipboard_config, app).await {
+                    state.lock().unwrap().clipboard_history = history;
+                }
+            });
+        }
+    };
+    record();
+    if let Err(e) = Clipboard::connect_changed(record) {
+        eprintln!("Error watching the clipboard: {}", e);
+    }
     
     // Animate window fade-in
     let window_clone = window.clone();
@@ -835,6 +1625,15 @@ pub fn build_ui(app: &Application, config: Config) {
     // Load apps in background thread after window appears (lazy loading)
     let state_clone = state.clone();
     thread::spawn(move || {
+        // Commands on PATH, for recognizing terminal queries, and those run before
+        CommandIndex::load();
+        let terminal_config = state_clone.lock().unwrap().config.terminal.clone();
+        let shell_history = ShellHistory::load(&terminal_config);
+        let history_state = state_clone.clone();
+        glib::MainContext::default().invoke(move || {
+            history_state.lock().unwrap().shell_history = shell_history;
+        });
+
         // Load apps in background
         if let Ok(app_launcher) = AppLauncher::new() {
             // Update UI from background thread using glib MainContext
@@ -847,7 +1646,217 @@ pub fn build_ui(app: &Application, config: Config) {
     });
 }
 
-fn update_results_list(list_box: &ListBox, emoji_grid: &Grid, results_container: &GtkBox, results: &[ResultItem], is_emoji_mode: bool, window: &Window, selected_index: usize) {
+/// Start the "!!" command of a result, then keep its output pane current until it ends
+fn run_inline_command(state: &Arc<Mutex<LauncherState>>, index: usize, entry: &Entry, output: &gtk::TextView, status: &Label) {
+    let timeout = {
+        let mut state = state.lock().unwrap();
+        if let Err(e) = state.execute_selected(index) {
+            eprintln!("Error running command: {}", e);
+        }
+        std::time::Duration::from_secs(state.config.terminal.inline_timeout_secs)
+    };
+    // Shows the pane and the copy action, the lock must be released first
+    entry.emit_by_name::<()>("changed", &[]);
+
+    let state = state.clone();
+    let output = output.clone();
+    let status = status.clone();
+    glib::timeout_add_local(std::time::Duration::from_millis(100), move || {
+        let state = state.lock().unwrap();
+        let Some(inline) = state.inline_command.as_ref() else {
+            return glib::ControlFlow::Break;
+        };
+        update_command_output(&output, &status, Some(inline), timeout);
+        if inline.is_running() {
+            glib::ControlFlow::Continue
+        } else {
+            glib::ControlFlow::Break
+        }
+    });
+}
+
+/// Show a "!!" command's output so far, scrolled to the end, and its status
+fn update_command_output(output: &gtk::TextView, status: &Label, inline: Option<&InlineCommand>, timeout: std::time::Duration) {
+    let Some(inline) = inline else {
+        output.buffer().set_text("");
+        return;
+    };
+    status.set_text(&inline.summary(timeout));
+
+    let text = inline.output();
+    let buffer = output.buffer();
+    // Output only grows, unchanged output keeps its scroll position
+    if buffer.char_count() as usize != text.chars().count() {
+        buffer.set_text(&text);
+        buffer.place_cursor(&buffer.end_iter());
+        output.scroll_mark_onscreen(&buffer.get_insert());
+    }
+}
+
+/// Run without a window for the clipboard history (`--resident`), remembering what is copied
+/// until the process is stopped
+pub fn run_resident(app: &Application) {
+    // Never released, there is no window to keep the application running
+    std::mem::forget(app.hold());
+
+    let result = Clipboard::connect_changed(|| {
+        // Settings may have changed since the start
+        let config = Config::load().unwrap_or_default().clipboard;
+        // The window focused now is most likely the one the copy was made in
+        let app = WindowSwitcher::focused_window().map(|window| window.app_name);
+        glib::MainContext::default().spawn_local(async move {
+            record_clipboard(&config, app).await;
+        });
+    });
+    if let Err(e) = result {
+        eprintln!("Error watching the clipboard: {}", e);
+    }
+}
+
+/// Add the clipboard's contents to the saved history, unless a password manager marked them as
+/// secret. Returns the history when it changed.
+async fn record_clipboard(config: &ClipboardConfig, app: Option<String>) -> Option<ClipboardHistory> {
+    if !config.history || Clipboard::is_secret() || ClipboardHistory::is_excluded(app.as_deref(), config) {
+        return None;
+    }
+    let content = Clipboard::read().await?;
+    let mut history = ClipboardHistory::load();
+    if !history.record(content, app.as_deref(), config) {
+        return None;
+    }
+    if let Err(e) = history.save(config) {
+        eprintln!("Error saving clipboard history: {}", e);
+    }
+    Some(history)
+}
+
+/// Close the launcher. Text to insert is sent once it is hidden and the window it was
+/// opened over has focus again, so it isn't typed into the launcher itself.
+fn close_and_insert(window: &Window, insertion: Option<Insertion>, state: &LauncherState) {
+    let Some(insertion) = insertion else {
+        window.close();
+        return;
+    };
+
+    // The clipboard is set while the launcher still has focus, Wayland requires that
+    let config = state.config.insert.clone();
+    if let Err(e) = insertion.prepare(&config) {
+        eprintln!("Error setting the clipboard: {}", e);
+    }
+    if insertion.method(&config) == InsertMethod::Clipboard {
+        window.close();
+        return;
+    }
+
+    window.set_visible(false);
+    let window = window.clone();
+    let previous_clipboard = state.clipboard_text.clone();
+    glib::MainContext::default().spawn_local(async move {
+        let blocking_insertion = insertion.clone();
+        let blocking_config = config.clone();
+        let result = gio::spawn_blocking(move || blocking_insertion.run(&blocking_config).map_err(|e| e.to_string())).await;
+        let inserted = match result {
+            Ok(Ok(inserted)) => inserted,
+            Ok(Err(e)) => {
+                eprintln!("Error inserting text: {}", e);
+                false
+            }
+            Err(_) => {
+                eprintln!("Error inserting text: insertion thread panicked");
+                false
+            }
+        };
+        insertion.finish(inserted, &config, previous_clipboard.as_deref());
+        window.close();
+    });
+}
+
+/// Back to ":" for the next emoji of a composition, refreshing even if it was already there
+fn reset_emoji_search(entry: &Entry) {
+    if entry.text() == ":" {
+        entry.emit_by_name::<()>("changed", &[]);
+    } else {
+        entry.set_text(":");
+        entry.set_position(-1);
+    }
+    entry.grab_focus();
+}
+
+/// Insert an emoji, or with `compose` add it to the composition, through the window's
+/// "launcher.pick-emoji" action so the state's composition buffer is taken into account
+fn pick_emoji(widget: &impl IsA<gtk::Widget>, emoji: &str, base: &str, compose: bool) {
+    let parameter = (emoji.to_string(), base.to_string(), compose).to_variant();
+    if let Err(e) = widget.activate_action("launcher.pick-emoji", Some(&parameter)) {
+        eprintln!("Error picking emoji: {}", e);
+    }
+}
+
+/// Show the group tabs with the active one highlighted, or hide them
+fn update_emoji_tabs(tabs: &GtkBox, active: Option<usize>) {
+    tabs.set_visible(active.is_some());
+    let mut child = tabs.first_child();
+    let mut index = 0;
+    while let Some(tab) = child {
+        if active == Some(index) {
+            tab.add_css_class("selected");
+        } else {
+            tab.remove_css_class("selected");
+        }
+        child = tab.next_sibling();
+        index += 1;
+    }
+}
+
+/// Scroll a browsed emoji group so the selected row stays visible
+fn scroll_to_emoji(scrolled: &ScrolledWindow, index: usize) {
+    // Rows are about 50px apart, as assumed for the window height
+    let adjustment = scrolled.vadjustment();
+    let top = (index / 8) as f64 * 50.0;
+    if top < adjustment.value() {
+        adjustment.set_value(top);
+    } else if top + 60.0 > adjustment.value() + adjustment.page_size() {
+        adjustment.set_value(top + 60.0 - adjustment.page_size());
+    }
+}
+
+/// Popover with the skin tone, gender and hair variants of an emoji. The picked
+/// variant is remembered for this emoji and inserted.
+fn show_variant_popover(anchor: &impl IsA<gtk::Widget>, emoji: &Emoji) {
+    let variants = EmojiPicker::variants(emoji);
+    if variants.len() < 2 {
+        return;
+    }
+
+    let flow_box = gtk::FlowBox::builder()
+        .max_children_per_line(8)
+        .selection_mode(gtk::SelectionMode::None)
+        .build();
+    let popover = gtk::Popover::builder().child(&flow_box).build();
+    popover.set_parent(anchor);
+
+    for variant in variants {
+        let button = Button::builder().label(&variant).has_frame(false).build();
+        button.add_css_class("emoji-button");
+
+        let base = emoji.base.clone();
+        let popover_clone = popover.clone();
+        button.connect_clicked(move |button| {
+            if let Err(e) = EmojiPicker::remember_variant(&base, &variant) {
+                eprintln!("Error saving emoji variant: {}", e);
+            }
+            // Still inside the window here, popping down unparents the popover
+            pick_emoji(button, &variant, &base, false);
+            popover_clone.popdown();
+        });
+        flow_box.insert(&button, -1);
+    }
+
+    popover.connect_closed(|popover| popover.unparent());
+    popover.popup();
+}
+
+#[allow(clippy::too_many_arguments)]
+fn update_results_list(list_box: &ListBox, emoji_grid: &Grid, results_container: &GtkBox, results: &[ResultItem], is_emoji_mode: bool, selected_index: usize, theme: &ThemeConfig) {
     // Clear existing content
     while let Some(child) = results_container.first_child() {
         results_container.remove(&child);
@@ -882,13 +1891,45 @@ fn update_results_list(list_box: &ListBox, emoji_grid: &Grid, results_container:
                 button.set_hexpand(true);
                 button.set_vexpand(true);
                 
+                if emoji.favorite {
+                    button.add_css_class("favorite");
+                }
+                
                 // Connect click handler
                 let emoji_emoji = emoji.emoji.clone();
-                let window_clone = window.clone();
-                button.connect_clicked(move |_| {
-                    let _ = crate::emoji_picker::EmojiPicker::insert_emoji(&emoji_emoji);
-                    window_clone.close();
+                let emoji_base = emoji.base.clone();
+                button.connect_clicked(move |button| {
+                    pick_emoji(button, &emoji_emoji, &emoji_base, false);
                 });
+
+                // Ctrl+click adds the emoji to the composition instead
+                let ctrl_click = gtk::GestureClick::new();
+                ctrl_click.set_propagation_phase(gtk::PropagationPhase::Capture);
+                let button_weak = button.downgrade();
+                let emoji_emoji = emoji.emoji.clone();
+                let emoji_base = emoji.base.clone();
+                ctrl_click.connect_pressed(move |gesture, _, _, _| {
+                    if gesture.current_event_state().contains(gdk::ModifierType::CONTROL_MASK) {
+                        gesture.set_state(gtk::EventSequenceState::Claimed);
+                        if let Some(button) = button_weak.upgrade() {
+                            pick_emoji(&button, &emoji_emoji, &emoji_base, true);
+                        }
+                    }
+                });
+                button.add_controller(ctrl_click);
+
+                // Long press shows the skin tone and gender variants
+                let long_press = gtk::GestureLongPress::new();
+                let button_weak = button.downgrade();
+                let emoji_clone = emoji.clone();
+                long_press.connect_pressed(move |gesture, _, _| {
+                    // Claiming the press keeps the button from also inserting on release
+                    gesture.set_state(gtk::EventSequenceState::Claimed);
+                    if let Some(button) = button_weak.upgrade() {
+                        show_variant_popover(&button, &emoji_clone);
+                    }
+                });
+                button.add_controller(long_press);
                 
                 emoji_grid.attach(&button, col, row, 1, 1);
                 
@@ -981,21 +2022,183 @@ fn update_results_list(list_box: &ListBox, emoji_grid: &Grid, results_container:
                     row_box.append(&icon_widget);
                     row_box.append(&text_box);
                 }
-                ResultItem::CalculatorResult(result) => {
-                    let label = Label::new(Some(&format!("= {}", result)));
+                ResultItem::CalculatorResult(calculation) => {
+                    let label = Label::new(Some(&format!("= {}", calculation.value)));
                     label.set_xalign(0.0);
+                    // Exact results can be very long (e.g. 100!)
+                    label.set_wrap(true);
+                    label.set_wrap_mode(gtk::pango::WrapMode::Char);
                     row_box.append(&label);
                 }
+                ResultItem::CalculatorAlternative(representation) => {
+                    let value_label = Label::new(Some(&representation.value));
+                    value_label.set_xalign(0.0);
+                    value_label.set_hexpand(true);
+                    value_label.set_wrap(true);
+                    value_label.set_wrap_mode(gtk::pango::WrapMode::Char);
+
+                    let kind_label = Label::new(Some(&representation.label));
+                    kind_label.add_css_class("app-description");
+
+                    row_box.append(&value_label);
+                    row_box.append(&kind_label);
+                }
+                ResultItem::DateTimeResult(result) => {
+                    let text_box = GtkBox::builder()
+                        .orientation(gtk::Orientation::Vertical)
+                        .spacing(1)
+                        .valign(gtk::Align::Center)
+                        .build();
+
+                    let value_label = Label::new(Some(&result.value));
+                    value_label.set_xalign(0.0);
+                    value_label.add_css_class("app-name");
+
+                    // Other formats (ISO 8601, epoch, relative, ...)
+                    let details_label = Label::new(Some(&result.details.join("  ·  ")));
+                    details_label.set_xalign(0.0);
+                    details_label.set_wrap(true);
+                    details_label.add_css_class("app-description");
+
+                    text_box.append(&value_label);
+                    text_box.append(&details_label);
+                    row_box.append(&text_box);
+                }
+                ResultItem::Plot(plot) => {
+                    let text_box = GtkBox::builder()
+                        .orientation(gtk::Orientation::Vertical)
+                        .spacing(4)
+                        .hexpand(true)
+                        .build();
+
+                    let area = DrawingArea::builder()
+                        .content_height(200)
+                        .hexpand(true)
+                        .build();
+                    let plot = plot.clone();
+                    let (text_color, accent_color) = (theme.text_color.clone(), theme.accent_color.clone());
+                    area.set_draw_func(move |_, cr, width, height| {
+                        Plotter::draw(&plot, cr, width as f64, height as f64, &text_color, &accent_color);
+                    });
+
+                    let hint_label = Label::new(Some("Enter copies the graph as PNG"));
+                    hint_label.set_xalign(0.0);
+                    hint_label.add_css_class("app-description");
+
+                    text_box.append(&area);
+                    text_box.append(&hint_label);
+                    row_box.append(&text_box);
+                }
                 ResultItem::Emoji(_) => {
                     // Should not happen in non-emoji mode, but handle it
                     let label = Label::new(Some(""));
                     row_box.append(&label);
                 }
+                ResultItem::UnicodeCharacter(info) => {
+                    let char_label = Label::new(Some(&UnicodeSearch::display(info)));
+                    char_label.set_width_chars(2);
+                    char_label.add_css_class("unicode-char");
+
+                    let text_box = GtkBox::builder()
+                        .orientation(gtk::Orientation::Vertical)
+                        .spacing(1)
+                        .valign(gtk::Align::Center)
+                        .build();
+
+                    let name_label = Label::new(Some(&info.name));
+                    name_label.set_xalign(0.0);
+                    name_label.add_css_class("app-name");
+
+                    // "U+2192 · Arrows · Math Symbol (Sm)"
+                    let desc_label = Label::new(Some(&format!("{} · {} · {}", info.codepoint, info.block, info.category)));
+                    desc_label.set_xalign(0.0);
+                    desc_label.add_css_class("app-description");
+
+                    text_box.append(&name_label);
+                    text_box.append(&desc_label);
+                    row_box.append(&char_label);
+                    row_box.append(&text_box);
+                }
+                ResultItem::UnicodeDetail { label, value } => {
+                    let value_label = Label::new(Some(value));
+                    value_label.set_xalign(0.0);
+                    value_label.set_hexpand(true);
+
+                    let kind_label = Label::new(Some(label));
+                    kind_label.add_css_class("app-description");
+
+                    row_box.append(&value_label);
+                    row_box.append(&kind_label);
+                }
+                ResultItem::Snippet(snippet) => {
+                    let text_box = GtkBox::builder()
+                        .orientation(gtk::Orientation::Vertical)
+                        .spacing(1)
+                        .valign(gtk::Align::Center)
+                        .hexpand(true)
+                        .build();
+
+                    let name_label = Label::new(Some(&snippet.name));
+                    name_label.set_xalign(0.0);
+                    name_label.add_css_class("app-name");
+
+                    // First line of the content, placeholders as written
+                    let preview = snippet.content.trim().lines().next().unwrap_or("");
+                    let preview_label = Label::new(Some(preview));
+                    preview_label.set_xalign(0.0);
+                    preview_label.set_ellipsize(gtk::pango::EllipsizeMode::End);
+                    preview_label.add_css_class("app-description");
+
+                    let collection_label = Label::new(Some(&snippet.collection));
+                    collection_label.add_css_class("app-description");
+
+                    text_box.append(&name_label);
+                    text_box.append(&preview_label);
+                    row_box.append(&text_box);
+                    row_box.append(&collection_label);
+                }
                 ResultItem::TerminalCommand(cmd) => {
                     let label = Label::new(Some(&format!("▶ {}", cmd)));
                     label.set_xalign(0.0);
                     row_box.append(&label);
                 }
+                ResultItem::HistoryCommand(entry) => {
+                    let label = Label::new(Some(&format!("🕘 {}", entry.command.lines().next().unwrap_or(""))));
+                    label.set_xalign(0.0);
+                    label.set_hexpand(true);
+                    label.set_ellipsize(gtk::pango::EllipsizeMode::End);
+
+                    let description_label = Label::new(Some(&entry.description()));
+                    description_label.add_css_class("app-description");
+
+                    row_box.append(&label);
+                    row_box.append(&description_label);
+                }
+                ResultItem::Completion(completion) => {
+                    let label = Label::new(Some(&format!("⇥ {}", completion.word)));
+                    label.set_xalign(0.0);
+                    label.set_hexpand(true);
+                    label.set_ellipsize(gtk::pango::EllipsizeMode::End);
+                    row_box.append(&label);
+
+                    if let Some(description) = &completion.description {
+                        let description_label = Label::new(Some(description));
+                        description_label.set_ellipsize(gtk::pango::EllipsizeMode::End);
+                        description_label.set_max_width_chars(40);
+                        description_label.add_css_class("app-description");
+                        row_box.append(&description_label);
+                    }
+                }
+                ResultItem::InlineCommand(cmd) => {
+                    let label = Label::new(Some(&format!("⚡ Run here: {}", cmd)));
+                    label.set_xalign(0.0);
+                    row_box.append(&label);
+                }
+                ResultItem::CopyCommandOutput => {
+                    let label = Label::new(Some("📋 Copy output"));
+                    label.set_xalign(0.0);
+                    row_box.append(&label);
+                }
                 ResultItem::SearchQuery { engine, query } => {
                     let label = Label::new(Some(&format!("🌐 Search {}: {}", engine, query)));
                     label.set_xalign(0.0);
@@ -1033,6 +2236,86 @@ fn update_results_list(list_box: &ListBox, emoji_grid: &Grid, results_container:
                     row_box.append(&icon_widget);
                     row_box.append(&text_box);
                 }
+                ResultItem::CalculatorHistory(history_entry) => {
+                    let text_box = GtkBox::builder()
+                        .orientation(gtk::Orientation::Vertical)
+                        .spacing(1)
+                        .valign(gtk::Align::Center)
+                        .build();
+
+                    let name_label = Label::new(Some(&history_entry.expression));
+                    name_label.set_xalign(0.0);
+                    name_label.add_css_class("app-name");
+
+                    let desc_label = Label::new(Some(&format!("= {}", history_entry.result)));
+                    desc_label.set_xalign(0.0);
+                    desc_label.add_css_class("app-description");
+
+                    text_box.append(&name_label);
+                    text_box.append(&desc_label);
+                    row_box.append(&text_box);
+                }
+                ResultItem::CalculatorConstant { constant, value } => {
+                    let text_box = GtkBox::builder()
+                        .orientation(gtk::Orientation::Vertical)
+                        .spacing(1)
+                        .valign(gtk::Align::Center)
+                        .build();
+
+                    // "h = 6.62607015e-34 J s"
+                    let name_label = Label::new(Some(format!("{} = {} {}", constant.name, value, constant.unit).trim_end()));
+                    name_label.set_xalign(0.0);
+                    name_label.add_css_class("app-name");
+
+                    let desc_label = Label::new(Some(&format!("{} · {}", constant.description, constant.source)));
+                    desc_label.set_xalign(0.0);
+                    desc_label.add_css_class("app-description");
+
+                    text_box.append(&name_label);
+                    text_box.append(&desc_label);
+                    row_box.append(&text_box);
+                }
+                ResultItem::ClearCalculatorHistory => {
+                    let label = Label::new(Some("🗑 Clear calculator history"));
+                    label.set_xalign(0.0);
+                    row_box.append(&label);
+                }
+                ResultItem::ClipboardEntry(clipboard_entry) => {
+                    // Images show a thumbnail
+                    if let ClipboardContent::Image(png) = &clipboard_entry.content {
+                        if let Ok(texture) = gdk::Texture::from_bytes(&glib::Bytes::from(png)) {
+                            let image = Image::from_paintable(Some(&texture));
+                            image.set_pixel_size(40);
+                            image.set_css_classes(&["app-icon"]);
+                            row_box.append(&image);
+                        }
+                    }
+
+                    let text_box = GtkBox::builder()
+                        .orientation(gtk::Orientation::Vertical)
+                        .spacing(1)
+                        .valign(gtk::Align::Center)
+                        .hexpand(true)
+                        .build();
+
+                    let name_label = Label::new(Some(&clipboard_entry.preview()));
+                    name_label.set_xalign(0.0);
+                    name_label.set_ellipsize(gtk::pango::EllipsizeMode::End);
+                    name_label.add_css_class("app-name");
+
+                    let desc_label = Label::new(Some(&clipboard_entry.description()));
+                    desc_label.set_xalign(0.0);
+                    desc_label.add_css_class("app-description");
+
+                    text_box.append(&name_label);
+                    text_box.append(&desc_label);
+                    row_box.append(&text_box);
+                }
+                ResultItem::ClearClipboardHistory => {
+                    let label = Label::new(Some("🗑 Clear clipboard history (pinned entries stay)"));
+                    label.set_xalign(0.0);
+                    row_box.append(&label);
+                }
                 ResultItem::Settings => {
                     // Create icon
                     let icon_widget = {
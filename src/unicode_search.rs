@@ -0,0 +1,346 @@
+use unicode_general_category::{get_general_category, GeneralCategory};
+
+/// Search over the whole Unicode character database by name or codepoint
+#[derive(Default)]
+pub struct UnicodeSearch {
+    names: Vec<(char, String)>, // Built on the first search, most launches never need it
+}
+
+/// Everything shown for one character in the `u:` inspector
+#[derive(Debug, Clone)]
+pub struct CharInfo {
+    pub character: char,
+    pub name: String,      // "RIGHTWARDS ARROW"
+    pub codepoint: String, // "U+2192"
+    pub block: String,     // "Arrows"
+    pub category: String,  // "Math Symbol (Sm)"
+    pub utf8: String,      // "E2 86 92"
+    pub utf16: String,     // "2192"
+    pub html: String,      // "&rarr;", or "&#x2192;" without a named entity
+}
+
+impl CharInfo {
+    /// Labelled values listed below a single character, each copied on Enter
+    pub fn details(&self) -> Vec<(&'static str, String)> {
+        vec![
+            ("name", self.name.clone()),
+            ("codepoint", self.codepoint.clone()),
+            ("block", self.block.clone()),
+            ("category", self.category.clone()),
+            ("UTF-8", self.utf8.clone()),
+            ("UTF-16", self.utf16.clone()),
+            ("HTML", self.html.clone()),
+        ]
+    }
+}
+
+const MAX_RESULTS: usize = 8;
+
+// Named HTML entities for the characters people most often look up
+const HTML_ENTITIES: &[(&str, char)] = &[
+    ("nbsp", '\u{A0}'),
+    ("amp", '&'),
+    ("lt", '<'),
+    ("gt", '>'),
+    ("quot", '"'),
+    ("apos", '\''),
+    ("iexcl", '¡'),
+    ("cent", '¢'),
+    ("pound", '£'),
+    ("yen", '¥'),
+    ("euro", '€'),
+    ("sect", '§'),
+    ("copy", '©'),
+    ("reg", '®'),
+    ("trade", '™'),
+    ("laquo", '«'),
+    ("raquo", '»'),
+    ("shy", '\u{AD}'),
+    ("deg", '°'),
+    ("plusmn", '±'),
+    ("sup2", '²'),
+    ("sup3", '³'),
+    ("micro", 'µ'),
+    ("para", '¶'),
+    ("middot", '·'),
+    ("frac14", '¼'),
+    ("frac12", '½'),
+    ("frac34", '¾'),
+    ("iquest", '¿'),
+    ("times", '×'),
+    ("divide", '÷'),
+    ("ndash", '–'),
+    ("mdash", '—'),
+    ("lsquo", '‘'),
+    ("rsquo", '’'),
+    ("sbquo", '‚'),
+    ("ldquo", '“'),
+    ("rdquo", '”'),
+    ("bdquo", '„'),
+    ("dagger", '†'),
+    ("Dagger", '‡'),
+    ("bull", '•'),
+    ("hellip", '…'),
+    ("permil", '‰'),
+    ("prime", '′'),
+    ("larr", '←'),
+    ("uarr", '↑'),
+    ("rarr", '→'),
+    ("darr", '↓'),
+    ("harr", '↔'),
+    ("lArr", '⇐'),
+    ("rArr", '⇒'),
+    ("hArr", '⇔'),
+    ("forall", '∀'),
+    ("part", '∂'),
+    ("exist", '∃'),
+    ("empty", '∅'),
+    ("nabla", '∇'),
+    ("isin", '∈'),
+    ("notin", '∉'),
+    ("prod", '∏'),
+    ("sum", '∑'),
+    ("minus", '−'),
+    ("radic", '√'),
+    ("infin", '∞'),
+    ("and", '∧'),
+    ("or", '∨'),
+    ("cap", '∩'),
+    ("cup", '∪'),
+    ("int", '∫'),
+    ("asymp", '≈'),
+    ("ne", '≠'),
+    ("equiv", '≡'),
+    ("le", '≤'),
+    ("ge", '≥'),
+    ("sub", '⊂'),
+    ("sup", '⊃'),
+    ("loz", '◊'),
+    ("spades", '♠'),
+    ("clubs", '♣'),
+    ("hearts", '♥'),
+    ("diams", '♦'),
+];
+
+impl UnicodeSearch {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Characters for a `u:` query: a codepoint ("U+2192", "0x2014", "&mdash;"), pasted
+    /// characters to look up, or words of a name ("greek small alpha")
+    pub fn search(&mut self, query: &str) -> Vec<CharInfo> {
+        let query = query.trim();
+        if query.is_empty() {
+            return Vec::new();
+        }
+
+        if let Some(c) = Self::parse_codepoint(query) {
+            return vec![Self::inspect(c)];
+        }
+
+        // A single character, or anything that isn't plain ASCII, was pasted to be inspected
+        if query.chars().count() == 1 || !query.is_ascii() {
+            let mut characters: Vec<char> = Vec::new();
+            for c in query.chars().filter(|c| !c.is_whitespace()) {
+                if !characters.contains(&c) {
+                    characters.push(c);
+                }
+            }
+            return characters.into_iter().take(MAX_RESULTS).map(Self::inspect).collect();
+        }
+
+        self.search_names(query).into_iter().map(Self::inspect).collect()
+    }
+
+    /// Name, codepoint, block and so on for one character
+    pub fn inspect(c: char) -> CharInfo {
+        let category = get_general_category(c);
+        let utf8: Vec<String> = c.to_string().bytes().map(|b| format!("{:02X}", b)).collect();
+        let mut units = [0u16; 2];
+        let utf16: Vec<String> = c.encode_utf16(&mut units).iter().map(|u| format!("{:04X}", u)).collect();
+        let html = HTML_ENTITIES
+            .iter()
+            .find(|(_, e)| *e == c)
+            .map(|(name, _)| format!("&{};", name))
+            .unwrap_or_else(|| format!("&#x{:X};", c as u32));
+
+        CharInfo {
+            character: c,
+            name: Self::name(c, category),
+            codepoint: format!("U+{:04X}", c as u32),
+            block: unicode_blocks::find_unicode_block(c)
+                .map(|block| block.name().to_string())
+                .unwrap_or_else(|| "No block".to_string()),
+            category: format!("{} ({})", Self::category_name(category), category.abbreviation()),
+            utf8: utf8.join(" "),
+            utf16: utf16.join(" "),
+            html,
+        }
+    }
+
+    /// The character as shown in a result row; marks get a dotted circle to sit on
+    /// and invisible characters their control picture
+    pub fn display(info: &CharInfo) -> String {
+        let c = info.character;
+        match get_general_category(c) {
+            GeneralCategory::NonspacingMark | GeneralCategory::EnclosingMark | GeneralCategory::SpacingMark => {
+                format!("◌{}", c)
+            }
+            GeneralCategory::Control if (c as u32) < 0x20 => {
+                char::from_u32(0x2400 + c as u32).unwrap_or(c).to_string()
+            }
+            GeneralCategory::Control => "␡".to_string(),
+            GeneralCategory::SpaceSeparator
+            | GeneralCategory::LineSeparator
+            | GeneralCategory::ParagraphSeparator
+            | GeneralCategory::Format => "⍽".to_string(),
+            _ => c.to_string(),
+        }
+    }
+
+    fn search_names(&mut self, query: &str) -> Vec<char> {
+        // The exact name always comes first ("em dash", "snowman")
+        let exact = unicode_names2::character(query);
+
+        if self.names.is_empty() {
+            self.names = Self::load_names();
+        }
+
+        let words: Vec<String> = query.split_whitespace().map(|w| w.to_uppercase()).collect();
+        let mut matches: Vec<(char, i32, usize)> = Vec::new();
+        for (c, name) in &self.names {
+            if Some(*c) == exact {
+                continue;
+            }
+            if let Some(score) = Self::score(name, &words) {
+                matches.push((*c, score, name.len()));
+            }
+        }
+
+        // Best word matches first, then shorter names, then lower codepoints
+        matches.sort_by(|a, b| b.1.cmp(&a.1).then(a.2.cmp(&b.2)).then(a.0.cmp(&b.0)));
+        exact
+            .into_iter()
+            .chain(matches.into_iter().map(|(c, _, _)| c))
+            .take(MAX_RESULTS)
+            .collect()
+    }
+
+    /// Every query word must start (or at least occur in) a word of the name;
+    /// names with fewer extra words rank higher
+    fn score(name: &str, words: &[String]) -> Option<i32> {
+        let name_words: Vec<&str> = name.split([' ', '-']).collect();
+        let mut score = 0;
+        for word in words {
+            score += name_words
+                .iter()
+                .map(|n| {
+                    if n == word {
+                        3
+                    } else if n.starts_with(word.as_str()) {
+                        2
+                    } else if n.contains(word.as_str()) {
+                        1
+                    } else {
+                        0
+                    }
+                })
+                .max()
+                .filter(|s| *s > 0)?;
+        }
+        Some(score - name_words.len() as i32)
+    }
+
+    /// All named characters, leaving out ideographs whose names are only their codepoint
+    fn load_names() -> Vec<(char, String)> {
+        (0..=0x10FFFFu32)
+            .filter_map(char::from_u32)
+            .filter_map(|c| Some((c, unicode_names2::name(c)?.to_string())))
+            .filter(|(_, name)| !name.contains("IDEOGRAPH-"))
+            .collect()
+    }
+
+    /// "U+2192", "u+2192", "0x2014", "\u{2014}", "&#8212;", "&#x2014;" or "&mdash;"
+    fn parse_codepoint(query: &str) -> Option<char> {
+        let hex = |text: &str| u32::from_str_radix(text, 16).ok().and_then(char::from_u32);
+
+        if let Some(code) = query.strip_prefix("U+").or_else(|| query.strip_prefix("u+")) {
+            return hex(code);
+        }
+        if let Some(code) = query.strip_prefix("0x").or_else(|| query.strip_prefix("0X")) {
+            return hex(code);
+        }
+        if let Some(code) = query.strip_prefix("\\u") {
+            return hex(code.trim_start_matches('{').trim_end_matches('}'));
+        }
+        let entity = query.strip_prefix('&')?.strip_suffix(';')?;
+        if let Some(number) = entity.strip_prefix('#') {
+            return match number.strip_prefix(['x', 'X']) {
+                Some(code) => hex(code),
+                None => number.parse().ok().and_then(char::from_u32),
+            };
+        }
+        HTML_ENTITIES.iter().find(|(name, _)| *name == entity).map(|(_, c)| *c)
+    }
+
+    /// Characters without a name in the database are described by their category
+    fn name(c: char, category: GeneralCategory) -> String {
+        if let Some(name) = unicode_names2::name(c) {
+            return name.to_string();
+        }
+        match category {
+            GeneralCategory::Control => "<control>",
+            GeneralCategory::PrivateUse => "<private use>",
+            GeneralCategory::Surrogate => "<surrogate>",
+            GeneralCategory::Unassigned => "<unassigned>",
+            _ => "<unnamed>",
+        }
+        .to_string()
+    }
+
+    /// "MathSymbol" becomes "Math Symbol"
+    fn category_name(category: GeneralCategory) -> String {
+        let mut name = String::new();
+        for c in format!("{:?}", category).chars() {
+            if c.is_uppercase() && !name.is_empty() {
+                name.push(' ');
+            }
+            name.push(c);
+        }
+        name
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_codepoint() {
+        for query in ["U+2014", "u+2014", "0x2014", "\\u2014", "\\u{2014}", "&#8212;", "&#x2014;", "&mdash;"] {
+            assert_eq!(UnicodeSearch::parse_codepoint(query), Some('—'), "{}", query);
+        }
+        assert_eq!(UnicodeSearch::parse_codepoint("U+D800"), None);
+        assert_eq!(UnicodeSearch::parse_codepoint("U+110000"), None);
+        assert_eq!(UnicodeSearch::parse_codepoint("&nosuch;"), None);
+        assert_eq!(UnicodeSearch::parse_codepoint("mdash"), None);
+    }
+
+    #[test]
+    fn test_inspect() {
+        let arrow = UnicodeSearch::inspect('→');
+        assert_eq!(arrow.name, "RIGHTWARDS ARROW");
+        assert_eq!(arrow.codepoint, "U+2192");
+        assert_eq!(arrow.block, "Arrows");
+        assert_eq!(arrow.category, "Math Symbol (Sm)");
+        assert_eq!(arrow.utf8, "E2 86 92");
+        assert_eq!(arrow.html, "&rarr;");
+        assert_eq!(UnicodeSearch::inspect('😀').utf16, "D83D DE00");
+        assert_eq!(UnicodeSearch::display(&UnicodeSearch::inspect('\u{301}')), "◌\u{301}");
+
+        let mut search = UnicodeSearch::new();
+        assert_eq!(search.search("U+00E9")[0].character, 'é');
+        assert_eq!(search.search("→é").iter().map(|info| info.character).collect::<String>(), "→é");
+    }
+}
@@ -0,0 +1,309 @@
+use crate::calculator::{Calculation, Calculator, Number, Value};
+use num_traits::ToPrimitive;
+
+pub struct Units;
+
+/// Exponents of the SI base units, in the order of `BASE_UNITS`
+pub type Dimension = [i32; 7];
+
+const DIMENSIONLESS: Dimension = [0; 7];
+const BASE_UNITS: [&str; 7] = ["m", "kg", "s", "A", "K", "mol", "cd"];
+
+const LENGTH: Dimension = [1, 0, 0, 0, 0, 0, 0];
+const MASS: Dimension = [0, 1, 0, 0, 0, 0, 0];
+const TIME: Dimension = [0, 0, 1, 0, 0, 0, 0];
+const CURRENT: Dimension = [0, 0, 0, 1, 0, 0, 0];
+const TEMPERATURE: Dimension = [0, 0, 0, 0, 1, 0, 0];
+const AMOUNT: Dimension = [0, 0, 0, 0, 0, 1, 0];
+const LUMINOUS_INTENSITY: Dimension = [0, 0, 0, 0, 0, 0, 1];
+const AREA: Dimension = [2, 0, 0, 0, 0, 0, 0];
+const VOLUME: Dimension = [3, 0, 0, 0, 0, 0, 0];
+const SPEED: Dimension = [1, 0, -1, 0, 0, 0, 0];
+const FREQUENCY: Dimension = [0, 0, -1, 0, 0, 0, 0];
+const FORCE: Dimension = [1, 1, -2, 0, 0, 0, 0];
+const ENERGY: Dimension = [2, 1, -2, 0, 0, 0, 0];
+const POWER: Dimension = [2, 1, -3, 0, 0, 0, 0];
+const PRESSURE: Dimension = [-1, 1, -2, 0, 0, 0, 0];
+const CHARGE: Dimension = [0, 0, 1, 1, 0, 0, 0];
+const VOLTAGE: Dimension = [2, 1, -3, -1, 0, 0, 0];
+const RESISTANCE: Dimension = [2, 1, -3, -2, 0, 0, 0];
+
+// Name, size in SI base units, dimension, whether SI prefixes apply ("km", "mA", "GeV").
+// "h" is the Planck constant and "min" a function, so hours and minutes are spelled out.
+const UNITS: &[(&str, &str, Dimension, bool)] = &[
+    ("m", "1", LENGTH, true),
+    ("g", "1/1000", MASS, true),
+    ("s", "1", TIME, true),
+    ("A", "1", CURRENT, true),
+    ("K", "1", TEMPERATURE, true),
+    ("mol", "1", AMOUNT, true),
+    ("cd", "1", LUMINOUS_INTENSITY, true),
+    ("N", "1", FORCE, true),
+    ("J", "1", ENERGY, true),
+    ("W", "1", POWER, true),
+    ("Pa", "1", PRESSURE, true),
+    ("Hz", "1", FREQUENCY, true),
+    ("C", "1", CHARGE, true),
+    ("V", "1", VOLTAGE, true),
+    ("ohm", "1", RESISTANCE, true),
+    ("Ω", "1", RESISTANCE, true),
+    ("L", "1/1000", VOLUME, true),
+    ("eV", "1.602176634e-19", ENERGY, true),
+    ("Wh", "3600", ENERGY, true),
+    ("cal", "4.184", ENERGY, true),
+    ("tonne", "1000", MASS, false),
+    ("minute", "60", TIME, false),
+    ("hr", "3600", TIME, false),
+    ("hour", "3600", TIME, false),
+    ("day", "86400", TIME, false),
+    ("week", "604800", TIME, false),
+    ("year", "31557600", TIME, false), // Julian year
+    ("inch", "0.0254", LENGTH, false),
+    ("ft", "0.3048", LENGTH, false),
+    ("yd", "0.9144", LENGTH, false),
+    ("mi", "1609.344", LENGTH, false),
+    ("nmi", "1852", LENGTH, false),
+    ("au", "149597870700", LENGTH, false),
+    ("ly", "9460730472580800", LENGTH, false),
+    ("pc", "30856775814913673", LENGTH, false),
+    ("ha", "10000", AREA, false),
+    ("acre", "4046.8564224", AREA, false),
+    ("gal", "0.003785411784", VOLUME, false),
+    ("lb", "0.45359237", MASS, false),
+    ("oz", "0.028349523125", MASS, false),
+    ("kph", "5/18", SPEED, false),
+    ("kmh", "5/18", SPEED, false),
+    ("mph", "0.44704", SPEED, false),
+    ("knot", "1852/3600", SPEED, false),
+    ("bar", "100000", PRESSURE, true),
+    ("atm", "101325", PRESSURE, false),
+    ("psi", "6894.757293168361", PRESSURE, false),
+];
+
+const PREFIXES: &[(&str, i32)] = &[
+    ("T", 12),
+    ("G", 9),
+    ("M", 6),
+    ("k", 3),
+    ("c", -2),
+    ("m", -3),
+    ("µ", -6),
+    ("μ", -6),
+    ("u", -6),
+    ("n", -9),
+    ("p", -12),
+    ("f", -15),
+];
+
+// Preferred names when showing a result in SI units
+const DERIVED_NAMES: &[(&str, Dimension)] = &[
+    ("N", FORCE),
+    ("J", ENERGY),
+    ("W", POWER),
+    ("Pa", PRESSURE),
+    ("Hz", FREQUENCY),
+    ("C", CHARGE),
+    ("V", VOLTAGE),
+    ("Ω", RESISTANCE),
+];
+
+impl Units {
+    /// The value of a unit name such as "km" or "eV", in SI base units
+    pub(crate) fn lookup(name: &str) -> Option<Value> {
+        if let Some((_, size, dimension, _)) = UNITS.iter().find(|(unit, ..)| *unit == name) {
+            return Some(Self::quantity(Self::parse_size(size), *dimension));
+        }
+
+        PREFIXES.iter().find_map(|(prefix, exponent)| {
+            let unit = name.strip_prefix(prefix)?;
+            let (_, size, dimension, _) = UNITS.iter().find(|(u, _, _, prefixed)| *u == unit && *prefixed)?;
+            let scale = Calculator::apply_binary('^', Number::from_integer(10), Number::from_integer(*exponent as i64)).ok()?;
+            let value = Calculator::apply_binary('*', Self::parse_size(size), scale).ok()?;
+            Some(Self::quantity(value, *dimension))
+        })
+    }
+
+    /// Build a value with units, dropping them when they cancel out (m / km = 0.001)
+    pub(crate) fn quantity(value: Number, dimension: Dimension) -> Value {
+        if dimension == DIMENSIONLESS {
+            Value::Number(value)
+        } else {
+            Value::Quantity(value, dimension)
+        }
+    }
+
+    pub(crate) fn apply_binary(op: char, a: Value, b: Value) -> Result<Value, String> {
+        let (x, dx) = Self::split(a)?;
+        let (y, dy) = Self::split(b)?;
+
+        match op {
+            '+' | '-' | 'm' => {
+                if dx != dy {
+                    return Err(format!("Incompatible units: {} and {}", Self::unit_name(&dx), Self::unit_name(&dy)));
+                }
+                Ok(Self::quantity(Calculator::apply_binary(op, x, y)?, dx))
+            }
+            '*' | '/' => {
+                let sign = if op == '*' { 1 } else { -1 };
+                let mut dimension = dx;
+                for (d, e) in dimension.iter_mut().zip(dy) {
+                    *d += sign * e;
+                }
+                Ok(Self::quantity(Calculator::apply_binary(op, x, y)?, dimension))
+            }
+            '^' => {
+                if dy != DIMENSIONLESS {
+                    return Err("Exponents can't have units".to_string());
+                }
+                let Some(n) = y.as_integer().and_then(|n| n.to_i32()) else {
+                    return Err("Units can only be raised to whole powers".to_string());
+                };
+                let dimension = dx.map(|d| d * n);
+                Ok(Self::quantity(Calculator::apply_binary('^', x, y)?, dimension))
+            }
+            _ => Err("This operator doesn't work with units".to_string()),
+        }
+    }
+
+    pub(crate) fn call_function(name: &str, args: Vec<Value>) -> Result<Value, String> {
+        match (name, args.as_slice()) {
+            ("abs", [Value::Quantity(n, d)]) => Ok(Value::Quantity(Calculator::call_function("abs", vec![n.clone()])?, *d)),
+            ("sqrt", [Value::Quantity(n, d)]) if d.iter().all(|e| e % 2 == 0) => {
+                Ok(Value::Quantity(Calculator::call_function("sqrt", vec![n.clone()])?, d.map(|e| e / 2)))
+            }
+            _ => Err(format!("{}() needs a number without units", name)),
+        }
+    }
+
+    /// Express a value in another unit: "c * 2 s in km" divides by one kilometre
+    pub(crate) fn convert(value: Value, target: Value) -> Result<Number, String> {
+        let (x, dx) = Self::split(value)?;
+        let (y, dy) = Self::split(target)?;
+        if dx != dy {
+            return Err(format!("Cannot convert {} to {}", Self::unit_name(&dx), Self::unit_name(&dy)));
+        }
+        if y.is_zero() {
+            return Err("Division by zero".to_string());
+        }
+        Calculator::apply_binary('/', x, y)
+    }
+
+    /// Add a unit to a formatted number and its alternatives
+    pub(crate) fn attach(calculation: Calculation, unit: &str) -> Calculation {
+        let with_unit = |text: String| if unit.is_empty() { text } else { format!("{} {}", text, unit) };
+        Calculation {
+            value: with_unit(calculation.value),
+            alternatives: calculation
+                .alternatives
+                .into_iter()
+                .filter(|r| r.label != "hex")
+                .map(|mut r| {
+                    r.value = with_unit(r.value);
+                    r
+                })
+                .collect(),
+        }
+    }
+
+    /// SI name for a dimension, e.g. "J", "m/s^2" or "m^3/(kg s^2)"
+    pub fn unit_name(dimension: &Dimension) -> String {
+        if let Some((name, _)) = DERIVED_NAMES.iter().find(|(_, d)| d == dimension) {
+            return name.to_string();
+        }
+
+        let part = |unit: &str, exponent: i32| {
+            if exponent == 1 {
+                unit.to_string()
+            } else {
+                format!("{}^{}", unit, exponent)
+            }
+        };
+        let numerator: Vec<String> = BASE_UNITS
+            .iter()
+            .zip(dimension)
+            .filter(|(_, e)| **e > 0)
+            .map(|(unit, e)| part(unit, *e))
+            .collect();
+        let denominator: Vec<String> = BASE_UNITS
+            .iter()
+            .zip(dimension)
+            .filter(|(_, e)| **e < 0)
+            .map(|(unit, e)| part(unit, -e))
+            .collect();
+
+        let numerator = if numerator.is_empty() { "1".to_string() } else { numerator.join(" ") };
+        match denominator.len() {
+            0 => numerator,
+            1 => format!("{}/{}", numerator, denominator[0]),
+            _ => format!("{}/({})", numerator, denominator.join(" ")),
+        }
+    }
+
+    fn split(value: Value) -> Result<(Number, Dimension), String> {
+        match value {
+            Value::Number(n) => Ok((n, DIMENSIONLESS)),
+            Value::Quantity(n, d) => Ok((n, d)),
+            _ => Err("Units only work with real numbers".to_string()),
+        }
+    }
+
+    /// Exact unit size from the table: "0.0254", "1.602176634e-19" or "5/18"
+    fn parse_size(size: &str) -> Number {
+        let literal = |text: &str| Calculator::parse_literal(text).expect("unit sizes are valid literals");
+        match size.split_once('/') {
+            Some((numer, denom)) => Number::Exact(literal(numer) / literal(denom)),
+            None => Number::Exact(literal(size)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lookup(name: &str) -> Value {
+        Units::lookup(name).unwrap()
+    }
+
+    #[test]
+    fn test_lookup() {
+        assert_eq!(lookup("km"), Value::Quantity(Number::from_integer(1000), LENGTH));
+        assert_eq!(lookup("mg"), Value::Quantity(Units::parse_size("1/1000000"), MASS));
+        assert_eq!(lookup("kmh"), Value::Quantity(Units::parse_size("5/18"), SPEED));
+        // Only SI units take prefixes, and unknown names aren't units
+        assert_eq!(Units::lookup("kmi"), None);
+        assert_eq!(Units::lookup("h"), None);
+        assert_eq!(Units::lookup("xyz"), None);
+    }
+
+    #[test]
+    fn test_arithmetic() {
+        let speed = Units::apply_binary('/', lookup("km"), lookup("hour")).unwrap();
+        assert_eq!(speed, lookup("kmh"));
+        // Units that cancel out leave a plain number
+        assert_eq!(Units::apply_binary('/', lookup("m"), lookup("km")), Ok(Value::Number(Units::parse_size("1/1000"))));
+        let area = Units::apply_binary('^', lookup("m"), Value::Number(Number::from_integer(2))).unwrap();
+        assert_eq!(Units::call_function("sqrt", vec![area]), Ok(lookup("m")));
+
+        assert_eq!(Units::apply_binary('+', lookup("m"), lookup("s")), Err("Incompatible units: m and s".to_string()));
+        assert!(Units::apply_binary('^', lookup("m"), lookup("s")).is_err());
+        assert!(Units::apply_binary('^', lookup("m"), Value::Number(Units::parse_size("1/2"))).is_err());
+        assert!(Units::call_function("sqrt", vec![lookup("m")]).is_err());
+    }
+
+    #[test]
+    fn test_convert() {
+        let marathon = Units::apply_binary('*', Value::Number(Units::parse_size("42.195")), lookup("km")).unwrap();
+        assert_eq!(Units::convert(marathon.clone(), lookup("m")), Ok(Number::from_integer(42195)));
+        assert_eq!(Units::convert(marathon, lookup("s")), Err("Cannot convert m to s".to_string()));
+        assert!(Units::convert(lookup("m"), Value::Quantity(Number::from_integer(0), LENGTH)).is_err());
+    }
+
+    #[test]
+    fn test_unit_name() {
+        assert_eq!(Units::unit_name(&ENERGY), "J");
+        assert_eq!(Units::unit_name(&SPEED), "m/s");
+        assert_eq!(Units::unit_name(&[0, 0, -2, 0, 0, 0, 0]), "1/s^2");
+        assert_eq!(Units::unit_name(&[3, -1, -2, 0, 0, 0, 0]), "m^3/(kg s^2)");
+    }
+}
@@ -1,5 +1,6 @@
 use fuzzy_matcher::FuzzyMatcher;
 use fuzzy_matcher::skim::SkimMatcherV2;
+use std::time::{SystemTime, UNIX_EPOCH};
 
 pub fn fuzzy_match(query: &str, target: &str) -> Option<i64> {
     let matcher = SkimMatcherV2::default();
@@ -10,3 +11,56 @@ pub fn format_app_name(name: &str) -> String {
     name.replace(".desktop", "")
 }
 
+/// How long ago a Unix timestamp was: "just now", "5 min ago", "yesterday"
+pub fn format_age(timestamp: u64) -> String {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    match now.saturating_sub(timestamp) {
+        0..=59 => "just now".to_string(),
+        seconds @ 60..=3599 => format!("{} min ago", seconds / 60),
+        seconds @ 3600..=86399 => format!("{} h ago", seconds / 3600),
+        seconds if seconds < 2 * 86400 => "yesterday".to_string(),
+        seconds => format!("{} days ago", seconds / 86400),
+    }
+}
+
+/// Shell words with quotes and backslashes removed, e.g. `git commit -m 'a b'`
+pub fn split_words(line: &str) -> Vec<String> {
+    let mut words = Vec::new();
+    let mut word = String::new();
+    let mut in_word = false;
+    let mut quote: Option<char> = None;
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        match (quote, c) {
+            (Some(q), c) if c == q => quote = None,
+            // In double quotes a backslash only escapes what would end or expand them
+            (Some('"'), '\\') if !chars.peek().is_some_and(|next| "\"$`\\".contains(*next)) => word.push(c),
+            (Some('"'), '\\') | (None, '\\') => {
+                word.extend(chars.next());
+                in_word = true;
+            }
+            (Some(_), c) => word.push(c),
+            (None, '\'' | '"') => {
+                quote = Some(c);
+                in_word = true;
+            }
+            (None, c) if c.is_whitespace() => {
+                if in_word {
+                    words.push(std::mem::take(&mut word));
+                    in_word = false;
+                }
+            }
+            (None, c) => {
+                word.push(c);
+                in_word = true;
+            }
+        }
+    }
+    if in_word {
+        words.push(word);
+    }
+    words
+}
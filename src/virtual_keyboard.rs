@@ -0,0 +1,232 @@
+use std::fmt::Write as _;
+use rustix::fs::MemfdFlags;
+use std::fs::File;
+use std::io::Write;
+use std::os::fd::AsFd;
+use std::time::Instant;
+use wayland_client::globals::{registry_queue_init, GlobalList, GlobalListContents};
+use wayland_client::protocol::{wl_registry::WlRegistry, wl_seat::WlSeat};
+use wayland_client::{delegate_noop, Connection, Dispatch, EventQueue, QueueHandle};
+use wayland_protocols_misc::zwp_input_method_v2::client::zwp_input_method_manager_v2::ZwpInputMethodManagerV2;
+use wayland_protocols_misc::zwp_input_method_v2::client::zwp_input_method_v2::{self, ZwpInputMethodV2};
+use wayland_protocols_misc::zwp_virtual_keyboard_v1::client::zwp_virtual_keyboard_manager_v1::ZwpVirtualKeyboardManagerV1;
+use wayland_protocols_misc::zwp_virtual_keyboard_v1::client::zwp_virtual_keyboard_v1::ZwpVirtualKeyboardV1;
+
+/// Types text on Wayland without wtype or ydotool: through the compositor's virtual keyboard
+/// (`zwp_virtual_keyboard_v1`) with a keymap made for the text, or as an input method
+/// (`zwp_input_method_v2`) where there is no virtual keyboard
+pub struct VirtualKeyboard;
+
+/// Input method state from the compositor's events
+#[derive(Default)]
+struct State {
+    active: bool,      // A text field has focus
+    done: u32,         // `done` events so far, the serial for committing
+    unavailable: bool, // Another input method is running
+}
+
+// wl_keyboard.keymap_format.xkb_v1
+const XKB_V1: u32 = 1;
+// Real modifier mask of Control in every XKB keymap
+const CONTROL_MASK: u32 = 4;
+// Keys in one generated keymap; longer texts get a new keymap every so many distinct characters
+const KEYS_PER_KEYMAP: usize = 200;
+
+impl VirtualKeyboard {
+    /// Type arbitrary Unicode into the focused window
+    pub fn type_text(text: &str) -> Result<(), Box<dyn std::error::Error>> {
+        let (mut queue, seat, globals) = Self::connect()?;
+        let qh = queue.handle();
+
+        if let Ok(manager) = globals.bind::<ZwpVirtualKeyboardManagerV1, _, _>(&qh, 1..=1, ()) {
+            let keysyms: Vec<String> = text.chars().filter_map(Self::keysym).collect();
+            return Self::send_keys(&manager, &seat, &mut queue, &keysyms, 0);
+        }
+
+        let manager = globals
+            .bind::<ZwpInputMethodManagerV2, _, _>(&qh, 1..=1, ())
+            .map_err(|_| "The compositor has neither a virtual keyboard nor input methods")?;
+        Self::commit_string(&manager, &seat, &mut queue, text)
+    }
+
+    /// Press a key by its keysym name ("Left", "v") a number of times, optionally with Ctrl held
+    pub fn press_key(keysym: &str, count: usize, control: bool) -> Result<(), Box<dyn std::error::Error>> {
+        let (mut queue, seat, globals) = Self::connect()?;
+        let manager = globals.bind::<ZwpVirtualKeyboardManagerV1, _, _>(&queue.handle(), 1..=1, ())?;
+        let keysyms = vec![keysym.to_string(); count];
+        Self::send_keys(&manager, &seat, &mut queue, &keysyms, if control { CONTROL_MASK } else { 0 })
+    }
+
+    /// Event queue (which keeps the connection open), seat and globals of the compositor
+    fn connect() -> Result<(EventQueue<State>, WlSeat, GlobalList), Box<dyn std::error::Error>> {
+        let (globals, queue) = registry_queue_init::<State>(&Connection::connect_to_env()?)?;
+        let seat = globals.bind::<WlSeat, _, _>(&queue.handle(), 1..=1, ())?;
+        Ok((queue, seat, globals))
+    }
+
+    /// Upload a keymap with one key per distinct keysym, then press and release them in order
+    fn send_keys(
+        manager: &ZwpVirtualKeyboardManagerV1,
+        seat: &WlSeat,
+        queue: &mut EventQueue<State>,
+        keysyms: &[String],
+        modifiers: u32,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let keyboard = manager.create_virtual_keyboard(seat, &queue.handle(), ());
+        let start = Instant::now();
+        let mut state = State::default();
+
+        let mut rest = keysyms;
+        while !rest.is_empty() {
+            // The longest run that fits one keymap
+            let mut keys: Vec<&str> = Vec::new();
+            let mut end = 0;
+            for keysym in rest {
+                if !keys.contains(&keysym.as_str()) {
+                    if keys.len() == KEYS_PER_KEYMAP {
+                        break;
+                    }
+                    keys.push(keysym);
+                }
+                end += 1;
+            }
+            let (run, remainder) = rest.split_at(end);
+
+            let (keymap, size) = Self::keymap_file(&Self::keymap(&keys))?;
+            keyboard.keymap(XKB_V1, keymap.as_fd(), size);
+            keyboard.modifiers(modifiers, 0, 0, 0);
+            for keysym in run {
+                // Evdev key codes, the keymap's are 8 higher
+                let key = keys.iter().position(|k| k == keysym).unwrap_or(0) as u32 + 1;
+                let time = start.elapsed().as_millis() as u32;
+                keyboard.key(time, key, 1);
+                keyboard.key(time, key, 0);
+                queue.roundtrip(&mut state)?;
+            }
+            rest = remainder;
+        }
+
+        keyboard.modifiers(0, 0, 0, 0);
+        keyboard.destroy();
+        queue.roundtrip(&mut state)?;
+        Ok(())
+    }
+
+    /// Commit the text to the focused text field as an input method
+    fn commit_string(
+        manager: &ZwpInputMethodManagerV2,
+        seat: &WlSeat,
+        queue: &mut EventQueue<State>,
+        text: &str,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let input_method = manager.get_input_method(seat, &queue.handle(), ());
+        let mut state = State::default();
+        // The first roundtrip creates the input method, the second brings its activation
+        queue.roundtrip(&mut state)?;
+        queue.roundtrip(&mut state)?;
+
+        let result = if state.unavailable {
+            Err("Another input method is running".into())
+        } else if !state.active {
+            Err("No text field has focus".into())
+        } else {
+            input_method.commit_string(text.to_string());
+            input_method.commit(state.done);
+            Ok(())
+        };
+        input_method.destroy();
+        queue.roundtrip(&mut state)?;
+        result
+    }
+
+    /// Keysym name for a character, `None` for control characters that can't be typed
+    fn keysym(c: char) -> Option<String> {
+        match c {
+            '\n' => Some("Return".to_string()),
+            '\t' => Some("Tab".to_string()),
+            c if c.is_control() => None,
+            c => Some(format!("U{:04X}", c as u32)),
+        }
+    }
+
+    /// XKB keymap with key `i` producing `keysyms[i]` on its only level, so no modifier is
+    /// needed for any character
+    fn keymap(keysyms: &[&str]) -> String {
+        let mut keycodes = String::new();
+        let mut symbols = String::new();
+        for (i, keysym) in keysyms.iter().enumerate() {
+            let _ = writeln!(keycodes, "        <K{}> = {};", i + 1, i + 9);
+            let _ = writeln!(symbols, "        key <K{}> {{ [ {} ] }};", i + 1, keysym);
+        }
+
+        format!(
+            "xkb_keymap {{\n    xkb_keycodes \"poppi\" {{\n        minimum = 8;\n        maximum = {};\n{}    }};\n    \
+             xkb_types \"poppi\" {{ include \"complete\" }};\n    \
+             xkb_compatibility \"poppi\" {{ include \"complete\" }};\n    \
+             xkb_symbols \"poppi\" {{\n{}    }};\n}};\n",
+            keysyms.len() + 9,
+            keycodes,
+            symbols
+        )
+    }
+
+    /// The compositor maps the keymap from a file descriptor, an anonymous in-memory file
+    fn keymap_file(keymap: &str) -> Result<(File, u32), Box<dyn std::error::Error>> {
+        let mut file = File::from(rustix::fs::memfd_create("poppi_launcher-keymap", MemfdFlags::CLOEXEC)?);
+
+        // Null terminated, as libxkbcommon expects
+        file.write_all(keymap.as_bytes())?;
+        file.write_all(&[0])?;
+        Ok((file, keymap.len() as u32 + 1))
+    }
+}
+
+impl Dispatch<WlRegistry, GlobalListContents> for State {
+    fn event(_: &mut Self, _: &WlRegistry, _: <WlRegistry as wayland_client::Proxy>::Event, _: &GlobalListContents, _: &Connection, _: &QueueHandle<Self>) {}
+}
+
+impl Dispatch<ZwpInputMethodV2, ()> for State {
+    fn event(state: &mut Self, _: &ZwpInputMethodV2, event: zwp_input_method_v2::Event, _: &(), _: &Connection, _: &QueueHandle<Self>) {
+        match event {
+            zwp_input_method_v2::Event::Activate => state.active = true,
+            zwp_input_method_v2::Event::Deactivate => state.active = false,
+            zwp_input_method_v2::Event::Done => state.done += 1,
+            zwp_input_method_v2::Event::Unavailable => state.unavailable = true,
+            _ => {}
+        }
+    }
+}
+
+delegate_noop!(State: ignore WlSeat);
+delegate_noop!(State: ZwpVirtualKeyboardManagerV1);
+delegate_noop!(State: ZwpVirtualKeyboardV1);
+delegate_noop!(State: ZwpInputMethodManagerV2);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{Read, Seek};
+
+    #[test]
+    fn test_keymap() {
+        let keymap = VirtualKeyboard::keymap(&["U00E9", "Return"]);
+        assert!(keymap.contains("maximum = 11;"));
+        assert!(keymap.contains("<K1> = 9;"));
+        assert!(keymap.contains("<K2> = 10;"));
+        assert!(keymap.contains("key <K1> { [ U00E9 ] };"));
+        assert!(keymap.contains("key <K2> { [ Return ] };"));
+        assert!(keymap.ends_with("};\n"));
+        assert_eq!(VirtualKeyboard::keysym('é').as_deref(), Some("U00E9"));
+        assert_eq!(VirtualKeyboard::keysym('\u{7}'), None);
+    }
+
+    #[test]
+    fn test_keymap_file() {
+        let (mut file, size) = VirtualKeyboard::keymap_file("xkb_keymap {};").unwrap();
+        let mut contents = Vec::new();
+        file.rewind().unwrap();
+        file.read_to_end(&mut contents).unwrap();
+        assert_eq!(contents, b"xkb_keymap {};\0");
+        assert_eq!(size as usize, contents.len());
+    }
+}
@@ -1,3 +1,4 @@
+use crate::command_index::CommandIndex;
 use std::process::Command;
 use fuzzy_matcher::FuzzyMatcher;
 use fuzzy_matcher::skim::SkimMatcherV2;
@@ -30,7 +31,7 @@ impl WindowSwitcher {
         }
         
         // Get XWayland windows with wmctrl (most reliable for GNOME)
-        if Self::has_command("wmctrl") {
+        if CommandIndex::contains("wmctrl") {
             match Self::get_windows_wmctrl_filtered() {
                 Ok(mut windows) => {
                     // Merge with existing windows, avoiding duplicates by title+app
@@ -58,7 +59,7 @@ impl WindowSwitcher {
         }
         
         // Fallback: try xdotool (but filter heavily)
-        if Self::has_command("xdotool") {
+        if CommandIndex::contains("xdotool") {
             match Self::get_windows_xdotool_filtered() {
                 Ok(windows) if !windows.is_empty() => return Ok(windows),
                 Ok(_) => {}, // Empty result
@@ -287,7 +288,7 @@ impl WindowSwitcher {
                 };
                 
                 // Check window type using xprop if available (to filter out DOCK, DESKTOP, etc.)
-                let should_include = if Self::has_command("xprop") {
+                let should_include = if CommandIndex::contains("xprop") {
                     Self::is_normal_window(&window_id)
                 } else {
                     true // If xprop not available, use basic filtering
@@ -522,7 +523,7 @@ impl WindowSwitcher {
         };
         
         // Try wmctrl first (most reliable for GNOME)
-        if Self::has_command("wmctrl") {
+        if CommandIndex::contains("wmctrl") {
             // Try with -i flag first (activate by window ID)
             let output = Command::new("wmctrl")
                 .arg("-i")
@@ -550,7 +551,7 @@ impl WindowSwitcher {
         }
         
         // Fallback: try xdotool (convert hex to decimal)
-        if Self::has_command("xdotool") {
+        if CommandIndex::contains("xdotool") {
             let xdotool_id = if window_id.starts_with("0x") {
                 if let Ok(id_num) = u64::from_str_radix(&window_id[2..], 16) {
                     id_num.to_string()
@@ -574,6 +575,68 @@ impl WindowSwitcher {
         Err(format!("Could not switch to window: {}", window.title).into())
     }
 
+    /// The focused window, with an empty title while none has focus; `None` when it can't be
+    /// found out (Wayland outside Hyprland and Sway, or no xdotool)
+    pub fn focused_window() -> Option<OpenWindow> {
+        if std::env::var("HYPRLAND_INSTANCE_SIGNATURE").is_ok() {
+            let output = Command::new("hyprctl").args(["activewindow", "-j"]).output().ok()?;
+            let window: Value = serde_json::from_slice(&output.stdout).unwrap_or(Value::Null);
+            let field = |key: &str| window.get(key).and_then(|v| v.as_str()).unwrap_or_default().to_string();
+            return Some(OpenWindow {
+                window_id: field("address"),
+                title: field("title"),
+                app_name: field("class"),
+            });
+        }
+        if std::env::var("SWAYSOCK").is_ok() {
+            let output = Command::new("swaymsg").args(["-t", "get_tree"]).output().ok()?;
+            let tree: Value = serde_json::from_slice(&output.stdout).ok()?;
+            return Some(Self::focused_sway_node(&tree).unwrap_or(OpenWindow {
+                window_id: String::new(),
+                title: String::new(),
+                app_name: String::new(),
+            }));
+        }
+
+        let is_wayland = std::env::var("WAYLAND_DISPLAY").is_ok()
+            || std::env::var("XDG_SESSION_TYPE").map(|s| s == "wayland").unwrap_or(false);
+        if is_wayland || !CommandIndex::contains("xdotool") {
+            return None;
+        }
+        let output = Command::new("xdotool")
+            .args(["getactivewindow", "getwindowname", "getwindowclassname"])
+            .output()
+            .ok()?;
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        let mut lines = stdout.lines();
+        Some(OpenWindow {
+            window_id: String::new(),
+            title: lines.next().unwrap_or_default().trim().to_string(),
+            app_name: lines.next().unwrap_or_default().trim().to_string(),
+        })
+    }
+
+    fn focused_sway_node(node: &Value) -> Option<OpenWindow> {
+        if node.get("focused").and_then(|f| f.as_bool()) == Some(true) {
+            // Native Wayland windows have an app_id, XWayland ones an X11 class
+            let app_name = node
+                .get("app_id")
+                .and_then(|a| a.as_str())
+                .or_else(|| node.pointer("/window_properties/class").and_then(|c| c.as_str()))
+                .unwrap_or_default();
+            return Some(OpenWindow {
+                window_id: node.get("id").map(|id| id.to_string()).unwrap_or_default(),
+                title: node.get("name").and_then(|n| n.as_str()).unwrap_or_default().to_string(),
+                app_name: app_name.to_string(),
+            });
+        }
+        ["nodes", "floating_nodes"]
+            .iter()
+            .filter_map(|key| node.get(key).and_then(|n| n.as_array()))
+            .flatten()
+            .find_map(Self::focused_sway_node)
+    }
+
     fn is_normal_window(window_id: &str) -> bool {
         // Check if window is a normal window type (not DOCK, DESKTOP, SPLASH, etc.)
         // Using xprop to check _NET_WM_WINDOW_TYPE
@@ -602,15 +665,5 @@ impl WindowSwitcher {
         // If xprop fails, assume it's normal (fallback to basic filtering)
         true
     }
-    
-    fn has_command(cmd: &str) -> bool {
-        // Use command -v which is faster and more portable than which
-        Command::new("sh")
-            .arg("-c")
-            .arg(&format!("command -v {}", cmd))
-            .output()
-            .map(|o| o.status.success())
-            .unwrap_or(false)
-    }
 }
 